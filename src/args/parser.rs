@@ -3,205 +3,831 @@ extern crate getopts;
 
 // Local files
 use crate::date::date;
+use crate::date::expression;
+use crate::date::time;
+use crate::file::recurrence;
+use crate::output::records;
+use crate::storage::calendar;
+use crate::storage::storage;
 
 // Structures
 
+/// Subcommands of the program, the first argument passed to `crust`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Add,
+    List,
+    Search,
+    Show,
+    Edit,
+    Remove,
+    Open,
+    Import,
+    Export,
+    Migrate,
+    Check,
+}
+
+/// Largest number of days `crust list --days` accepts, about ten years.
+const MAX_DAYS: i32 = 3660;
+
+/// Name, `Command` and description of every subcommand, in the order they are listed in the help.
+const COMMANDS: [(&str, Command, &str); 11] = [
+    ("add", Command::Add, "Add an entry to the agenda"),
+    (
+        "list",
+        Command::List,
+        "List the entries in a range of dates",
+    ),
+    (
+        "search",
+        Command::Search,
+        "Search the entries in a range of dates",
+    ),
+    ("show", Command::Show, "Show the entries of a single date"),
+    (
+        "edit",
+        Command::Edit,
+        "Edit an entry of a date with the system default $EDITOR",
+    ),
+    ("rm", Command::Remove, "Remove an entry of a date"),
+    ("open", Command::Open, "Open the calendar UI"),
+    (
+        "import",
+        Command::Import,
+        "Import events from an iCalendar or JSON file",
+    ),
+    (
+        "export",
+        Command::Export,
+        "Export entries as an iCalendar file",
+    ),
+    (
+        "migrate",
+        Command::Migrate,
+        "Convert the stored entries to another storage backend",
+    ),
+    (
+        "check",
+        Command::Check,
+        "Check the agenda files for problems",
+    ),
+];
+
+/// Errors that stop the argument parsing.
+#[derive(Debug)]
+pub enum ArgumentError {
+    /// Help or version information was requested, the message should be printed to stdout.
+    Help(String),
+    /// The arguments are invalid, the message should be printed to stderr.
+    Invalid(String),
+}
+
 /// Store optional flags passed to the program during argument parsing.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct ProgramFlags {
-    pub open_calendar: bool,
     pub full_day: bool,
-    pub edit: bool,
-    pub remove: bool,
+    pub recurring: bool,
+    pub all: bool,
+    pub fix: bool,
 }
 
-impl Default for ProgramFlags {
-    /// Default values for `ProgramFlags`, all initialised to `false`.
+/// Stores the repetition rule of a recurring entry.
+///
+/// The entry repeats only if `frequency` is not `None`, the remaining fields map onto the
+/// fields of `Recurrence` with the same name.
+#[derive(Clone, Debug)]
+pub struct RepeatArguments {
+    pub frequency: Option<recurrence::Frequency>,
+    pub interval: u32,
+    pub by_day: Vec<recurrence::ByDay>,
+    pub count: Option<u32>,
+    pub until: Option<date::Date>,
+    pub exceptions: Vec<date::Date>,
+}
+
+impl Default for RepeatArguments {
+    /// Default values for `RepeatArguments`, a non-repeating entry.
     fn default() -> Self {
-        ProgramFlags {
-            open_calendar: false,
-            full_day: false,
-            edit: false,
-            remove: false,
+        RepeatArguments {
+            frequency: None,
+            interval: 1,
+            by_day: Vec::new(),
+            count: None,
+            until: None,
+            exceptions: Vec::new(),
         }
     }
 }
 
 /// Stores arguments after argument parsing.
 ///
-/// The `start` and `end` fields are assumed to be of the format `xy:zw`.
+/// The `start` and `end` fields hold the validated times of timed entries. The `from` and `to`
+/// fields hold the date range of the `list`, `search` and `export` commands, `output` the format
+/// in which `list` and `search` write their entries, `query` the text searched for and `file`
+/// holds the file of the `import` command. `format` is the format of the exported or imported
+/// file and `backend` the storage backend `migrate` converts to. `calendars` holds the names
+/// given with `--calendar`, an empty vector selects every calendar for reading and the default
+/// calendar for writing.
 #[derive(Clone, Debug)]
 pub struct ProgramArguments {
+    pub command: Command,
     pub date: date::Date,
     pub flags: ProgramFlags,
-    pub start: String,
-    pub end: String,
+    pub start: Option<time::Time>,
+    pub end: Option<time::Time>,
     pub entry: String,
+    pub repeat: RepeatArguments,
+    pub from: date::Date,
+    pub to: date::Date,
+    pub format: String,
+    pub output: records::OutputFormat,
+    pub query: String,
+    pub index: Option<usize>,
+    pub id: Option<String>,
+    pub file: String,
+    pub backend: storage::Backend,
+    pub calendars: Vec<String>,
 }
 
 impl Default for ProgramArguments {
     /// Default values for `ProgramArguments`
     ///
-    /// Values inherent from `Date` and `ProgramFlags` structs. Remaining String fields
-    /// initialise to empty strings.
+    /// Opens the calendar UI on the current day. Values inherent from `Date`, `ProgramFlags` and
+    /// `RepeatArguments` structs. Remaining String fields initialise to empty strings, except for
+    /// the export format which defaults to `ics`.
     fn default() -> Self {
         ProgramArguments {
+            command: Command::Open,
             date: date::Date::default(),
             flags: ProgramFlags::default(),
-            start: "".to_string(),
-            end: "".to_string(),
+            start: None,
+            end: None,
             entry: "".to_string(),
+            repeat: RepeatArguments::default(),
+            from: date::Date::default(),
+            to: date::Date::default(),
+            format: "ics".to_string(),
+            output: records::OutputFormat::default(),
+            query: "".to_string(),
+            index: None,
+            id: None,
+            file: "".to_string(),
+            backend: storage::Backend::Toml,
+            calendars: Vec::new(),
         }
     }
 }
 
-pub fn parse_arguments() -> ProgramArguments {
-    // Get arguments passed to program.
-    let args: Vec<String> = std::env::args().collect();
+/// Parse the arguments passed to the program, `args[0]` being the program name.
+///
+/// The first argument selects the subcommand, the remaining arguments are parsed with the options
+/// of that subcommand. Without arguments the calendar UI is opened, as `crust open` does.
+/// Requesting help, or passing invalid arguments, results in an `ArgumentError` containing the
+/// message that should be shown to the user.
+pub fn parse_arguments(args: &[String]) -> Result<ProgramArguments, ArgumentError> {
+    let name: &str = match args.get(1) {
+        Some(n) => n,
+        None => "open",
+    };
 
-    // Create `ProgramArguments` structure with default values.
-    let mut return_args: ProgramArguments = ProgramArguments::default();
+    match name {
+        "-h" | "--help" => {
+            return Err(ArgumentError::Help(general_usage()));
+        }
+        "-V" | "--version" => {
+            return Err(ArgumentError::Help(format!(
+                "crust {}",
+                env!("CARGO_PKG_VERSION")
+            )));
+        }
+        "help" => {
+            return match args.get(2) {
+                Some(topic) => match find_command(topic) {
+                    Some(command) => Err(ArgumentError::Help(command_usage(command))),
+                    None => Err(ArgumentError::Invalid(format!(
+                        "Unknown command `{}`.\n\n{}",
+                        topic,
+                        general_usage()
+                    ))),
+                },
+                None => Err(ArgumentError::Help(general_usage())),
+            };
+        }
+        _ => {}
+    }
 
-    // Define the valid options with `getopts`.
-    let mut opts = getopts::Options::new();
-    opts.optopt("d", "day", "Enter day for agenda", "DAY [1-31]");
-    opts.optopt("m", "month", "Enter month for agenda", "MONTH [1-12]");
-    opts.optopt("y", "year", "Enter year for agenda", "YEAR [0-i32 MAX]");
-    opts.optopt(
-        "a",
-        "add",
-        "Add entry to current day, or the date specified by the -d, -m and -y 
-        arguments.",
-        "ENTRY ['STRING']",
-    );
-    opts.optopt("s", "start", "Starting time of entry", "START [xy:wz]");
-    opts.optopt("e", "end", "Ending time of entry", "END [xy:wz]");
-    opts.optflag("o", "open", "Open calendar UI");
-    opts.optflag(
-        "f",
-        "full",
-        "Entry counts for entire current day, or the date specified by the -d, -m 
-        and -y arguments.",
-    );
-    opts.optflag(
-        "",
-        "edit",
-        "Edit file corresponding to current day, or the date specified by the 
-        -d, -m and -y arguments using the system default $EDITOR.",
-    );
-    opts.optflag(
-        "",
-        "remove",
-        "Remove agenda entry for current day, or the date specified by the 
-        -d, -m and -y arguments.",
-    );
+    let command = match find_command(name) {
+        Some(c) => c,
+        None => {
+            return Err(ArgumentError::Invalid(format!(
+                "Unknown command `{}`.\n\n{}",
+                name,
+                general_usage()
+            )));
+        }
+    };
 
-    // Parse the argument options.
-    // Options with arguments.
-    let matches = match opts.parse(&args[1..]) {
+    // Parse the argument options of the subcommand.
+    let opts = command_options(command);
+    let matches = match opts.parse(args.get(2..).unwrap_or_default()) {
         Ok(m) => m,
         Err(e) => {
-            eprintln!("{}", e);
-            panic!("Invalid arguments.");
+            return Err(invalid(command, &e.to_string()));
         }
     };
+    if matches.opt_present("h") {
+        return Err(ArgumentError::Help(command_usage(command)));
+    }
+
+    match parse_command(command, &matches) {
+        Ok(a) => Ok(a),
+        Err(e) => Err(invalid(command, &e)),
+    }
+}
+
+/// Fill in `ProgramArguments` from the options parsed for `command`.
+fn parse_command(command: Command, matches: &getopts::Matches) -> Result<ProgramArguments, String> {
+    // Create `ProgramArguments` structure with default values.
+    let mut return_args: ProgramArguments = ProgramArguments {
+        command,
+        ..ProgramArguments::default()
+    };
+    // Relative dates are resolved with respect to the current day.
+    let today = date::Date::default();
 
-    // Update the program arguments for each present option case.
-    if matches.opt_present("d") {
-        let read_arg = match matches.opt_str("d") {
-            Some(v) => v,
-            None => {
-                panic!("Invalid use of -d argument");
+    // Only `add` takes its entry and `search` its query as free arguments, and `import` a single
+    // file.
+    match command {
+        Command::Add => {
+            return_args.entry = matches.free.join(" ");
+            if return_args.entry.trim().is_empty() {
+                return Err("Missing the TEXT of the entry.".to_string());
             }
-        };
-        return_args.date.day = match read_arg.parse::<i32>() {
-            Ok(v) => v,
-            Err(e) => {
-                eprintln!("{}", e);
-                panic!("Error converting -d argument to integer.");
+        }
+        Command::Search => {
+            return_args.query = matches.free.join(" ");
+            if return_args.query.trim().is_empty() {
+                return Err("Missing the QUERY to search for.".to_string());
             }
-        };
+        }
+        Command::Import => {
+            if matches.free.len() != 1 {
+                return Err("Expected exactly one FILE to import.".to_string());
+            }
+            return_args.file = matches.free[0].clone();
+            // Without --format, the extension of the file decides.
+            return_args.format = match matches.opt_str("format") {
+                Some(format) => format.to_lowercase(),
+                None if return_args.file.to_lowercase().ends_with(".json") => "json".to_string(),
+                None => "ics".to_string(),
+            };
+            if return_args.format != "ics" && return_args.format != "json" {
+                return Err(format!(
+                    "Unsupported format `{}`, expected ics or json.",
+                    return_args.format
+                ));
+            }
+        }
+        _ => {
+            if !matches.free.is_empty() {
+                return Err(format!("Unexpected arguments: {}", matches.free.join(" ")));
+            }
+        }
     }
 
-    if matches.opt_present("m") {
-        let read_arg = match matches.opt_str("m") {
-            Some(v) => v,
-            None => {
-                panic!("Invalid use of -m argument");
-            }
-        };
-        return_args.date.month = match read_arg.parse::<i32>() {
-            Ok(v) => v,
-            Err(e) => {
-                eprintln!("{}", e);
-                panic!("Error converting -m argument to integer.");
+    // Commands that work on a single date take the -d argument.
+    if uses_date(command) {
+        if let Some(d) = parse_date_opt(matches, "date", &today)? {
+            return_args.date = d;
+        }
+    }
+
+    if command != Command::Migrate {
+        return_args.calendars = matches.opt_strs("calendar");
+        for name in return_args.calendars.iter() {
+            if let Err(e) = calendar::validate_name(name) {
+                return Err(format!("Invalid value for --calendar: {}", e));
             }
-        };
+        }
     }
 
-    if matches.opt_present("y") {
-        let read_arg = match matches.opt_str("y") {
-            Some(v) => v,
-            None => {
-                panic!("Invalid use of -y argument");
+    match command {
+        Command::Add => {
+            check_conflicts(matches, &[("full", "start"), ("full", "end")])?;
+            return_args.flags.full_day = matches.opt_present("full");
+            return_args.start = parse_opt::<time::Time>(matches, "start")?;
+            return_args.end = parse_opt::<time::Time>(matches, "end")?;
+            match (return_args.start, return_args.end) {
+                (None, _) if !return_args.flags.full_day => {
+                    return Err(
+                        "Entries need a --start time, or --full for full day entries.".to_string(),
+                    );
+                }
+                (Some(start), Some(end)) if end < start => {
+                    return Err(format!("--end {} lies before --start {}.", end, start));
+                }
+                _ => {}
             }
-        };
-        return_args.date.year = match read_arg.parse::<i32>() {
-            Ok(v) => v,
-            Err(e) => {
-                eprintln!("{}", e);
-                panic!("Error converting -y argument to integer.");
+            parse_repeat(matches, &today, &mut return_args.repeat)?;
+        }
+        Command::Show => {
+            if let Some(output) = parse_opt::<records::OutputFormat>(matches, "output")? {
+                return_args.output = output;
             }
-        };
+        }
+        Command::Edit | Command::Remove => {
+            check_conflicts(matches, &[("index", "id"), ("index", "all"), ("id", "all")])?;
+            if command == Command::Edit {
+                check_conflicts(
+                    matches,
+                    &[
+                        ("recurring", "date"),
+                        ("recurring", "index"),
+                        ("recurring", "id"),
+                        ("recurring", "all"),
+                    ],
+                )?;
+                return_args.flags.recurring = matches.opt_present("recurring");
+            }
+            return_args.flags.all = matches.opt_present("all");
+            return_args.index = parse_opt::<usize>(matches, "index")?;
+            if return_args.index == Some(0) {
+                return Err("--index must be a positive integer.".to_string());
+            }
+            return_args.id = matches.opt_str("id");
+        }
+        Command::Check => {
+            return_args.flags.fix = matches.opt_present("fix");
+        }
+        Command::Migrate => {
+            return_args.backend = match parse_opt::<storage::Backend>(matches, "to")? {
+                Some(backend) => backend,
+                None => {
+                    return Err("Missing the --to backend to migrate to.".to_string());
+                }
+            };
+        }
+        Command::List | Command::Search | Command::Export => {
+            return_args.from = parse_date_opt(matches, "from", &today)?.unwrap_or(today);
+            // Only `list` takes the length of the range in days.
+            let days: Option<i32> = if command == Command::List {
+                check_conflicts(matches, &[("to", "days")])?;
+                parse_opt::<i32>(matches, "days")?
+            } else {
+                None
+            };
+            return_args.to = match parse_date_opt(matches, "to", &today)? {
+                Some(to) => to,
+                None if days.is_some() => {
+                    let days = days.unwrap_or(1);
+                    if !(1..=MAX_DAYS).contains(&days) {
+                        return Err(format!("--days must lie between 1 and {}.", MAX_DAYS));
+                    }
+                    let mut to = return_args.from;
+                    to.add_days(days - 1);
+                    to
+                }
+                None => {
+                    // Listing defaults to a week, searching and exporting to a year.
+                    let mut to = return_args.from;
+                    to.add_days(if command == Command::List { 6 } else { 365 });
+                    to
+                }
+            };
+            if return_args.to < return_args.from {
+                return Err(format!(
+                    "--to {} lies before --from {}.",
+                    return_args.to, return_args.from
+                ));
+            }
+            if command != Command::Export {
+                if let Some(output) = parse_opt::<records::OutputFormat>(matches, "output")? {
+                    return_args.output = output;
+                }
+            } else if let Some(format) = matches.opt_str("format") {
+                if format != "ics" {
+                    return Err(format!("Unsupported format `{}`, expected ics.", format));
+                }
+                return_args.format = format;
+            }
+        }
+        _ => {}
     }
 
-    if matches.opt_present("a") {
-        let read_arg = match matches.opt_str("a") {
-            Some(v) => v,
-            None => {
-                panic!("Invalid use of -a argument");
+    // Returns updated arguments
+    return Ok(return_args);
+}
+
+/// Parse the repetition options of the `add` command into `repeat`.
+fn parse_repeat(
+    matches: &getopts::Matches,
+    today: &date::Date,
+    repeat: &mut RepeatArguments,
+) -> Result<(), String> {
+    repeat.frequency = parse_opt::<recurrence::Frequency>(matches, "repeat")?;
+    if repeat.frequency.is_none() {
+        for name in ["interval", "byday", "count", "until", "except"] {
+            if matches.opt_present(name) {
+                return Err(format!(
+                    "--{} can only be used together with --repeat.",
+                    name
+                ));
             }
-        };
-        return_args.entry = read_arg;
+        }
+        return Ok(());
     }
 
-    if matches.opt_present("s") {
-        let read_arg = match matches.opt_str("s") {
-            Some(v) => v,
-            None => {
-                panic!("Invalid use of -s argument");
+    if let Some(interval) = parse_opt::<u32>(matches, "interval")? {
+        if interval == 0 {
+            return Err("--interval must be a positive integer.".to_string());
+        }
+        repeat.interval = interval;
+    }
+    if let Some(by_day) = matches.opt_str("byday") {
+        for value in by_day.split(',') {
+            repeat.by_day.push(value.parse::<recurrence::ByDay>()?);
+        }
+    }
+    if let Some(frequency) = repeat.frequency {
+        if let Err(e) = recurrence::validate_by_day(frequency, &repeat.by_day) {
+            return Err(format!("Invalid value for --byday: {}", e));
+        }
+    }
+    repeat.count = parse_opt::<u32>(matches, "count")?;
+    if repeat.count == Some(0) {
+        return Err("--count must be a positive integer.".to_string());
+    }
+    repeat.until = parse_date_opt(matches, "until", today)?;
+    if let Some(exceptions) = matches.opt_str("except") {
+        for value in exceptions.split(',') {
+            match expression::parse_date(value, today) {
+                Ok(d) => repeat.exceptions.push(d),
+                Err(e) => return Err(format!("Invalid value for --except: {}", e)),
             }
-        };
-        return_args.start = read_arg;
+        }
     }
+    return Ok(());
+}
+
+/// Parse the value of option `name`, if it is present.
+fn parse_opt<T>(matches: &getopts::Matches, name: &str) -> Result<Option<T>, String>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    match matches.opt_str(name) {
+        Some(value) => match value.parse::<T>() {
+            Ok(v) => Ok(Some(v)),
+            Err(e) => Err(format!("Invalid value `{}` for --{}: {}", value, name, e)),
+        },
+        None => Ok(None),
+    }
+}
 
-    if matches.opt_present("e") {
-        let read_arg = match matches.opt_str("e") {
-            Some(v) => v,
-            None => {
-                panic!("Invalid use of -e argument");
+/// Parse the date expression of option `name` relative to `today`, if it is present.
+fn parse_date_opt(
+    matches: &getopts::Matches,
+    name: &str,
+    today: &date::Date,
+) -> Result<Option<date::Date>, String> {
+    match matches.opt_str(name) {
+        Some(value) => match expression::parse_date(&value, today) {
+            Ok(d) => Ok(Some(d)),
+            Err(e) => Err(format!("Invalid value for --{}: {}", name, e)),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Return an error if both options of any of the `pairs` are present.
+fn check_conflicts(matches: &getopts::Matches, pairs: &[(&str, &str)]) -> Result<(), String> {
+    for (first, second) in pairs.iter() {
+        if matches.opt_present(first) && matches.opt_present(second) {
+            return Err(format!(
+                "--{} cannot be used together with --{}.",
+                first, second
+            ));
+        }
+    }
+    return Ok(());
+}
+
+/// Check if `command` works on a single date given by the -d argument.
+fn uses_date(command: Command) -> bool {
+    matches!(
+        command,
+        Command::Add | Command::Show | Command::Edit | Command::Remove | Command::Open
+    )
+}
+
+/// Find the `Command` with the given name.
+fn find_command(name: &str) -> Option<Command> {
+    return COMMANDS
+        .iter()
+        .find(|(n, _, _)| *n == name)
+        .map(|(_, c, _)| *c);
+}
+
+/// Obtain the name and description of `command`.
+fn command_info(command: Command) -> (&'static str, &'static str) {
+    for (name, c, description) in COMMANDS.iter() {
+        if *c == command {
+            return (name, description);
+        }
+    }
+    return ("", "");
+}
+
+/// Define the valid options of `command` with `getopts`.
+fn command_options(command: Command) -> getopts::Options {
+    let mut opts = getopts::Options::new();
+    opts.optflag("h", "help", "Show this help message");
+
+    if uses_date(command) {
+        opts.optopt("d", "date", "Date of the entry, defaults to today", "DATE");
+    }
+
+    match command {
+        Command::Add | Command::Import => {
+            opts.optopt(
+                "c",
+                "calendar",
+                "Calendar to add the entries to, created if it does not exist, defaults to the \
+                 default calendar",
+                "NAME",
+            );
+        }
+        Command::Edit => {
+            opts.optopt(
+                "c",
+                "calendar",
+                "Only select from the entries of calendar NAME, defaults to all calendars, or to \
+                 the default calendar with --recurring",
+                "NAME",
+            );
+        }
+        Command::Remove => {
+            opts.optopt(
+                "c",
+                "calendar",
+                "Only select from the entries of calendar NAME, defaults to all calendars",
+                "NAME",
+            );
+        }
+        Command::Migrate => {}
+        Command::Check => {
+            opts.optmulti(
+                "c",
+                "calendar",
+                "Only check calendar NAME, can be repeated, defaults to all calendars",
+                "NAME",
+            );
+        }
+        _ => {
+            opts.optmulti(
+                "c",
+                "calendar",
+                "Only show the entries of calendar NAME, can be repeated, defaults to all \
+                 calendars",
+                "NAME",
+            );
+        }
+    }
+
+    match command {
+        Command::Add => {
+            opts.optopt(
+                "s",
+                "start",
+                "Starting time of entry, e.g. 9, 9:30, 9.30 or 9:30pm",
+                "START",
+            );
+            opts.optopt(
+                "e",
+                "end",
+                "Ending time of entry, not before the starting time",
+                "END",
+            );
+            opts.optflag("f", "full", "Entry counts for the entire day");
+            opts.optopt(
+                "r",
+                "repeat",
+                "Repeat the entry, starting from the date",
+                "FREQUENCY [daily|weekly|monthly|yearly]",
+            );
+            opts.optopt(
+                "",
+                "interval",
+                "Repeat the entry every INTERVAL days, weeks, months or years",
+                "INTERVAL [1-u32 MAX]",
+            );
+            opts.optopt(
+                "",
+                "byday",
+                "Weekdays on which the entry repeats, for monthly and yearly entries optionally \
+                 prefixed by which occurrence in the month, e.g. MO,WE or 2TU or -1FR",
+                "BYDAY [MO,TU,...]",
+            );
+            opts.optopt(
+                "",
+                "count",
+                "Stop repeating the entry after COUNT occurrences",
+                "COUNT [1-u32 MAX]",
+            );
+            opts.optopt(
+                "",
+                "until",
+                "Stop repeating the entry after UNTIL",
+                "UNTIL [DATE]",
+            );
+            opts.optopt(
+                "",
+                "except",
+                "Dates on which the repeating entry does not occur",
+                "EXCEPT [DATE,...]",
+            );
+        }
+        Command::Show => {
+            opts.optopt(
+                "o",
+                "output",
+                "Format in which the entries are written, defaults to text",
+                "OUTPUT [text|json|csv|tsv]",
+            );
+        }
+        Command::Edit | Command::Remove => {
+            opts.optopt(
+                "i",
+                "index",
+                "Number of the entry, as shown by `crust show`",
+                "INDEX [1-usize MAX]",
+            );
+            opts.optopt(
+                "",
+                "id",
+                "Id of the entry, as shown by `crust show --output json`",
+                "ID",
+            );
+            if command == Command::Edit {
+                opts.optflag("a", "all", "Edit all entries stored for the date at once");
+                opts.optflag(
+                    "",
+                    "recurring",
+                    "Edit the repeating entries instead of the entries of a date",
+                );
+            } else {
+                opts.optflag("a", "all", "Remove all entries of the date");
             }
-        };
-        return_args.end = read_arg;
+        }
+        Command::List | Command::Search | Command::Export => {
+            opts.optopt(
+                "",
+                "from",
+                "First date of the range, defaults to today",
+                "FROM [DATE]",
+            );
+            if command == Command::List {
+                opts.optopt(
+                    "",
+                    "to",
+                    "Last date of the range, defaults to a week after the first date",
+                    "TO [DATE]",
+                );
+                opts.optopt(
+                    "",
+                    "days",
+                    "Number of days in the range, starting from the first date",
+                    "DAYS [1-3660]",
+                );
+            } else {
+                opts.optopt(
+                    "",
+                    "to",
+                    "Last date of the range, defaults to a year after the first date",
+                    "TO [DATE]",
+                );
+            }
+            if command == Command::Export {
+                opts.optopt("", "format", "Format of the export", "FORMAT [ics]");
+            } else {
+                opts.optopt(
+                    "o",
+                    "output",
+                    "Format in which the entries are written, defaults to text",
+                    "OUTPUT [text|json|csv|tsv]",
+                );
+            }
+        }
+        Command::Import => {
+            opts.optopt(
+                "",
+                "format",
+                "Format of the file, defaults to json for .json files and ics otherwise",
+                "FORMAT [ics|json]",
+            );
+        }
+        Command::Migrate => {
+            opts.optopt(
+                "",
+                "to",
+                "Storage backend to convert the entries to, sqlite needs the `sqlite` feature",
+                "BACKEND [toml|sqlite]",
+            );
+        }
+        Command::Check => {
+            opts.optflag(
+                "",
+                "fix",
+                "Repair the problems that can be repaired without losing entries",
+            );
+        }
+        _ => {}
     }
+    return opts;
+}
 
-    // Options that are flags without arguments.
-    if matches.opt_present("o") {
-        return_args.flags.open_calendar = true;
+/// Obtain the help message of `command`.
+pub fn command_usage(command: Command) -> String {
+    let (name, description) = command_info(command);
+    let arguments = match command {
+        Command::Add => " TEXT",
+        Command::Search => " QUERY",
+        Command::Import => " FILE",
+        _ => "",
+    };
+    let brief = format!(
+        "{}.\n\nUsage: crust {}{} [options]",
+        description, name, arguments
+    );
+    let mut usage = command_options(command).usage(&brief);
+    if uses_date(command) || matches!(command, Command::List | Command::Search | Command::Export) {
+        usage.push_str(&format!("\n{}", expression::DATE_FORMATS));
     }
+    return usage;
+}
 
-    if matches.opt_present("f") {
-        return_args.flags.full_day = true;
+/// Obtain the help message listing all commands.
+pub fn general_usage() -> String {
+    let mut usage = String::from(
+        "Crust: calendar CLI in Rust.\n\nUsage: crust [COMMAND] [options]\n\nCommands:\n",
+    );
+    for (name, _, description) in COMMANDS.iter() {
+        usage.push_str(&format!("    {:<9}{}\n", name, description));
     }
+    usage.push_str(
+        "\nWithout a command the calendar UI is opened.\n\
+         Run `crust help COMMAND` or `crust COMMAND --help` for the options of a command.",
+    );
+    return usage;
+}
+
+/// Create the `ArgumentError` for an invalid use of `command`.
+fn invalid(command: Command, message: &str) -> ArgumentError {
+    let (name, _) = command_info(command);
+    return ArgumentError::Invalid(format!(
+        "{}\nRun `crust {} --help` for more information.",
+        message, name
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    if matches.opt_present("edit") {
-        return_args.flags.edit = true;
+    fn arguments(args: &[&str]) -> Result<ProgramArguments, ArgumentError> {
+        let mut all = vec!["crust".to_string()];
+        all.extend(args.iter().map(|a| a.to_string()));
+        return parse_arguments(&all);
     }
 
-    if matches.opt_present("remove") {
-        return_args.flags.remove = true;
+    #[test]
+    fn no_command_opens_the_calendar() {
+        match arguments(&[]) {
+            Ok(a) => assert_eq!(a.command, Command::Open),
+            Err(_) => panic!("`crust` without arguments is valid"),
+        }
     }
 
-    // Returns updated arguments
-    return return_args;
+    #[test]
+    fn list_days_is_bounded() {
+        let a = match arguments(&["list", "--from", "01-01-2026", "--days", "3"]) {
+            Ok(a) => a,
+            Err(_) => panic!("--days 3 is valid"),
+        };
+        assert_eq!(
+            a.to,
+            date::Date {
+                day: 3,
+                month: 1,
+                year: 2026
+            }
+        );
+        for days in ["0", "-1", "3661", "2147483647"] {
+            assert!(matches!(
+                arguments(&["list", "--days", days]),
+                Err(ArgumentError::Invalid(_))
+            ));
+        }
+    }
 }
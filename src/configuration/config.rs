@@ -1,11 +1,12 @@
-// TODO: Add a way to search for $HOME/.config/crust/crust.toml for user defined configuration
-// instead of the default, along with a way to use that user defined configuration.
-
-// TODO: Restructure the `Config` structure in a toml deserializable way as we did with the `Agenda` struct.
-
 // External crates
+extern crate dirs;
 extern crate ratatui;
+extern crate serde;
+extern crate toml;
 use ratatui::prelude::*;
+use serde::{Deserialize, Deserializer};
+use std::io::Read;
+use std::str::FromStr;
 
 // Rose Pine Moon colors
 const OVERLAY: Color = Color::Rgb(57, 53, 82);
@@ -18,30 +19,123 @@ const FOAM: Color = Color::Rgb(156, 207, 216);
 const IRIS: Color = Color::Rgb(196, 167, 231);
 const GOLD_DARK: Color = Color::Rgb(151, 92, 10);
 
-/// Color configuration for the UI elements.
-#[derive(Debug, Clone)]
+/// Color, key and layout configuration for the UI elements.
+///
+/// The structure is deserialized from `$HOME/.config/crust/crust.toml`. Every field that is not
+/// present in the file is filled in by the `Default` implementation, so a configuration file only
+/// needs to contain the values that differ from the defaults. Unknown keys are rejected so typos
+/// are reported instead of silently ignored.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
+    #[serde(deserialize_with = "deserialize_color")]
     pub calendar_title: Color,
+    #[serde(deserialize_with = "deserialize_color")]
     pub calendar_box: Color,
+    #[serde(deserialize_with = "deserialize_color")]
     pub calendar_month_title: Color,
+    #[serde(deserialize_with = "deserialize_color")]
     pub calendar_month_box: Color,
+    #[serde(deserialize_with = "deserialize_color")]
     pub calendar_days_of_week: Color,
+    #[serde(deserialize_with = "deserialize_color")]
     pub calendar_days_of_week_bg: Color,
+    #[serde(deserialize_with = "deserialize_color")]
     pub calendar_day: Color,
+    #[serde(deserialize_with = "deserialize_color")]
     pub calendar_day_bg: Color,
+    #[serde(deserialize_with = "deserialize_color")]
     pub calendar_day_with_entry: Color,
+    #[serde(deserialize_with = "deserialize_color")]
     pub calendar_day_with_entry_bg: Color,
+    #[serde(deserialize_with = "deserialize_color")]
     pub calendar_day_selected: Color,
+    #[serde(deserialize_with = "deserialize_color")]
     pub calendar_day_selected_bg: Color,
+    #[serde(deserialize_with = "deserialize_color")]
     pub agenda_title: Color,
+    #[serde(deserialize_with = "deserialize_color")]
     pub agenda_box: Color,
+    #[serde(deserialize_with = "deserialize_color")]
     pub agenda_entry_title: Color,
+    #[serde(deserialize_with = "deserialize_color")]
     pub agenda_entry_box: Color,
+    #[serde(deserialize_with = "deserialize_color")]
     pub agenda_entry_full_day_event: Color,
+    #[serde(deserialize_with = "deserialize_color")]
     pub agenda_entry_timed_event: Color,
-    pub key_quit: char,
-    pub key_next: char,
-    pub key_previous: char,
+    /// Key sequences of the UI. Every binding is a sequence of keys, such as `"gg"`, or a list of
+    /// sequences that all do the same. See `validate_keys` for the sequences that are allowed.
+    #[serde(deserialize_with = "deserialize_keys")]
+    pub key_quit: Vec<String>,
+    #[serde(deserialize_with = "deserialize_keys")]
+    pub key_next: Vec<String>,
+    #[serde(deserialize_with = "deserialize_keys")]
+    pub key_previous: Vec<String>,
+    #[serde(deserialize_with = "deserialize_keys")]
+    pub key_next_week: Vec<String>,
+    #[serde(deserialize_with = "deserialize_keys")]
+    pub key_previous_week: Vec<String>,
+    #[serde(deserialize_with = "deserialize_keys")]
+    pub key_next_month: Vec<String>,
+    #[serde(deserialize_with = "deserialize_keys")]
+    pub key_previous_month: Vec<String>,
+    #[serde(deserialize_with = "deserialize_keys")]
+    pub key_next_year: Vec<String>,
+    #[serde(deserialize_with = "deserialize_keys")]
+    pub key_previous_year: Vec<String>,
+    /// Keys that move the UI back to the current day.
+    #[serde(deserialize_with = "deserialize_keys")]
+    pub key_today: Vec<String>,
+    /// Keys that move the UI to the next day with an entry in the shown calendars.
+    #[serde(deserialize_with = "deserialize_keys")]
+    pub key_next_entry: Vec<String>,
+    /// Keys that move the UI to the previous day with an entry in the shown calendars.
+    #[serde(deserialize_with = "deserialize_keys")]
+    pub key_previous_entry: Vec<String>,
+    /// Keys that move the UI to the first day of the selected month.
+    #[serde(deserialize_with = "deserialize_keys")]
+    pub key_first_day: Vec<String>,
+    /// Keys that move the UI to the last day of the selected month.
+    #[serde(deserialize_with = "deserialize_keys")]
+    pub key_last_day: Vec<String>,
+    /// Keys that open the prompt in which a date expression is typed to go to that date.
+    #[serde(deserialize_with = "deserialize_keys")]
+    pub key_go_to_date: Vec<String>,
+    /// Keys that open the form to add an event to the selected date.
+    #[serde(deserialize_with = "deserialize_keys")]
+    pub key_add: Vec<String>,
+    /// Keys that open the form to edit the entry selected in the agenda.
+    #[serde(deserialize_with = "deserialize_keys")]
+    pub key_edit: Vec<String>,
+    /// Keys that delete the entry selected in the agenda, after confirmation.
+    #[serde(deserialize_with = "deserialize_keys")]
+    pub key_delete: Vec<String>,
+    /// Minimum amount of terminal columns needed for the side-by-side layout. Narrower terminals
+    /// use the restricted vertical layout.
+    pub layout_min_columns: u16,
+    /// Percentage of the terminal width used by the calendar in the side-by-side layout, the
+    /// agenda takes up the remaining width.
+    pub layout_calendar_percentage: u16,
+    /// Directory in which the agenda entries are stored, see `storage::data_dir`. A leading `~`
+    /// stands for the home directory.
+    pub data_dir: Option<std::path::PathBuf>,
+    /// Color and icon of the named calendars, by name. Calendars without an entry use the agenda
+    /// colors and the default icon.
+    pub calendars: std::collections::BTreeMap<String, CalendarStyle>,
+}
+
+/// Color and icon of the entries of a single calendar.
+///
+/// Read from a `[calendars.NAME]` table of the configuration, both fields are optional.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CalendarStyle {
+    /// Color of the entries, replacing both the full day and the timed event colors.
+    #[serde(deserialize_with = "deserialize_optional_color")]
+    pub color: Option<Color>,
+    /// Icon shown in front of the entries.
+    pub icon: Option<String>,
 }
 
 impl Default for Config {
@@ -66,9 +160,349 @@ impl Default for Config {
             agenda_entry_box: PINE,
             agenda_entry_full_day_event: PINE,
             agenda_entry_timed_event: IRIS,
-            key_quit: 'q',
-            key_next: 'n',
-            key_previous: 'p',
+            key_quit: keys(&["q"]),
+            // Besides the mnemonic keys, the calendar grid can be moved over with the vim keys.
+            key_next: keys(&["n", "l"]),
+            key_previous: keys(&["p", "h"]),
+            key_next_week: keys(&["w", "j"]),
+            key_previous_week: keys(&["b", "k"]),
+            key_next_month: keys(&["m", "L"]),
+            key_previous_month: keys(&["M", "H"]),
+            key_next_year: keys(&["y"]),
+            key_previous_year: keys(&["Y"]),
+            key_today: keys(&["t"]),
+            key_next_entry: keys(&["]"]),
+            key_previous_entry: keys(&["["]),
+            key_first_day: keys(&["gg"]),
+            key_last_day: keys(&["G"]),
+            key_go_to_date: keys(&["gd"]),
+            key_add: keys(&["a"]),
+            key_edit: keys(&["e"]),
+            key_delete: keys(&["d"]),
+            layout_min_columns: 80,
+            layout_calendar_percentage: 40,
+            data_dir: None,
+            calendars: std::collections::BTreeMap::new(),
+        }
+    }
+}
+
+/// Errors that can occur while loading the user configuration.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The configuration file exists but could not be read.
+    Read(std::path::PathBuf, std::io::Error),
+    /// The configuration file is not valid TOML or contains invalid keys or values.
+    Parse(std::path::PathBuf, toml::de::Error),
+    /// The configuration file parsed, but a value lies outside its allowed range.
+    Invalid(std::path::PathBuf, String),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Read(path, e) => {
+                write!(f, "Error reading configuration {}: {}", path.display(), e)
+            }
+            ConfigError::Parse(path, e) => {
+                write!(f, "Error in configuration {}:\n{}", path.display(), e)
+            }
+            ConfigError::Invalid(path, msg) => {
+                write!(f, "Error in configuration {}: {}", path.display(), msg)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Obtain the color of the entries of `kind`, see `records::KIND_FULL_DAY`, in the calendar
+    /// `name`.
+    pub fn entry_color(&self, name: &str, kind: &str) -> Color {
+        return match self.calendars.get(name).and_then(|style| style.color) {
+            Some(color) => color,
+            None if kind == crate::output::records::KIND_FULL_DAY => {
+                self.agenda_entry_full_day_event
+            }
+            None => self.agenda_entry_timed_event,
+        };
+    }
+
+    /// Obtain the icon of the entries in the calendar `name`, if one is configured.
+    pub fn calendar_icon(&self, name: &str) -> Option<&str> {
+        return self
+            .calendars
+            .get(name)
+            .and_then(|style| style.icon.as_deref());
+    }
+
+    /// Obtain the name and key sequences of every key binding of the UI.
+    pub fn key_bindings(&self) -> [(&'static str, &[String]); 18] {
+        return [
+            ("key_quit", &self.key_quit),
+            ("key_next", &self.key_next),
+            ("key_previous", &self.key_previous),
+            ("key_next_week", &self.key_next_week),
+            ("key_previous_week", &self.key_previous_week),
+            ("key_next_month", &self.key_next_month),
+            ("key_previous_month", &self.key_previous_month),
+            ("key_next_year", &self.key_next_year),
+            ("key_previous_year", &self.key_previous_year),
+            ("key_today", &self.key_today),
+            ("key_next_entry", &self.key_next_entry),
+            ("key_previous_entry", &self.key_previous_entry),
+            ("key_first_day", &self.key_first_day),
+            ("key_last_day", &self.key_last_day),
+            ("key_go_to_date", &self.key_go_to_date),
+            ("key_add", &self.key_add),
+            ("key_edit", &self.key_edit),
+            ("key_delete", &self.key_delete),
+        ];
+    }
+
+    /// Check that every key sequence can be typed unambiguously.
+    ///
+    /// Sequences may not be empty or start with the keys `1` to `9`, which show and hide the
+    /// calendars. No sequence may be bound twice or be the start of another one, e.g. `g` and
+    /// `gg`, as the longer sequence could then never be typed.
+    fn validate_keys(&self) -> Result<(), String> {
+        let sequences: Vec<(&str, &String)> = self
+            .key_bindings()
+            .into_iter()
+            .flat_map(|(name, seqs)| seqs.iter().map(move |seq| (name, seq)))
+            .collect();
+        for (i, (name, seq)) in sequences.iter().enumerate() {
+            match seq.chars().next() {
+                None => return Err(format!("{} contains an empty key sequence", name)),
+                Some(c) if c.is_ascii_digit() && c != '0' => {
+                    return Err(format!(
+                        "{} cannot start with `{}`, the keys 1 to 9 show and hide the calendars",
+                        name, c
+                    ));
+                }
+                Some(_) => {}
+            }
+            for (other, other_seq) in sequences[..i].iter() {
+                if other_seq == seq {
+                    return Err(format!(
+                        "{} and {} are both bound to `{}`",
+                        other, name, seq
+                    ));
+                }
+                if other_seq.starts_with(seq.as_str()) || seq.starts_with(other_seq.as_str()) {
+                    return Err(format!(
+                        "{} `{}` and {} `{}` cannot both be bound, one starts the other",
+                        other, other_seq, name, seq
+                    ));
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    /// Load the user configuration from `$HOME/.config/crust/crust.toml`.
+    ///
+    /// If the configuration directory cannot be determined, or the file does not exist, the
+    /// default configuration is returned.
+    pub fn load() -> Result<Self, ConfigError> {
+        match config_path() {
+            Some(path) if path.exists() => Config::from_file(&path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    /// Load the configuration stored in the .toml file at `path`.
+    ///
+    /// Values present in the file take precedence over the defaults. Errors contain the
+    /// offending key together with the line and column in the file.
+    pub fn from_file(path: &std::path::Path) -> Result<Self, ConfigError> {
+        let mut file = match std::fs::File::open(path) {
+            Ok(f) => f,
+            Err(e) => {
+                return Err(ConfigError::Read(path.to_path_buf(), e));
+            }
+        };
+        let mut contents = String::new();
+        if let Err(e) = file.read_to_string(&mut contents) {
+            return Err(ConfigError::Read(path.to_path_buf(), e));
+        }
+
+        let conf: Config = match toml::from_str(&contents) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(ConfigError::Parse(path.to_path_buf(), e));
+            }
+        };
+
+        for name in conf.calendars.keys() {
+            if let Err(e) = crate::storage::calendar::validate_name(name) {
+                return Err(ConfigError::Invalid(
+                    path.to_path_buf(),
+                    format!("calendars: {}", e),
+                ));
+            }
+        }
+        if let Err(e) = conf.validate_keys() {
+            return Err(ConfigError::Invalid(path.to_path_buf(), e));
+        }
+        // Percentages outside of this range leave no room for either the calendar or the agenda.
+        if conf.layout_calendar_percentage == 0 || conf.layout_calendar_percentage >= 100 {
+            return Err(ConfigError::Invalid(
+                path.to_path_buf(),
+                format!(
+                    "layout_calendar_percentage must lie between 1 and 99, got {}",
+                    conf.layout_calendar_percentage
+                ),
+            ));
+        }
+        return Ok(conf);
+    }
+}
+
+/// Obtain the $HOME/.config/crust/crust.toml path.
+pub fn config_path() -> Option<std::path::PathBuf> {
+    let mut filedir = dirs::config_dir()?;
+    filedir.push("crust");
+    filedir.push("crust.toml");
+    return Some(filedir);
+}
+
+/// Obtain the key sequences `seqs` as owned strings.
+fn keys(seqs: &[&str]) -> Vec<String> {
+    return seqs.iter().map(|seq| seq.to_string()).collect();
+}
+
+/// Deserialize the key sequences of a binding from a single sequence such as `"gg"` or a list of
+/// sequences such as `["n", "l"]`.
+fn deserialize_keys<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Keys {
+        One(String),
+        Many(Vec<String>),
+    }
+    return match Keys::deserialize(deserializer) {
+        Ok(Keys::One(seq)) => Ok(vec![seq]),
+        Ok(Keys::Many(seqs)) => Ok(seqs),
+        Err(_) => Err(serde::de::Error::custom(
+            "invalid keys, expected a key sequence like \"gg\" or a list like [\"n\", \"l\"]",
+        )),
+    };
+}
+
+/// Deserialize an optional color, see `deserialize_color`.
+fn deserialize_optional_color<'de, D>(deserializer: D) -> Result<Option<Color>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    return deserialize_color(deserializer).map(Some);
+}
+
+/// Deserialize a color from a hex string such as `"#3e8fb0"`, a named color such as
+/// `"lightblue"` or an ANSI color index such as `"42"`.
+fn deserialize_color<'de, D>(deserializer: D) -> Result<Color, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    match Color::from_str(&value) {
+        Ok(c) => Ok(c),
+        Err(_) => Err(serde::de::Error::custom(format!(
+            "invalid color `{}`, expected a hex string like \"#3e8fb0\" or a named color like \"lightblue\"",
+            value
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Load the configuration `contents` from a temporary file.
+    fn load(test: &str, contents: &str) -> Result<Config, ConfigError> {
+        let mut path = std::env::temp_dir();
+        path.push(format!("crust-config-{}-{}.toml", test, std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        let result = Config::from_file(&path);
+        let _ = std::fs::remove_file(&path);
+        return result;
+    }
+
+    #[test]
+    fn missing_values_keep_their_default() {
+        let conf = load("merge", "calendar_title = \"#ff0000\"\nkey_quit = \"x\"\n").unwrap();
+        let default = Config::default();
+        assert_eq!(conf.calendar_title, Color::Rgb(255, 0, 0));
+        assert_eq!(conf.key_quit, vec!["x"]);
+        assert_eq!(conf.calendar_box, default.calendar_box);
+        assert_eq!(conf.key_next, default.key_next);
+        assert_eq!(
+            conf.layout_calendar_percentage,
+            default.layout_calendar_percentage
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(matches!(
+            load("unknown", "calender_title = \"#ff0000\"\n"),
+            Err(ConfigError::Parse(_, _))
+        ));
+        assert!(matches!(
+            load("unknown-style", "[calendars.work]\ncolour = \"red\"\n"),
+            Err(ConfigError::Parse(_, _))
+        ));
+    }
+
+    #[test]
+    fn colors_are_hex_names_or_indices() {
+        let conf = load(
+            "colors",
+            "calendar_title = \"#3e8fb0\"\ncalendar_box = \"lightblue\"\n\
+             calendar_day = \"42\"\n[calendars.work]\ncolor = \"red\"\n",
+        )
+        .unwrap();
+        assert_eq!(conf.calendar_title, Color::Rgb(62, 143, 176));
+        assert_eq!(conf.calendar_box, Color::LightBlue);
+        assert_eq!(conf.calendar_day, Color::Indexed(42));
+        assert_eq!(conf.entry_color("work", "timed"), Color::Red);
+        assert!(matches!(
+            load("bad-color", "calendar_title = \"#12345\"\n"),
+            Err(ConfigError::Parse(_, _))
+        ));
+    }
+
+    #[test]
+    fn key_sequences_are_validated() {
+        let conf = load("keys", "key_quit = [\"q\", \"ZZ\"]\n").unwrap();
+        assert_eq!(conf.key_quit, vec!["q", "ZZ"]);
+        for contents in [
+            "key_quit = \"\"\n",
+            "key_quit = \"1\"\n",
+            "key_quit = \"n\"\n",
+            "key_quit = \"g\"\n",
+        ] {
+            assert!(
+                matches!(load("bad-keys", contents), Err(ConfigError::Invalid(_, _))),
+                "{}",
+                contents
+            );
+        }
+        assert!(Config::default().validate_keys().is_ok());
+    }
+
+    #[test]
+    fn calendar_percentage_lies_between_1_and_99() {
+        for (value, valid) in [(0, false), (1, true), (99, true), (100, false)] {
+            let result = load(
+                "percentage",
+                &format!("layout_calendar_percentage = {}\n", value),
+            );
+            assert_eq!(result.is_ok(), valid, "{}", value);
         }
     }
 }
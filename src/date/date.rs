@@ -1,9 +1,8 @@
 // External crates
 extern crate chrono;
-extern crate dirs;
+extern crate serde;
 
-// Local files
-use crate::file;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 // Constants
 pub const MONTHS: [&str; 12] = [
@@ -187,12 +186,14 @@ impl Date {
         if self.day == 1 {
             // Check if the current month is January, if so wrap to December of the previous year.
             if self.month == 1 {
-                self.day = 31;
                 self.month = 12;
                 self.year -= 1;
             }
-            // Otherwise, go the last day of the previous month.
-            self.month -= 1; // This mutates the month so max_day() should use the mutated month.
+            // Otherwise, go the previous month.
+            else {
+                self.month -= 1;
+            }
+            // Go to the last day of the new month, max_day() uses the mutated month.
             self.day = self.max_day();
         }
         // Otherwise, simply decrease the day by one.
@@ -226,6 +227,18 @@ impl Date {
         // start.
     }
 
+    /// Add a number of `months` to the `Date`.
+    ///
+    /// The amount of months can be positive or negative. If the day does not exist in the
+    /// resulting month, e.g. when adding a month to January 31st, the last day of that month is
+    /// used instead.
+    pub fn add_months(&mut self, months: i32) {
+        let zero_based_month = self.year * 12 + (self.month - 1) + months;
+        self.year = zero_based_month.div_euclid(12);
+        self.month = zero_based_month.rem_euclid(12) + 1;
+        self.day = self.day.min(self.max_day());
+    }
+
     /// Get new instance of `Date` with the first day of the next month.
     ///
     /// Function is useful when we need to know the next month, but don't care about the day.
@@ -246,42 +259,33 @@ impl Date {
         return new_date;
     }
 
-    /// Obtain $HOME/.cache/crust/dd-mm-yyyy.toml directory from `Date`.
-    pub fn to_filepath(&self) -> std::path::PathBuf {
-        // Get the $HOME/.cache/ path.
-        let mut filedir: std::path::PathBuf =
-            dirs::cache_dir().expect("Could not obtain $HOME/.cache");
-
-        // Change to the $HOME/.cache/crust/ path.
-        filedir.push("crust");
-
-        // Get the appropriate day format.
-        let day_str: String = {
-            if self.day < 10 {
-                format!("0{}", self.day)
-            } else {
-                self.day.to_string()
-            }
-        };
-
-        // Get the appropriate month format.
-        let month_str: String = {
-            if self.month < 10 {
-                format!("0{}", self.month)
-            } else {
-                self.month.to_string()
-            }
-        };
-
-        // Get appropriate year format
-        let year_str: String = self.year.to_string();
+    /// Obtain the signed number of days from `Date` to the `other` date.
+    ///
+    /// The result is positive if `other` lies after `Date`, negative if it lies before and zero
+    /// if both dates are equal. If either date cannot be represented, zero is returned.
+    pub fn days_until(&self, other: &Date) -> i32 {
+        match (self.to_naive_date(), other.to_naive_date()) {
+            (Some(f), Some(t)) => t.signed_duration_since(f).num_days() as i32,
+            _ => 0,
+        }
+    }
 
-        // Format into the filename
-        let filename: String = format!("{}-{}-{}.toml", day_str, month_str, year_str);
+    /// Convert `Date` into a `chrono::NaiveDate`, returning `None` for impossible dates.
+    pub fn to_naive_date(&self) -> Option<chrono::NaiveDate> {
+        if self.month < 1 || self.day < 1 {
+            return None;
+        }
+        return chrono::NaiveDate::from_ymd_opt(self.year, self.month as u32, self.day as u32);
+    }
 
-        // Add to filepath and return it.
-        filedir.push(filename);
-        return filedir;
+    /// Convert a `chrono::NaiveDate` into a `Date`.
+    pub fn from_naive_date(naive: &chrono::NaiveDate) -> Self {
+        use chrono::Datelike;
+        Date {
+            day: naive.day() as i32,
+            month: naive.month() as i32,
+            year: naive.year(),
+        }
     }
 
     /// Obtain string containing name of month from `Date`.
@@ -346,24 +350,6 @@ impl Date {
         );
         return title;
     }
-
-    /// Obtain `Agenda` structure from `Date` if the corresponding file in $HOME/.cache/crust/
-    /// exists.
-    ///
-    /// If the dd-mm-yyyy.toml file corresponding to `Date` exists in the $HOME/.cache/crust/,
-    /// its contents are parsed into the `Agenda` structure. If it does not exist, we return
-    /// `None`.
-    pub fn get_agenda(&self) -> Option<file::parser::Agenda> {
-        // Get the file directory.
-        let mut filedir: std::path::PathBuf = self.to_filepath();
-
-        // Check if file exists, return `None` if not.
-        if filedir.exists() {
-            return file::parser::parse_agenda_toml(&mut filedir);
-        } else {
-            return None;
-        }
-    }
 }
 
 impl PartialEq for Date {
@@ -442,3 +428,121 @@ impl Default for Date {
         Date { day, month, year }
     }
 }
+
+impl std::fmt::Display for Date {
+    /// Format `Date` as `dd-mm-yyyy`, the same format used for the agenda filenames.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}-{:02}-{}", self.day, self.month, self.year)
+    }
+}
+
+impl std::str::FromStr for Date {
+    type Err = String;
+
+    /// Parse a `dd-mm-yyyy` string into a `Date`.
+    ///
+    /// The resulting `Date` is checked with the `validate` method, so strings like `31-02-2024`
+    /// are rejected.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.trim().split('-').collect();
+        if parts.len() != 3 {
+            return Err(format!("invalid date `{}`, expected dd-mm-yyyy", s));
+        }
+        let mut values: [i32; 3] = [0; 3];
+        for (i, part) in parts.iter().enumerate() {
+            values[i] = match part.parse::<i32>() {
+                Ok(v) => v,
+                Err(_) => {
+                    return Err(format!("invalid date `{}`, expected dd-mm-yyyy", s));
+                }
+            };
+        }
+        let date = Date {
+            day: values[0],
+            month: values[1],
+            year: values[2],
+        };
+        if !date.validate() {
+            return Err(format!("date `{}` does not exist", s));
+        }
+        return Ok(date);
+    }
+}
+
+impl Serialize for Date {
+    /// `Date` is serialized as a `dd-mm-yyyy` string.
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Date {
+    /// `Date` is deserialized from a `dd-mm-yyyy` string.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        value.parse::<Date>().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(day: i32, month: i32, year: i32) -> Date {
+        return Date { day, month, year };
+    }
+
+    #[test]
+    fn decrement_wraps_month_and_year() {
+        let cases = [
+            (d(2, 3, 2025), d(1, 3, 2025)),
+            (d(1, 3, 2025), d(28, 2, 2025)),
+            (d(1, 3, 2024), d(29, 2, 2024)),
+            (d(1, 5, 2025), d(30, 4, 2025)),
+            (d(1, 2, 2025), d(31, 1, 2025)),
+            (d(1, 1, 2025), d(31, 12, 2024)),
+        ];
+        for (start, expected) in cases.iter() {
+            let mut date = *start;
+            date.decrement();
+            assert_eq!(date, *expected, "day before {}", start);
+        }
+    }
+
+    #[test]
+    fn increment_wraps_month_and_year() {
+        let mut date = d(28, 2, 2025);
+        date.increment();
+        assert_eq!(date, d(1, 3, 2025));
+        let mut date = d(31, 12, 2024);
+        date.increment();
+        assert_eq!(date, d(1, 1, 2025));
+    }
+
+    #[test]
+    fn add_days_crosses_year_boundaries() {
+        let mut date = d(3, 1, 2025);
+        date.add_days(-7);
+        assert_eq!(date, d(27, 12, 2024));
+        date.add_days(7);
+        assert_eq!(date, d(3, 1, 2025));
+        date.add_days(-366);
+        assert_eq!(date, d(3, 1, 2024));
+    }
+
+    #[test]
+    fn add_months_clamps_the_day() {
+        let mut date = d(31, 1, 2024);
+        date.add_months(1);
+        assert_eq!(date, d(29, 2, 2024));
+        let mut date = d(15, 1, 2025);
+        date.add_months(-13);
+        assert_eq!(date, d(15, 12, 2023));
+    }
+}
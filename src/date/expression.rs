@@ -0,0 +1,291 @@
+// Local files
+use crate::date::date;
+
+/// Largest offset in years, months, weeks or days accepted, about a century.
+const MAX_OFFSET_YEARS: i32 = 100;
+
+/// Description of the accepted date expressions, shown in the usage of the commands.
+pub const DATE_FORMATS: &str =
+    "DATE is one of: dd-mm-yyyy, dd/mm/yyyy, yyyy-mm-dd, dd/mm, dd (day of the current month),
+today, tomorrow, yesterday, +3d, -1w, +2m, +1y, [next|last] friday, next week/month/year,
+first/last day of [this|next|last] month.";
+
+/// Parse a date expression, relative to `today`, into a `Date`.
+///
+/// Besides the `dd-mm-yyyy` format of the agenda files, the following expressions are accepted
+/// (case insensitive):
+/// - Absolute dates: `yyyy-mm-dd`, `dd/mm/yyyy`, `dd/mm` and `dd`, where the last two use the
+///   current year and the current month and year respectively.
+/// - Named days: `today`, `tomorrow` and `yesterday`.
+/// - Offsets: `+3d`, `-1w`, `+2m`, `+1y`. Adding months or years to a day that does not exist in
+///   the resulting month gives the last day of that month.
+/// - Weekdays: `friday` and `next friday` give the first Friday after today, `last friday` the
+///   last Friday before today. Weekdays can be abbreviated to their first three letters.
+/// - `next week`, `next month`, `next year` and their `last` counterparts.
+/// - `first day of month` and `last day of month`, optionally with `this`, `next` or `last`
+///   before `month`.
+///
+/// The resulting `Date` is validated, an error message is returned if the expression can not be
+/// read or results in an invalid date.
+pub fn parse_date(expression: &str, today: &date::Date) -> Result<date::Date, String> {
+    let normalized: String = expression
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase();
+
+    let parsed: Option<date::Date> = match normalized.as_str() {
+        "" => None,
+        "today" | "now" => Some(*today),
+        "tomorrow" => shifted(today, 1, 'd'),
+        "yesterday" => shifted(today, -1, 'd'),
+        _ => parse_absolute(&normalized, today)
+            .or_else(|| parse_offset(&normalized, today))
+            .or_else(|| parse_relative(&normalized, today))
+            .or_else(|| parse_day_of_month(&normalized, today)),
+    };
+
+    return match parsed {
+        Some(d) if d.validate() => Ok(d),
+        Some(_) => Err(format!("`{}` is not a valid date", expression.trim())),
+        None => Err(format!("could not read date `{}`", expression.trim())),
+    };
+}
+
+/// Read `dd-mm-yyyy`, `dd/mm/yyyy`, `yyyy-mm-dd`, `dd/mm` and `dd` dates.
+///
+/// The returned `Date` is not validated.
+fn parse_absolute(expression: &str, today: &date::Date) -> Option<date::Date> {
+    let parts: Vec<&str> = expression.split(['-', '/']).collect();
+    if parts
+        .iter()
+        .any(|p| p.is_empty() || !p.chars().all(|c| c.is_ascii_digit()))
+    {
+        return None;
+    }
+    let numbers: Vec<i32> = parts
+        .iter()
+        .map(|p| p.parse::<i32>())
+        .collect::<Result<Vec<i32>, _>>()
+        .ok()?;
+
+    return match numbers.as_slice() {
+        [day] => Some(date::Date {
+            day: *day,
+            month: today.month,
+            year: today.year,
+        }),
+        [day, month] if expression.contains('/') => Some(date::Date {
+            day: *day,
+            month: *month,
+            year: today.year,
+        }),
+        [year, month, day] if parts[0].len() == 4 && expression.contains('-') => Some(date::Date {
+            day: *day,
+            month: *month,
+            year: *year,
+        }),
+        [day, month, year] if parts[2].len() == 4 => Some(date::Date {
+            day: *day,
+            month: *month,
+            year: *year,
+        }),
+        _ => None,
+    };
+}
+
+/// Read offsets like `+3d`, `-1w`, `+2m` and `+1y`.
+///
+/// Offsets of more than `MAX_OFFSET_YEARS` years are not read.
+fn parse_offset(expression: &str, today: &date::Date) -> Option<date::Date> {
+    // The sign and the unit are needed, `+` or `-` alone is not an offset.
+    if expression.chars().count() < 2 {
+        return None;
+    }
+    let sign: i32 = match expression.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let unit: char = expression.chars().last()?;
+    let amount: i32 = expression[1..expression.len() - unit.len_utf8()]
+        .parse()
+        .ok()?;
+    if !matches!(unit, 'd' | 'w' | 'm' | 'y') {
+        return None;
+    }
+    return shifted(today, sign * amount, unit);
+}
+
+/// Read weekdays and `next`/`last` week, month or year.
+fn parse_relative(expression: &str, today: &date::Date) -> Option<date::Date> {
+    let (direction, rest): (i32, &str) = match expression.split_once(' ') {
+        Some(("next", rest)) => (1, rest),
+        Some(("last", rest)) | Some(("previous", rest)) => (-1, rest),
+        Some(("this", rest)) => (0, rest),
+        Some(_) => return None,
+        None => (0, expression),
+    };
+
+    match rest {
+        "week" if direction != 0 => return shifted(today, direction, 'w'),
+        "month" if direction != 0 => return shifted(today, direction, 'm'),
+        "year" if direction != 0 => return shifted(today, direction, 'y'),
+        _ => {}
+    }
+
+    let weekday = weekday_number(rest)?;
+    let mut result: date::Date = *today;
+    if direction < 0 {
+        result.decrement();
+        while result.day_of_week() != weekday {
+            result.decrement();
+        }
+    } else {
+        result.increment();
+        while result.day_of_week() != weekday {
+            result.increment();
+        }
+    }
+    return Some(result);
+}
+
+/// Read `first day of month` and `last day of month`, with an optional `this`, `next` or `last`
+/// before `month`.
+fn parse_day_of_month(expression: &str, today: &date::Date) -> Option<date::Date> {
+    let (which, month) = expression.split_once(" day of ")?;
+    let shift: i32 = match month {
+        "month" | "this month" | "the month" => 0,
+        "next month" => 1,
+        "last month" | "previous month" => -1,
+        _ => return None,
+    };
+    let mut result: date::Date = *today;
+    result.day = 1;
+    result.add_months(shift);
+    match which {
+        "first" => {}
+        "last" => result.day = result.max_day(),
+        _ => return None,
+    }
+    return Some(result);
+}
+
+/// Obtain the weekday number, where `Monday = 1` and `Sunday = 7`, from its (abbreviated) name.
+fn weekday_number(name: &str) -> Option<i32> {
+    if name.len() < 3 {
+        return None;
+    }
+    return date::DAYS
+        .iter()
+        .position(|day| day.to_lowercase().starts_with(name))
+        .map(|index| index as i32 + 1);
+}
+
+/// Copy of `today` shifted by `amount` days (`d`), weeks (`w`), months (`m`) or years (`y`).
+///
+/// Returns `None` if the shift is larger than `MAX_OFFSET_YEARS` years.
+fn shifted(today: &date::Date, amount: i32, unit: char) -> Option<date::Date> {
+    let mut result: date::Date = *today;
+    match unit {
+        'd' | 'w' => {
+            let days = amount.checked_mul(if unit == 'w' { 7 } else { 1 })?;
+            if days.abs() > MAX_OFFSET_YEARS * 366 {
+                return None;
+            }
+            result.add_days(days);
+        }
+        _ => {
+            let months = amount.checked_mul(if unit == 'y' { 12 } else { 1 })?;
+            if months.abs() > MAX_OFFSET_YEARS * 12 {
+                return None;
+            }
+            result.add_months(months);
+        }
+    }
+    return Some(result);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(day: i32, month: i32, year: i32) -> date::Date {
+        return date::Date { day, month, year };
+    }
+
+    fn parse(expression: &str, today: &date::Date) -> Option<date::Date> {
+        return parse_date(expression, today).ok();
+    }
+
+    #[test]
+    fn absolute_dates() {
+        let today = d(18, 10, 2026);
+        assert_eq!(parse("01-02-2025", &today), Some(d(1, 2, 2025)));
+        assert_eq!(parse("01/02/2025", &today), Some(d(1, 2, 2025)));
+        assert_eq!(parse("2025-02-01", &today), Some(d(1, 2, 2025)));
+        assert_eq!(parse("1/2", &today), Some(d(1, 2, 2026)));
+        assert_eq!(parse("5", &today), Some(d(5, 10, 2026)));
+        assert_eq!(parse("31-02-2025", &today), None);
+    }
+
+    #[test]
+    fn named_days_at_year_boundaries() {
+        assert_eq!(parse("yesterday", &d(1, 1, 2025)), Some(d(31, 12, 2024)));
+        assert_eq!(parse("Tomorrow", &d(31, 12, 2024)), Some(d(1, 1, 2025)));
+        assert_eq!(parse(" today ", &d(1, 1, 2025)), Some(d(1, 1, 2025)));
+    }
+
+    #[test]
+    fn offsets() {
+        let today = d(3, 1, 2025);
+        assert_eq!(parse("-1w", &today), Some(d(27, 12, 2024)));
+        assert_eq!(parse("+3d", &today), Some(d(6, 1, 2025)));
+        assert_eq!(parse("+2m", &d(31, 12, 2024)), Some(d(28, 2, 2025)));
+        assert_eq!(parse("-1y", &d(29, 2, 2024)), Some(d(28, 2, 2023)));
+    }
+
+    #[test]
+    fn invalid_offsets_are_errors() {
+        let today = d(3, 1, 2025);
+        for expression in [
+            "+",
+            "-",
+            "+d",
+            "+3x",
+            "+999999999w",
+            "+2147483647d",
+            "-1201m",
+        ] {
+            assert!(
+                parse_date(expression, &today).is_err(),
+                "`{}` is not a date",
+                expression
+            );
+        }
+        assert_eq!(parse("+100y", &today), Some(d(3, 1, 2125)));
+    }
+
+    #[test]
+    fn weekdays() {
+        // 1 January 2025 is a Wednesday.
+        let today = d(1, 1, 2025);
+        assert_eq!(parse("friday", &today), Some(d(3, 1, 2025)));
+        assert_eq!(parse("next wed", &today), Some(d(8, 1, 2025)));
+        assert_eq!(parse("last friday", &today), Some(d(27, 12, 2024)));
+    }
+
+    #[test]
+    fn first_and_last_day_of_month() {
+        let today = d(15, 1, 2025);
+        assert_eq!(parse("first day of month", &today), Some(d(1, 1, 2025)));
+        assert_eq!(
+            parse("last day of next month", &today),
+            Some(d(28, 2, 2025))
+        );
+        assert_eq!(
+            parse("last day of last month", &today),
+            Some(d(31, 12, 2024))
+        );
+    }
+}
@@ -1 +1,10 @@
+// The date module predates the clippy checks, its style is kept as it is.
+#[allow(
+    clippy::module_inception,
+    clippy::needless_bool,
+    clippy::clone_on_copy,
+    clippy::manual_unwrap_or
+)]
 pub mod date;
+pub mod expression;
+pub mod time;
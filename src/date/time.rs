@@ -0,0 +1,155 @@
+// External crates
+extern crate serde;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Time of day with a precision of minutes, used for the start and end of timed events.
+///
+/// Times are ordered chronologically and written as `hh:mm`, the format used in the agenda
+/// files.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Time {
+    pub hour: u32,
+    pub minute: u32,
+}
+
+impl Time {
+    /// Create a `Time` if `hour` and `minute` describe a valid time of day.
+    pub fn new(hour: u32, minute: u32) -> Option<Self> {
+        if hour > 23 || minute > 59 {
+            return None;
+        }
+        return Some(Time { hour, minute });
+    }
+}
+
+impl std::fmt::Display for Time {
+    /// Format `Time` as `hh:mm`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
+impl std::str::FromStr for Time {
+    type Err = String;
+
+    /// Parse a time of day.
+    ///
+    /// Accepted are hours (`9`), hours and minutes separated by `:`, `.` or `h` (`9:30`, `9.30`,
+    /// `9h30`) or not separated at all (`0930`), each optionally followed by `am` or `pm`
+    /// (`9:30pm`, `12am`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("`{}` is not a valid time", s.trim());
+        let lowercase = s.trim().to_lowercase();
+
+        // Strip the 12-hour clock suffix, remembering whether it is in the afternoon.
+        let (clock, afternoon): (&str, Option<bool>) =
+            if let Some(rest) = lowercase.strip_suffix("am") {
+                (rest.trim_end(), Some(false))
+            } else if let Some(rest) = lowercase.strip_suffix("pm") {
+                (rest.trim_end(), Some(true))
+            } else {
+                (lowercase.as_str(), None)
+            };
+
+        let (hour_str, minute_str): (&str, &str) = match clock.split_once([':', '.', 'h']) {
+            Some((h, m)) => (h, m),
+            // Only digits are valid, so anything else is rejected before splitting mid-character.
+            None if !clock.is_ascii() => return Err(invalid()),
+            None if clock.len() > 2 => clock.split_at(clock.len() - 2),
+            None => (clock, "0"),
+        };
+        let all_digits = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+        if !all_digits(hour_str) || !all_digits(minute_str) || minute_str.len() > 2 {
+            return Err(invalid());
+        }
+        let mut hour: u32 = hour_str.parse().map_err(|_| invalid())?;
+        let minute: u32 = minute_str.parse().map_err(|_| invalid())?;
+
+        if let Some(pm) = afternoon {
+            if hour == 0 || hour > 12 {
+                return Err(invalid());
+            }
+            // 12am is midnight and 12pm is noon.
+            hour %= 12;
+            if pm {
+                hour += 12;
+            }
+        }
+        return Time::new(hour, minute).ok_or_else(invalid);
+    }
+}
+
+impl Serialize for Time {
+    /// Serialize `Time` as its `hh:mm` string.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Time {
+    /// Deserialize `Time` from any string accepted by `FromStr`.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        return value.parse::<Time>().map_err(serde::de::Error::custom);
+    }
+}
+
+/// Deserialize an optional `Time`, where an empty string means no time.
+///
+/// Agenda files written before times were validated contain `end = ''` for events without an
+/// end time, this keeps them readable.
+pub fn deserialize_optional<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Time>, D::Error> {
+    let value: Option<String> = Option::deserialize(deserializer)?;
+    return match value {
+        Some(v) if !v.trim().is_empty() => match v.parse::<Time>() {
+            Ok(t) => Ok(Some(t)),
+            Err(e) => Err(serde::de::Error::custom(e)),
+        },
+        _ => Ok(None),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t(hour: u32, minute: u32) -> Option<Time> {
+        return Time::new(hour, minute);
+    }
+
+    #[test]
+    fn parses_accepted_formats() {
+        let cases = [
+            ("9", t(9, 0)),
+            ("09:30", t(9, 30)),
+            ("9.30", t(9, 30)),
+            ("9h30", t(9, 30)),
+            ("0930", t(9, 30)),
+            (" 23:59 ", t(23, 59)),
+            ("9:30pm", t(21, 30)),
+            ("12am", t(0, 0)),
+            ("12 PM", t(12, 0)),
+        ];
+        for (text, expected) in cases.iter() {
+            assert_eq!(text.parse::<Time>().ok(), *expected, "parsing `{}`", text);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_times() {
+        for text in [
+            "", "24:00", "9:60", "9:5x", "13pm", "0am", "9:300", "noon", "-1", "éa", "1é5",
+        ] {
+            assert!(text.parse::<Time>().is_err(), "`{}` is not a time", text);
+        }
+    }
+
+    #[test]
+    fn formats_as_hours_and_minutes() {
+        assert_eq!(Time::new(7, 5).unwrap().to_string(), "07:05");
+        assert!(Time::new(9, 0) < Time::new(10, 0));
+    }
+}
@@ -0,0 +1,66 @@
+// Local files
+use crate::configuration::config;
+
+/// Errors returned by the commands of `crust`.
+///
+/// Library functions return an `Error` instead of printing or exiting, so `crust` can be used
+/// from other programs. The variants separate the causes a caller may want to handle differently,
+/// the binary maps each of them to its own exit code. The message of every variant is meant to
+/// be shown to the user as is.
+#[derive(Debug)]
+pub enum Error {
+    /// The configuration file cannot be used.
+    Config(config::ConfigError),
+    /// The stored agenda cannot be read or written.
+    Storage(String),
+    /// The selected entry or calendar does not exist.
+    NotFound(String),
+    /// Input other than the program arguments is invalid, e.g. a file to import or entries
+    /// changed in the editor. Nothing was changed.
+    Invalid(String),
+    /// `$EDITOR` is not set, cannot be started or exited with an error.
+    Editor(String),
+    /// The entries changed while they were being edited, the edits were not written.
+    Conflict(String),
+    /// The terminal cannot be used to show the UI.
+    Terminal(String),
+    /// `crust check` found problems in the agenda files that are not fixed.
+    Problems(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Config(e) => write!(f, "{}", e),
+            Error::Storage(msg)
+            | Error::NotFound(msg)
+            | Error::Invalid(msg)
+            | Error::Editor(msg)
+            | Error::Conflict(msg)
+            | Error::Terminal(msg)
+            | Error::Problems(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Config(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<config::ConfigError> for Error {
+    fn from(e: config::ConfigError) -> Self {
+        Error::Config(e)
+    }
+}
+
+impl From<String> for Error {
+    /// The storage backends report their errors as messages, which makes them `Storage` errors.
+    fn from(msg: String) -> Self {
+        Error::Storage(msg)
+    }
+}
@@ -0,0 +1,110 @@
+use std::io::Write;
+
+/// Name of the lock file in the directory of the agenda files.
+pub const LOCK_FILENAME: &str = ".lock";
+
+/// Exclusive advisory lock on the agenda store.
+///
+/// Every change to the agenda files reads, modifies and writes them while holding the lock, so
+/// concurrent `crust` processes cannot interleave their changes. The lock is released when the
+/// `StoreLock` is dropped.
+#[derive(Debug)]
+pub struct StoreLock {
+    _file: Option<std::fs::File>,
+}
+
+impl StoreLock {
+    /// Acquire the lock on the file at `lockpath`, waiting for other `crust` processes to
+    /// release it.
+    pub fn acquire(lockpath: &std::path::Path) -> Result<Self, String> {
+        let file = match std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lockpath)
+        {
+            Ok(f) => f,
+            Err(e) => {
+                return Err(format!("Error opening {}: {}", lockpath.display(), e));
+            }
+        };
+        if let Err(e) = file.lock() {
+            return Err(format!("Error locking {}: {}", lockpath.display(), e));
+        }
+        return Ok(StoreLock { _file: Some(file) });
+    }
+
+    /// Create a `StoreLock` that holds no lock, for storage that is not shared between processes.
+    pub fn unlocked() -> Self {
+        return StoreLock { _file: None };
+    }
+}
+
+/// Replace the contents of the file at `path` by `contents` in a single step.
+///
+/// The contents are written to a temporary file in the same directory, flushed to disk and then
+/// renamed over `path`. Readers see either the old or the new contents, never a half-written
+/// file, even if `crust` is interrupted. The directory is flushed as well, so the rename itself
+/// survives a crash.
+pub fn write_atomic(path: &std::path::Path, contents: &str) -> Result<(), String> {
+    let filename = match path.file_name() {
+        Some(f) => f.to_string_lossy().to_string(),
+        None => {
+            return Err(format!("Invalid file name {}", path.display()));
+        }
+    };
+    // Temporary files start with a dot, so they are never mistaken for day files.
+    let temp_path = path.with_file_name(format!(".{}.{}.tmp", filename, std::process::id()));
+
+    let result = std::fs::File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| std::fs::rename(&temp_path, path));
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(format!("Error writing {}: {}", path.display(), e));
+    }
+    if let Err(e) = sync_parent(path) {
+        return Err(format!(
+            "Error flushing the directory of {}: {}",
+            path.display(),
+            e
+        ));
+    }
+    return Ok(());
+}
+
+/// Flush the directory containing `path` to disk, which makes renames in it durable.
+///
+/// Directories cannot be opened as files on every platform, there this does nothing.
+fn sync_parent(path: &std::path::Path) -> std::io::Result<()> {
+    if cfg!(unix) {
+        let parent = match path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _ => std::path::Path::new("."),
+        };
+        std::fs::File::open(parent)?.sync_all()?;
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_atomic_replaces_the_contents() {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("crust-atomic-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("01-02-2026.toml");
+        write_atomic(&path, "first").unwrap();
+        write_atomic(&path, "second").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "second");
+        // Only the file itself remains, the temporary file is renamed over it.
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
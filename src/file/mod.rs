@@ -1 +1,4 @@
+pub mod atomic;
+pub mod modify;
 pub mod parser;
+pub mod recurrence;
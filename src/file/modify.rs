@@ -0,0 +1,534 @@
+// External crates
+extern crate toml;
+
+// Local files
+use crate::args;
+use crate::date::date;
+use crate::error::error;
+use crate::file::parser;
+use crate::file::recurrence;
+use crate::output::records;
+use crate::storage::calendar;
+use crate::storage::storage;
+
+/// Remove the entry of `program_args.date` selected by `--index` or `--id`, or every entry of
+/// that date with `--all`.
+///
+/// The entries of the `calendars` are numbered as in `crust show`. The selected events are deleted
+/// from their calendar. Removing an occurrence of a recurring event adds the date to the
+/// exceptions of that event. Returns a message describing what was removed. The calendars are
+/// locked from reading the entries until they are written.
+pub fn remove_entry(
+    calendars: &[calendar::Calendar],
+    program_args: &args::parser::ProgramArguments,
+) -> Result<String, error::Error> {
+    let mut _locks = Vec::new();
+    for cal in calendars.iter() {
+        _locks.push(cal.storage.lock()?);
+    }
+    let entry_date = program_args.date;
+    let entries = calendar_entries(&entry_date, calendars)?;
+    if entries.is_empty() {
+        return Err(error::Error::NotFound(format!(
+            "No entry to remove for {}.",
+            entry_date
+        )));
+    }
+
+    let selected: Vec<(usize, records::Entry)> = if program_args.flags.all {
+        entries
+    } else {
+        vec![entries[select_entry(program_args, &entries, "remove")?].clone()]
+    };
+    for (owner, cal) in calendars.iter().enumerate() {
+        let mut recurring = cal.storage.load_recurring()?;
+        let mut skipped_occurrence = false;
+        for (_, entry) in selected.iter().filter(|(c, _)| *c == owner) {
+            match entry.source {
+                records::Source::Occurrence(i) => {
+                    skip_occurrence(&entry_date, &mut recurring[i]);
+                    skipped_occurrence = true;
+                }
+                _ => {
+                    cal.storage.delete_event(&entry_date, &entry.record.id)?;
+                }
+            }
+        }
+        if skipped_occurrence {
+            cal.storage.save_recurring(&recurring)?;
+        }
+    }
+
+    if selected.len() == 1 {
+        return Ok(format!(
+            "Removed `{}` from {}.",
+            selected[0].1.record.text, entry_date
+        ));
+    }
+    return Ok(format!(
+        "Removed {} entries from {}.",
+        selected.len(),
+        entry_date
+    ));
+}
+
+/// Edit the entry of `program_args.date` selected by `--index` or `--id` with the system default
+/// `$EDITOR`. With `--all`, every entry stored on the date in a single calendar is edited at once.
+///
+/// The selected events are written to a temporary file in the format of the day files. After
+/// the editor exits, the file is read again and validated before the events replace the selected
+/// ones, so a mistake never makes the stored events unreadable. Editing an occurrence of a recurring
+/// event turns it into a single event on that date. Returns a message describing the change.
+///
+/// The storage is not locked while the editor is open. Before writing, the storage is locked and
+/// the entries of the date are read again, if they changed in the meantime nothing is written and
+/// the edits are kept in the temporary file.
+pub fn edit_entry(
+    calendars: &[calendar::Calendar],
+    program_args: &args::parser::ProgramArguments,
+) -> Result<String, error::Error> {
+    let entry_date = program_args.date;
+    // Find the calendar of the edited entries, the entries are edited in that calendar only.
+    let (owner, chosen): (usize, Option<records::Entry>) = if program_args.flags.all {
+        if calendars.len() != 1 {
+            return Err(error::Error::Invalid(
+                "Select the calendar to edit with --calendar.".to_string(),
+            ));
+        }
+        (0, None)
+    } else {
+        let entries = calendar_entries(&entry_date, calendars)?;
+        if entries.is_empty() {
+            return Err(error::Error::NotFound(format!(
+                "No entry to edit for {}.",
+                entry_date
+            )));
+        }
+        let (owner, entry) = entries[select_entry(program_args, &entries, "edit")?].clone();
+        (owner, Some(entry))
+    };
+    let storage = calendars[owner].storage.as_ref();
+    let stored = storage.load_day(&entry_date)?;
+    let recurring = storage.load_recurring()?;
+    let entries = records::date_entries(&entry_date, &stored, &recurring);
+
+    let selected: Vec<records::Entry> = match chosen {
+        Some(entry) => vec![entry],
+        None => entries
+            .iter()
+            .filter(|e| !matches!(e.source, records::Source::Occurrence(_)))
+            .cloned()
+            .collect(),
+    };
+
+    // Collect the selected events in an agenda of their own.
+    let mut original = parser::Agenda::default();
+    for entry in selected.iter() {
+        match entry.source {
+            records::Source::Day(i) => original.day.push(stored.day[i].clone()),
+            records::Source::Timed(i) => original.timestamp.push(stored.timestamp[i].clone()),
+            records::Source::Occurrence(i) => recurring[i].add_to_agenda(&mut original),
+        }
+    }
+
+    let (edited, temp_path) = edit_in_editor(&entry_date, &original)?;
+    if edited.day == original.day && edited.timestamp == original.timestamp {
+        let _ = std::fs::remove_file(&temp_path);
+        return Ok("No changes.".to_string());
+    }
+
+    let _lock = storage.lock()?;
+    let mut stored = storage.load_day(&entry_date)?;
+    let mut recurring = storage.load_recurring()?;
+    if records::date_entries(&entry_date, &stored, &recurring) != entries {
+        return Err(error::Error::Conflict(format!(
+            "The entries of {} changed while editing, nothing changed. Your edits are kept in {}.",
+            entry_date,
+            temp_path.display()
+        )));
+    }
+
+    let sources: Vec<records::Source> = selected.iter().map(|e| e.source).collect();
+    remove_sources(&entry_date, &sources, &mut stored, &mut recurring);
+    stored.day.extend(edited.day);
+    stored.timestamp.extend(edited.timestamp);
+    stored.timestamp.sort();
+    storage.save_day(&entry_date, &stored)?;
+    if sources
+        .iter()
+        .any(|s| matches!(s, records::Source::Occurrence(_)))
+    {
+        storage.save_recurring(&recurring)?;
+    }
+    let _ = std::fs::remove_file(&temp_path);
+    return Ok(format!("Updated the entries of {}.", entry_date));
+}
+
+/// Replace the entry with the identifier `id` on `entry_date` in `cal` by the entry described by
+/// the program arguments, as the UI does when an entry is edited.
+///
+/// The new entry is added as `parser::write_entry` adds it, keeping the `uid` of an imported
+/// entry. Replacing an occurrence of a recurring event turns it into a single event on that date,
+/// as `edit_entry` does. The calendar is locked while the entry is replaced. Returns a message
+/// describing the change.
+pub fn replace_entry(
+    cal: &calendar::Calendar,
+    entry_date: &date::Date,
+    id: &str,
+    program_args: &args::parser::ProgramArguments,
+) -> Result<String, error::Error> {
+    let _lock = cal.storage.lock()?;
+    let mut stored = cal.storage.load_day(entry_date)?;
+    let mut recurring = cal.storage.load_recurring()?;
+    let entry = find_entry(entry_date, &stored, &recurring, id)?;
+    let uid = match entry.source {
+        records::Source::Day(i) => stored.day[i].uid.clone(),
+        records::Source::Timed(i) => stored.timestamp[i].uid.clone(),
+        records::Source::Occurrence(_) => None,
+    };
+    remove_sources(entry_date, &[entry.source], &mut stored, &mut recurring);
+    parser::add_event(&mut stored, program_args, uid);
+    cal.storage.save_day(entry_date, &stored)?;
+    if let records::Source::Occurrence(_) = entry.source {
+        cal.storage.save_recurring(&recurring)?;
+    }
+    return Ok(format!(
+        "Updated `{}` on {}.",
+        program_args.entry, entry_date
+    ));
+}
+
+/// Delete the entry with the identifier `id` on `entry_date` from `cal`, as the UI does.
+///
+/// Deleting an occurrence of a recurring event adds the date to the exceptions of that event, as
+/// `remove_entry` does. The calendar is locked while the entry is deleted. Returns a message
+/// describing what was removed.
+pub fn delete_entry(
+    cal: &calendar::Calendar,
+    entry_date: &date::Date,
+    id: &str,
+) -> Result<String, error::Error> {
+    let _lock = cal.storage.lock()?;
+    let mut stored = cal.storage.load_day(entry_date)?;
+    let mut recurring = cal.storage.load_recurring()?;
+    let entry = find_entry(entry_date, &stored, &recurring, id)?;
+    remove_sources(entry_date, &[entry.source], &mut stored, &mut recurring);
+    match entry.source {
+        records::Source::Occurrence(_) => cal.storage.save_recurring(&recurring)?,
+        _ => cal.storage.save_day(entry_date, &stored)?,
+    }
+    return Ok(format!(
+        "Removed `{}` from {}.",
+        entry.record.text, entry_date
+    ));
+}
+
+/// Find the entry with the identifier `id` among the entries of `entry_date`.
+fn find_entry(
+    entry_date: &date::Date,
+    stored: &parser::Agenda,
+    recurring: &[recurrence::Recurrence],
+    id: &str,
+) -> Result<records::Entry, error::Error> {
+    return match records::date_entries(entry_date, stored, recurring)
+        .into_iter()
+        .find(|e| e.record.id == id)
+    {
+        Some(entry) => Ok(entry),
+        None => Err(error::Error::NotFound(format!(
+            "There is no entry with id `{}` on {}.",
+            id, entry_date
+        ))),
+    };
+}
+
+/// List the entries of `entry_date` in the `calendars` in the order of `crust show`, each together
+/// with the index of its calendar.
+///
+/// This is the numbering `--index` selects from, so a caller can let the user choose an entry.
+pub fn calendar_entries(
+    entry_date: &date::Date,
+    calendars: &[calendar::Calendar],
+) -> Result<Vec<(usize, records::Entry)>, String> {
+    let mut entries: Vec<(usize, records::Entry)> = Vec::new();
+    for (owner, cal) in calendars.iter().enumerate() {
+        let stored = cal.storage.load_day(entry_date)?;
+        let recurring = cal.storage.load_recurring()?;
+        for mut entry in records::date_entries(entry_date, &stored, &recurring).into_iter() {
+            entry.record.calendar = cal.name.clone();
+            entries.push((owner, entry));
+        }
+    }
+    entries.sort_by(|a, b| {
+        records::listing_order(&a.1.record).cmp(&records::listing_order(&b.1.record))
+    });
+    return Ok(entries);
+}
+
+/// Find the position of the entry selected by `--index` or `--id` among `entries`, an error
+/// asks for either of them for the `action` when neither is given.
+fn select_entry(
+    program_args: &args::parser::ProgramArguments,
+    entries: &[(usize, records::Entry)],
+    action: &str,
+) -> Result<usize, error::Error> {
+    if let Some(index) = program_args.index {
+        return match entries.get(index - 1) {
+            Some(_) => Ok(index - 1),
+            None => Err(error::Error::NotFound(format!(
+                "There is no entry {} on {}, it has {} entries.",
+                index,
+                program_args.date,
+                entries.len()
+            ))),
+        };
+    }
+    if let Some(id) = &program_args.id {
+        return match entries.iter().position(|(_, e)| e.record.id == *id) {
+            Some(position) => Ok(position),
+            None => Err(error::Error::NotFound(format!(
+                "There is no entry with id `{}` on {}.",
+                id, program_args.date
+            ))),
+        };
+    }
+    return Err(error::Error::Invalid(format!(
+        "Select the entry to {} with --index or --id.",
+        action
+    )));
+}
+
+/// Remove the events at `sources` from `stored`, or skip the recurring events at `sources` on
+/// `entry_date`.
+fn remove_sources(
+    entry_date: &date::Date,
+    sources: &[records::Source],
+    stored: &mut parser::Agenda,
+    recurring: &mut [recurrence::Recurrence],
+) {
+    let mut day_indices: Vec<usize> = Vec::new();
+    let mut timed_indices: Vec<usize> = Vec::new();
+    for source in sources.iter() {
+        match source {
+            records::Source::Day(i) => day_indices.push(*i),
+            records::Source::Timed(i) => timed_indices.push(*i),
+            records::Source::Occurrence(i) => skip_occurrence(entry_date, &mut recurring[*i]),
+        }
+    }
+    // Remove from the back, so the remaining indices stay valid.
+    day_indices.sort();
+    timed_indices.sort();
+    for i in day_indices.iter().rev() {
+        stored.day.remove(*i);
+    }
+    for i in timed_indices.iter().rev() {
+        stored.timestamp.remove(*i);
+    }
+}
+
+/// Add `entry_date` to the exceptions of `recurrence`, so it does not occur on that date.
+fn skip_occurrence(entry_date: &date::Date, recurrence: &mut recurrence::Recurrence) {
+    if !recurrence.exceptions.contains(entry_date) {
+        recurrence.exceptions.push(*entry_date);
+        recurrence.exceptions.sort();
+    }
+}
+
+/// Let the user edit `agenda` with the system default `$EDITOR`, returning the edited agenda and
+/// the temporary file it was edited in.
+///
+/// If the edited file cannot be read, or contains an event ending before it starts, it is kept
+/// so the changes are not lost and an error is returned. Otherwise the caller removes the
+/// temporary file once the changes are written.
+fn edit_in_editor(
+    entry_date: &date::Date,
+    agenda: &parser::Agenda,
+) -> Result<(parser::Agenda, std::path::PathBuf), error::Error> {
+    let contents = match toml::to_string(agenda) {
+        Ok(c) => c,
+        Err(e) => {
+            return Err(error::Error::Storage(format!(
+                "Error serializing the entries: {}",
+                e
+            )));
+        }
+    };
+    let (edited_contents, temp_path) = run_editor(&format!("crust-{}", entry_date), &contents)?;
+
+    let edited: parser::Agenda = match toml::from_str(&edited_contents) {
+        Ok(a) => a,
+        Err(e) => {
+            return Err(error::Error::Invalid(format!(
+                "Error reading the edited entries, nothing changed. Your edits are kept in {}:\n{}",
+                temp_path.display(),
+                e
+            )));
+        }
+    };
+    if let Err(e) = edited.validate() {
+        return Err(error::Error::Invalid(format!(
+            "{}, nothing changed. Your edits are kept in {}.",
+            e,
+            temp_path.display()
+        )));
+    }
+    return Ok((edited, temp_path));
+}
+
+/// Edit the recurring event definitions in `storage` with the system default `$EDITOR`.
+///
+/// The definitions are written to a temporary file in the format of
+/// `recurring.toml`. They replace the stored definitions only if the edited
+/// file can be read as recurring events and the stored definitions did not change while editing.
+/// Otherwise the file is kept so the changes are not lost. Returns a message describing the
+/// change.
+pub fn edit_recurring(storage: &dyn storage::Storage) -> Result<String, error::Error> {
+    let original = storage.load_recurring()?;
+    let contents = match toml::to_string(&recurrence::RecurringEvents {
+        recurring: original.clone(),
+    }) {
+        Ok(c) => c,
+        Err(e) => {
+            return Err(error::Error::Storage(format!(
+                "Error serializing recurring events: {}",
+                e
+            )));
+        }
+    };
+    let (edited_contents, temp_path) = run_editor("crust-recurring", &contents)?;
+
+    // Missing tables default to no definitions, so a misspelled table would remove them all.
+    let edited: Result<recurrence::RecurringEvents, String> =
+        match toml::from_str::<toml::Table>(&edited_contents) {
+            Ok(table) => match table.keys().find(|k| *k != "recurring") {
+                Some(key) => Err(format!("unknown table `{}`", key)),
+                None => toml::from_str(&edited_contents).map_err(|e| e.to_string()),
+            },
+            Err(e) => Err(e.to_string()),
+        };
+    let edited: recurrence::RecurringEvents = match edited {
+        Ok(r) => r,
+        Err(e) => {
+            return Err(error::Error::Invalid(format!(
+                "Error reading the edited recurring events, nothing changed. Your edits are kept in {}:\n{}",
+                temp_path.display(),
+                e
+            )));
+        }
+    };
+    if let Some(e) = edited.recurring.iter().find_map(|r| r.validate().err()) {
+        return Err(error::Error::Invalid(format!(
+            "{}, nothing changed. Your edits are kept in {}.",
+            e,
+            temp_path.display()
+        )));
+    }
+    if edited.recurring == original {
+        let _ = std::fs::remove_file(&temp_path);
+        return Ok("No changes.".to_string());
+    }
+
+    let _lock = storage.lock()?;
+    if storage.load_recurring()? != original {
+        return Err(error::Error::Conflict(format!(
+            "The recurring events changed while editing, nothing changed. Your edits are kept in {}.",
+            temp_path.display()
+        )));
+    }
+    storage.save_recurring(&edited.recurring)?;
+    let _ = std::fs::remove_file(&temp_path);
+    return Ok("Updated the recurring events.".to_string());
+}
+
+/// Write `contents` to a new temporary file whose name starts with `prefix`, open it with the
+/// system default `$EDITOR` and return the contents after the editor exits, together with the
+/// path of the file.
+///
+/// If `$EDITOR` is not set or fails, the temporary file is removed again and an error is
+/// returned.
+fn run_editor(prefix: &str, contents: &str) -> Result<(String, std::path::PathBuf), error::Error> {
+    let editor = match std::env::var("EDITOR") {
+        Ok(v) if !v.trim().is_empty() => v,
+        _ => {
+            return Err(error::Error::Editor(
+                "Set $EDITOR to edit entries.".to_string(),
+            ));
+        }
+    };
+    let temp_path = create_temp_file(prefix, contents)?;
+
+    match std::process::Command::new(&editor).arg(&temp_path).status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => {
+            let _ = std::fs::remove_file(&temp_path);
+            return Err(error::Error::Editor(format!(
+                "{} exited with {}, nothing changed.",
+                editor, status
+            )));
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&temp_path);
+            return Err(error::Error::Editor(format!(
+                "Error opening {}: {}",
+                editor, e
+            )));
+        }
+    };
+
+    return match std::fs::read_to_string(&temp_path) {
+        Ok(c) => Ok((c, temp_path)),
+        Err(e) => Err(error::Error::Storage(format!(
+            "Error reading {}: {}",
+            temp_path.display(),
+            e
+        ))),
+    };
+}
+
+/// Create a new file in the temporary directory, whose name starts with `prefix`, containing
+/// `contents`.
+///
+/// The temporary directory is shared with other users, so an existing file or symbolic link is
+/// never opened, another name is tried instead. On Unix only the user can read the file.
+fn create_temp_file(prefix: &str, contents: &str) -> Result<std::path::PathBuf, error::Error> {
+    use std::io::Write;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut attempt: u32 = 0;
+    loop {
+        let mut temp_path = std::env::temp_dir();
+        temp_path.push(match attempt {
+            0 => format!("{}-{}.toml", prefix, std::process::id()),
+            _ => format!("{}-{}-{}.toml", prefix, std::process::id(), attempt),
+        });
+        match options.open(&temp_path) {
+            Ok(mut file) => {
+                if let Err(e) = file.write_all(contents.as_bytes()) {
+                    let _ = std::fs::remove_file(&temp_path);
+                    return Err(error::Error::Storage(format!(
+                        "Error writing {}: {}",
+                        temp_path.display(),
+                        e
+                    )));
+                }
+                return Ok(temp_path);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists && attempt < 100 => {
+                attempt += 1;
+            }
+            Err(e) => {
+                return Err(error::Error::Storage(format!(
+                    "Error creating {}: {}",
+                    temp_path.display(),
+                    e
+                )));
+            }
+        }
+    }
+}
@@ -1,64 +1,169 @@
 // External crates
-extern crate dirs;
-extern crate regex;
 extern crate serde;
 extern crate toml;
 
 use serde::{Deserialize, Serialize};
 use std::io::Read;
-use std::io::Write;
 
 // Local files
 use crate::args;
-use crate::date::date;
+use crate::date::time;
+use crate::error::error;
+use crate::file::recurrence;
+use crate::storage::storage;
 
 /// Store full day events.
-#[derive(Deserialize, Serialize, Debug)]
+///
+/// The optional `uid` identifies events imported from other calendars, it is used to recognise
+/// the event when it is imported again.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct DayEvent {
     pub event: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<String>,
 }
 
 /// Store events between two time stamps.
 ///
-/// Time stamps `start` and `end` have the assumed form xy:zw.
-#[derive(Deserialize, Serialize, Debug, Eq, Ord, PartialEq, PartialOrd)]
+/// Events are ordered chronologically by their `start` and `end` time, events without an `end`
+/// come first. An empty `end` in the file means the event has no end time. The optional `uid`
+/// serves the same purpose as for `DayEvent`.
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, Ord, PartialEq, PartialOrd)]
 pub struct TimedEvent {
-    pub start: String,
-    pub end: String,
+    pub start: time::Time,
+    #[serde(
+        default,
+        deserialize_with = "time::deserialize_optional",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub end: Option<time::Time>,
     pub event: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<String>,
+}
+
+/// Timed event as it is read from an agenda file.
+///
+/// Older versions wrote `start = ''` for events added with `-a` but without `-s`, these events
+/// have no `start` time here.
+#[derive(Deserialize)]
+struct StoredTimedEvent {
+    #[serde(deserialize_with = "time::deserialize_optional")]
+    start: Option<time::Time>,
+    #[serde(default, deserialize_with = "time::deserialize_optional")]
+    end: Option<time::Time>,
+    event: String,
+    #[serde(default)]
+    uid: Option<String>,
+}
+
+/// Agenda as it is read from an agenda file, see `Agenda`.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct StoredAgenda {
+    day: Vec<DayEvent>,
+    timestamp: Vec<StoredTimedEvent>,
+}
+
+impl From<StoredAgenda> for Agenda {
+    /// Keep the timed events with a `start` time, the others become full day events.
+    fn from(stored: StoredAgenda) -> Self {
+        let mut agenda = Agenda {
+            day: stored.day,
+            timestamp: Vec::new(),
+        };
+        for timed_event in stored.timestamp.into_iter() {
+            match timed_event.start {
+                Some(start) => agenda.timestamp.push(TimedEvent {
+                    start,
+                    end: timed_event.end,
+                    event: timed_event.event,
+                    uid: timed_event.uid,
+                }),
+                None => agenda.day.push(DayEvent {
+                    event: timed_event.event,
+                    uid: timed_event.uid,
+                }),
+            }
+        }
+        return agenda;
+    }
 }
 
 /// Deserialization struct for the agenda .toml files
 ///
 /// Full day events are use the `DayEvent` struct and timed events are contained in
 /// the `TimedEvent` struct. If a .toml file cannot be entirely deserialized into the struct,
-/// the remaining fields are filled in with default values.
-#[derive(Deserialize, Serialize, Debug)]
-#[serde(default)]
+/// the remaining fields are filled in with default values. Timed events with an empty `start`,
+/// written by older versions, are read as full day events.
+///
+/// The default `Agenda` has no events.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(from = "StoredAgenda")]
 pub struct Agenda {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub day: Vec<DayEvent>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub timestamp: Vec<TimedEvent>,
 }
 
-impl Default for Agenda {
-    /// Default values for `Agenda` are vectors with one element and empty strings.
-    fn default() -> Self {
-        Agenda {
-            day: vec![DayEvent {
-                event: "".to_string(),
-            }],
-            timestamp: vec![TimedEvent {
-                start: "".to_string(),
-                end: "".to_string(),
-                event: "".to_string(),
-            }],
+impl Agenda {
+    /// Check if the `Agenda` contains no events with text.
+    pub fn is_empty(&self) -> bool {
+        return self.day.iter().all(|e| e.event.is_empty())
+            && self.timestamp.iter().all(|e| e.event.is_empty());
+    }
+
+    /// Obtain a copy of the `Agenda` without the events that have no text.
+    pub fn cleaned(&self) -> Agenda {
+        return Agenda {
+            day: self
+                .day
+                .iter()
+                .filter(|e| !e.event.is_empty())
+                .cloned()
+                .collect(),
+            timestamp: self
+                .timestamp
+                .iter()
+                .filter(|e| !e.event.is_empty())
+                .cloned()
+                .collect(),
+        };
+    }
+
+    /// Check that no timed event ends before it starts.
+    pub fn validate(&self) -> Result<(), String> {
+        for timed_event in self.timestamp.iter() {
+            if let Some(end) = timed_event.end {
+                if end < timed_event.start {
+                    return Err(format!(
+                        "`{}` ends at {} before it starts at {}",
+                        timed_event.event, end, timed_event.start
+                    ));
+                }
+            }
         }
+        return Ok(());
+    }
+
+    /// Obtain the `uid` of every event in the `Agenda` that has one, without duplicates.
+    pub fn uids(&self) -> Vec<String> {
+        let mut uids: Vec<String> = self
+            .day
+            .iter()
+            .filter_map(|e| e.uid.clone())
+            .chain(self.timestamp.iter().filter_map(|e| e.uid.clone()))
+            .collect();
+        uids.sort();
+        uids.dedup();
+        return uids;
     }
 }
 
 /// Convert agenda information to `Agenda` struct.
 ///
-/// Given the filepath `$HOME/.cache/crust/dd-mm-yyyy.toml`, parse the file into the `Agenda`
+/// Given the filepath of a `dd-mm-yyyy.toml` file in the data directory, parse the file into the `Agenda`
 /// struct. Any field that cannot be filled in by the `.toml` file is filled in by the `Default`
 /// implementation on `Agenda`.
 pub fn parse_agenda_toml(filedir: &mut std::path::PathBuf) -> Option<Agenda> {
@@ -98,181 +203,115 @@ pub fn parse_agenda_toml(filedir: &mut std::path::PathBuf) -> Option<Agenda> {
     return Some(toml_struct);
 }
 
-/// Get a vector of dates stored in $HOME/.cache/crust/.
+/// Add the entry described by the program arguments to `storage`.
 ///
-/// If any error occurs when attempting to find the $HOME/.cache/crust/ directory, or listing the
-/// files in that directory, we return an empty vector. Only files of the form `dd-mm-yyyy.toml`
-/// are converted into a `Date` structure, and only the `Date` structures validated with the
-/// `validate` method on `Date` are pushed onto the vector of valid entries.
-pub fn get_agenda_entries() -> Vec<date::Date> {
-    // Initialise the output vector.
-    let empty: Vec<date::Date> = Vec::new();
-    let mut valid_entries: Vec<date::Date> = Vec::new();
+/// The events of the date are loaded, the entry is added and the whole day is saved again, so
+/// every string is escaped properly. Events that cannot be read are left untouched and reported
+/// as an error, instead of being overwritten. If the program arguments contain a repetition rule,
+/// the entry is stored as a recurring event starting on the given date instead. The storage is
+/// locked while the entry is added.
+pub fn write_entry(
+    storage: &dyn storage::Storage,
+    program_args: &args::parser::ProgramArguments,
+) -> Result<(), error::Error> {
+    let _lock = storage.lock()?;
+    if let Some(frequency) = program_args.repeat.frequency {
+        let mut recurring = storage.load_recurring()?;
+        recurring.push(recurrence::Recurrence {
+            id: recurrence::new_id(&program_args.entry),
+            event: program_args.entry.clone(),
+            full_day: program_args.flags.full_day,
+            start: program_args.start,
+            end: program_args.end,
+            frequency,
+            interval: program_args.repeat.interval,
+            first: program_args.date,
+            by_day: program_args.repeat.by_day.clone(),
+            count: program_args.repeat.count,
+            until: program_args.repeat.until,
+            exceptions: program_args.repeat.exceptions.clone(),
+        });
+        storage.save_recurring(&recurring)?;
+        return Ok(());
+    }
 
-    // Get the $HOME/.cache/crust path
-    let mut filedir: std::path::PathBuf = match dirs::cache_dir() {
-        Some(v) => v,
-        None => {
-            return empty;
-        }
-    };
-    filedir.push("crust");
+    // Read the current entries of the date, if any.
+    let mut agenda: Agenda = storage.load_day(&program_args.date)?;
+    add_event(&mut agenda, program_args, None);
+    storage.save_day(&program_args.date, &agenda)?;
+    return Ok(());
+}
 
-    // Get a list of files in that directory
-    let paths = match std::fs::read_dir(filedir) {
-        Ok(v) => v,
-        Err(_) => {
-            return empty;
+/// Add the single event described by the program arguments to `agenda`, with the `uid` of the
+/// event it replaces if any.
+///
+/// The repetition rule is not used, see `write_entry`.
+pub fn add_event(
+    agenda: &mut Agenda,
+    program_args: &args::parser::ProgramArguments,
+    uid: Option<String>,
+) {
+    // Check if the entry is meant for the entire day, or with time stamps, add the entry
+    // accordingly.
+    match program_args.start {
+        Some(start) if !program_args.flags.full_day => {
+            agenda.timestamp.push(TimedEvent {
+                start,
+                end: program_args.end,
+                event: program_args.entry.clone(),
+                uid,
+            });
+            agenda.timestamp.sort();
         }
-    };
-
-    // Define a regular expression to extract the correct file name structure with capture groups for
-    // the day, month and year.
-    let re = match regex::Regex::new(
-        r"^([0-3][0-9])(?:-)([0-1][0-9])(?:-)([0-9][0-9][0-9][0-9])(?:\.toml)$",
-    ) {
-        Ok(r) => r,
-        Err(_) => {
-            return empty;
-        }
-    };
-
-    // Go through every file in the $HOME/.cache/crust/ directory. If an error occurs during one of
-    // the steps in the loop, go to the next iteration.
-    for path in paths {
-        let path_os_string = match path {
-            Ok(p) => p.file_name(),
-            Err(_) => {
-                continue;
-            }
-        };
-        let filename = match path_os_string.to_str() {
-            Some(f) => f,
-            None => {
-                continue;
-            }
-        };
-
-        // Check if the filename matches with the regular expression.
-        if re.is_match(&filename) {
-            // Capture the date month and year.
-            let caps = match re.captures(&filename) {
-                Some(c) => c,
-                None => {
-                    continue;
-                }
-            };
-
-            let day = match caps[1].parse::<i32>() {
-                Ok(d) => d,
-                Err(_) => {
-                    continue;
-                }
-            };
-
-            let month = match caps[2].parse::<i32>() {
-                Ok(m) => m,
-                Err(_) => {
-                    continue;
-                }
-            };
-
-            let year = match caps[3].parse::<i32>() {
-                Ok(y) => y,
-                Err(_) => {
-                    continue;
-                }
-            };
-
-            // Define the date from the filename.
-            let file_date: date::Date = date::Date { day, month, year };
-
-            // Check if the `Date` is valid before pushing it onto the vector.
-            if file_date.validate() {
-                valid_entries.push(file_date);
-            }
+        _ => {
+            agenda.day.push(DayEvent {
+                event: program_args.entry.clone(),
+                uid,
+            });
         }
     }
-    return valid_entries;
 }
 
-/// Create and write to file in $HOME/.cache/crust/ to store agenda entries.
-pub fn write_entry(program_args: &args::parser::ProgramArguments) {
-    // Get the file directory
-    let filedir = program_args.date.to_filepath();
-
-    // Open file if it exists, create and open if it does not.
-    let mut file = match std::fs::OpenOptions::new()
-        .create(true)
-        .write(true)
-        .append(true)
-        .open(filedir)
-    {
-        Ok(v) => v,
-        Err(e) => {
-            eprintln!("{}", e);
-            panic!("Error when attempting to open or create file.");
-        }
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // Check if the entry is meant for the entire day, or with time stamps, write the
-    // entry accordingly.
-    if program_args.flags.full_day {
-        match file.write(format!("[[day]]\nevent = '{}'\n", program_args.entry).as_bytes()) {
-            Ok(_) => {}
-            Err(e) => {
-                eprintln!("{}", e);
-                panic!("Error when attempting to write to file.");
-            }
-        };
-    } else {
-        match file.write(
-            format!(
-                "[[timestamp]]\nstart = '{}'\nend = '{}'\nevent = '{}'\n",
-                program_args.start, program_args.end, program_args.entry
-            )
-            .as_bytes(),
-        ) {
-            Ok(_) => {}
-            Err(e) => {
-                eprintln!("{}", e);
-                panic!("Error when attempting to write to file.");
-            }
-        };
+    #[test]
+    fn legacy_empty_start_is_a_full_day_event() {
+        let contents = "[[day]]\nevent = 'Holiday'\n\n\
+                        [[timestamp]]\nstart = ''\nend = ''\nevent = 'Call mum'\n\n\
+                        [[timestamp]]\nstart = '09:00'\nend = ''\nevent = 'Meeting'\n";
+        let agenda: Agenda = toml::from_str(contents).unwrap();
+        let days: Vec<&str> = agenda.day.iter().map(|e| e.event.as_str()).collect();
+        assert_eq!(days, vec!["Holiday", "Call mum"]);
+        assert_eq!(agenda.timestamp.len(), 1);
+        assert_eq!(agenda.timestamp[0].event, "Meeting");
+        assert_eq!(agenda.timestamp[0].start, time::Time::new(9, 0).unwrap());
+        assert_eq!(agenda.timestamp[0].end, None);
     }
-}
 
-/// Open system default `$EDITOR` for date specified by program arguments.
-pub fn open_editor(program_args: &args::parser::ProgramArguments) {
-    let filepath = program_args.date.to_filepath();
-    let editor = match std::env::var("EDITOR") {
-        Ok(v) => v,
-        Err(e) => {
-            eprintln!("{}", e);
-            panic!("Error obtaining system default $EDITOR.");
-        }
-    };
-    match std::process::Command::new(editor).arg(&filepath).status() {
-        Ok(_) => {}
-        Err(e) => {
-            eprintln!("{}", e);
-            panic!("Error opening file with default $EDITOR");
-        }
-    };
-}
+    #[test]
+    fn timed_events_need_a_valid_start() {
+        assert!(toml::from_str::<Agenda>("[[timestamp]]\nevent = 'No start'\n").is_err());
+        assert!(toml::from_str::<Agenda>("[[timestamp]]\nstart = 'noon'\nevent = 'x'\n").is_err());
+    }
 
-/// Remove file corresponding to date specified by program arguments.
-pub fn remove_file(program_args: &args::parser::ProgramArguments) {
-    let filepath = program_args.date.to_filepath();
-    if filepath.exists() {
-        match std::fs::remove_file(filepath) {
-            Ok(_) => {}
-            Err(e) => {
-                eprintln!("{}", e);
-                panic!("Error while attempting to delete file.");
-            }
-        }
-    } else {
-        panic!("File to delete does not exist.");
+    #[test]
+    fn agenda_round_trip() {
+        let agenda = Agenda {
+            day: vec![DayEvent {
+                event: "Holiday".to_string(),
+                uid: Some("abc@example.com".to_string()),
+            }],
+            timestamp: vec![TimedEvent {
+                start: time::Time::new(9, 30).unwrap(),
+                end: Some(time::Time::new(10, 0).unwrap()),
+                event: "Meeting".to_string(),
+                uid: None,
+            }],
+        };
+        let contents = toml::to_string(&agenda).unwrap();
+        assert_eq!(toml::from_str::<Agenda>(&contents).unwrap(), agenda);
+        assert_eq!(toml::from_str::<Agenda>("").unwrap(), Agenda::default());
     }
 }
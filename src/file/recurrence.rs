@@ -0,0 +1,698 @@
+// External crates
+extern crate chrono;
+extern crate serde;
+extern crate toml;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// Local files
+use crate::date::date;
+use crate::date::time;
+use crate::file::parser;
+
+/// Name of the file in the data directory that stores the recurring event definitions.
+pub const RECURRING_FILENAME: &str = "recurring.toml";
+
+/// How often a recurring event repeats, mirroring the iCalendar `FREQ` values.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl std::str::FromStr for Frequency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "daily" => Ok(Frequency::Daily),
+            "weekly" => Ok(Frequency::Weekly),
+            "monthly" => Ok(Frequency::Monthly),
+            "yearly" => Ok(Frequency::Yearly),
+            _ => Err(format!(
+                "invalid frequency `{}`, expected daily, weekly, monthly or yearly",
+                s
+            )),
+        }
+    }
+}
+
+/// Weekday rule mirroring the iCalendar `BYDAY` values, e.g. `MO`, `2TU` or `-1FR`.
+///
+/// The `weekday` uses the same convention as `Date::day_of_week`, `Monday = 1` and `Sunday = 7`.
+/// The optional `ordinal` selects the n-th such weekday in the month, counting from the end of
+/// the month when negative. It is only meaningful for monthly and yearly events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByDay {
+    pub ordinal: Option<i32>,
+    pub weekday: i32,
+}
+
+const WEEKDAY_CODES: [&str; 7] = ["MO", "TU", "WE", "TH", "FR", "SA", "SU"];
+
+impl std::str::FromStr for ByDay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.trim().to_uppercase();
+        if value.len() < 2 || !value.is_char_boundary(value.len() - 2) {
+            return Err(format!("invalid weekday rule `{}`", s));
+        }
+        let (ordinal_str, code) = value.split_at(value.len() - 2);
+        let weekday = match WEEKDAY_CODES.iter().position(|c| *c == code) {
+            Some(i) => i as i32 + 1,
+            None => {
+                return Err(format!(
+                    "invalid weekday rule `{}`, expected MO, TU, WE, TH, FR, SA or SU",
+                    s
+                ));
+            }
+        };
+        let ordinal = if ordinal_str.is_empty() {
+            None
+        } else {
+            match ordinal_str.trim_start_matches('+').parse::<i32>() {
+                Ok(o) if o != 0 && o.abs() <= 53 => Some(o),
+                _ => {
+                    return Err(format!("invalid weekday ordinal in `{}`", s));
+                }
+            }
+        };
+        return Ok(ByDay { ordinal, weekday });
+    }
+}
+
+impl std::fmt::Display for ByDay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let code = WEEKDAY_CODES[(self.weekday - 1) as usize];
+        match self.ordinal {
+            Some(o) => write!(f, "{}{}", o, code),
+            None => write!(f, "{}", code),
+        }
+    }
+}
+
+impl Serialize for ByDay {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for ByDay {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        value.parse::<ByDay>().map_err(serde::de::Error::custom)
+    }
+}
+
+fn default_interval() -> u32 {
+    1
+}
+
+fn is_default_interval(interval: &u32) -> bool {
+    *interval == 1
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Definition of a recurring event, modelled after the iCalendar `RRULE`.
+///
+/// The event first occurs on `first` and then repeats every `interval` days, weeks, months or
+/// years depending on the `frequency`. The repetition is limited by `count` occurrences and/or
+/// the `until` date, both inclusive. Dates in `exceptions` are skipped, but still count towards
+/// `count` as in RFC 5545.
+///
+/// When `by_day` is empty the weekday, day of the month or date of `first` is repeated.
+/// Otherwise:
+/// - Daily events only occur on the listed weekdays.
+/// - Weekly events occur on every listed weekday of the matching weeks.
+/// - Monthly events occur on the listed (n-th) weekdays of the matching months.
+/// - Yearly events occur on the listed (n-th) weekdays in the month of `first`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Recurrence {
+    #[serde(default)]
+    pub id: String,
+    pub event: String,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub full_day: bool,
+    #[serde(
+        default,
+        deserialize_with = "time::deserialize_optional",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub start: Option<time::Time>,
+    #[serde(
+        default,
+        deserialize_with = "time::deserialize_optional",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub end: Option<time::Time>,
+    pub frequency: Frequency,
+    #[serde(
+        default = "default_interval",
+        skip_serializing_if = "is_default_interval"
+    )]
+    pub interval: u32,
+    pub first: date::Date,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub by_day: Vec<ByDay>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<date::Date>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exceptions: Vec<date::Date>,
+}
+
+/// Deserialization struct for `recurring.toml`.
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct RecurringEvents {
+    pub recurring: Vec<Recurrence>,
+}
+
+impl Recurrence {
+    /// Check if `date` satisfies the repetition rule, ignoring `count`, `until` and `exceptions`.
+    fn matches_rule(&self, date: &date::Date) -> bool {
+        if *date < self.first {
+            return false;
+        }
+        let interval = self.interval.max(1) as i32;
+        let weekday = date.day_of_week();
+        let weekday_listed = |ordinal_allowed: bool| -> bool {
+            self.by_day.iter().any(|by_day| {
+                by_day.weekday == weekday
+                    && match by_day.ordinal {
+                        Some(o) if ordinal_allowed => nth_weekday_matches(date, o),
+                        _ => true,
+                    }
+            })
+        };
+
+        match self.frequency {
+            Frequency::Daily => {
+                if self.first.days_until(date) % interval != 0 {
+                    return false;
+                }
+                return self.by_day.is_empty() || weekday_listed(false);
+            }
+            Frequency::Weekly => {
+                // Count weeks between the Mondays of both weeks.
+                let days_between_mondays =
+                    self.first.days_until(date) + self.first.day_of_week() - weekday;
+                let weeks = days_between_mondays / 7;
+                if weeks % interval != 0 {
+                    return false;
+                }
+                if self.by_day.is_empty() {
+                    return weekday == self.first.day_of_week();
+                } else {
+                    return weekday_listed(false);
+                }
+            }
+            Frequency::Monthly => {
+                let months =
+                    (date.year * 12 + date.month) - (self.first.year * 12 + self.first.month);
+                if months % interval != 0 {
+                    return false;
+                }
+                if self.by_day.is_empty() {
+                    return date.day == self.first.day;
+                } else {
+                    return weekday_listed(true);
+                }
+            }
+            Frequency::Yearly => {
+                if (date.year - self.first.year) % interval != 0 {
+                    return false;
+                }
+                if date.month != self.first.month {
+                    return false;
+                }
+                if self.by_day.is_empty() {
+                    return date.day == self.first.day;
+                } else {
+                    return weekday_listed(true);
+                }
+            }
+        }
+    }
+
+    /// Check that the rule can be used, returning an error message otherwise, see
+    /// `validate_by_day`.
+    pub fn validate(&self) -> Result<(), String> {
+        return validate_by_day(self.frequency, &self.by_day);
+    }
+
+    /// Obtain all dates between `from` and `to`, both inclusive, on which the event occurs.
+    pub fn occurrences(&self, from: &date::Date, to: &date::Date) -> Vec<date::Date> {
+        let mut dates: Vec<date::Date> = Vec::new();
+        let last: date::Date = match self.until {
+            Some(until) if until < *to => until,
+            _ => *to,
+        };
+
+        // With a `count` we need to walk from the first occurrence to know how many occurrences
+        // precede `from`, otherwise we can start in the period of `from`.
+        let first_period = if self.count.is_some() || *from <= self.first {
+            0
+        } else {
+            self.periods_until(from)
+        };
+        let mut counted: u32 = 0;
+        self.walk(first_period, &last, |current| {
+            counted += 1;
+            if let Some(count) = self.count {
+                if counted > count {
+                    return false;
+                }
+            }
+            if current >= *from && !self.exceptions.contains(&current) {
+                dates.push(current);
+            }
+            return true;
+        });
+        return dates;
+    }
+
+    /// Obtain the first date on which the event occurs, counting dates in `exceptions`, or `None`
+    /// if it never occurs.
+    ///
+    /// `first` itself does not have to satisfy the rule, a weekly event on Mondays that starts on
+    /// a Wednesday first occurs on the next Monday. Rules are searched up to eight years after
+    /// `first`, the longest gap between two leap days.
+    pub fn first_occurrence(&self) -> Option<date::Date> {
+        if self.count == Some(0) {
+            return None;
+        }
+        let mut last = self.first;
+        last.add_months(12 * 8);
+        if let Some(until) = self.until {
+            last = last.min(until);
+        }
+        let mut found: Option<date::Date> = None;
+        self.walk(0, &last, |current| {
+            found = Some(current);
+            return false;
+        });
+        return found;
+    }
+
+    /// Call `visit` with the dates up to `last` that satisfy the rule, in order, starting in the
+    /// period `period`, see `period_start`. Stops as soon as `visit` returns `false`.
+    ///
+    /// Only the periods of the rule are visited, every `interval` days, weeks, months or years,
+    /// so the cost does not depend on the number of days since `first`.
+    fn walk(&self, mut period: i64, last: &date::Date, mut visit: impl FnMut(date::Date) -> bool) {
+        while let Some(start) = self.period_start(period) {
+            if start > *last {
+                return;
+            }
+            for current in self.candidates(&start) {
+                if current > *last {
+                    return;
+                }
+                if self.matches_rule(&current) && !visit(current) {
+                    return;
+                }
+            }
+            period += 1;
+        }
+    }
+
+    /// Obtain the first day of the period `n` of the rule, counting from the period of `first`.
+    ///
+    /// Periods are `interval` days, weeks starting on Monday, months or years long. Yearly periods
+    /// start on the first day of the month of `first`. Returns `None` past the supported dates.
+    fn period_start(&self, n: i64) -> Option<date::Date> {
+        let steps = n.checked_mul(self.interval.max(1) as i64)?;
+        let mut start = self.first;
+        match self.frequency {
+            Frequency::Daily | Frequency::Weekly => {
+                let mut days = steps;
+                if self.frequency == Frequency::Weekly {
+                    days = days.checked_mul(7)?;
+                    // Go back to the Monday of the week of `first`.
+                    days -= (self.first.day_of_week() - 1) as i64;
+                }
+                let shifted = self
+                    .first
+                    .to_naive_date()?
+                    .checked_add_signed(chrono::TimeDelta::try_days(days)?)?;
+                start = date::Date::from_naive_date(&shifted);
+            }
+            Frequency::Monthly | Frequency::Yearly => {
+                let months = match self.frequency {
+                    Frequency::Monthly => steps,
+                    _ => steps.checked_mul(12)?,
+                };
+                start.day = 1;
+                start.add_months(i32::try_from(months).ok()?);
+            }
+        }
+        return Some(start);
+    }
+
+    /// Obtain the number of whole periods from the period of `first` to the period of `date`,
+    /// rounded down. `date` should not lie before `first`.
+    fn periods_until(&self, date: &date::Date) -> i64 {
+        let units: i64 = match self.frequency {
+            Frequency::Daily => self.first.days_until(date) as i64,
+            Frequency::Weekly => {
+                (self.first.days_until(date) + self.first.day_of_week() - 1) as i64 / 7
+            }
+            Frequency::Monthly => {
+                ((date.year - self.first.year) * 12 + date.month - self.first.month) as i64
+            }
+            Frequency::Yearly => {
+                let before_month = date.month < self.first.month;
+                (date.year - self.first.year - before_month as i32) as i64
+            }
+        };
+        return units.max(0) / self.interval.max(1) as i64;
+    }
+
+    /// Obtain the dates of the period starting at `start` on which the event can occur, sorted.
+    ///
+    /// Only the weekdays or day of the month of the rule are listed, `matches_rule` decides which
+    /// of them are occurrences.
+    fn candidates(&self, start: &date::Date) -> Vec<date::Date> {
+        let mut weekdays: Vec<i32> = self.by_day.iter().map(|b| b.weekday).collect();
+        weekdays.sort();
+        weekdays.dedup();
+        let mut dates: Vec<date::Date> = Vec::new();
+        match self.frequency {
+            Frequency::Daily => dates.push(*start),
+            Frequency::Weekly => {
+                if weekdays.is_empty() {
+                    weekdays.push(self.first.day_of_week());
+                }
+                let mut current = *start;
+                for weekday in 1..=7 {
+                    if weekdays.contains(&weekday) {
+                        dates.push(current);
+                    }
+                    current.increment();
+                }
+            }
+            Frequency::Monthly | Frequency::Yearly => {
+                let mut current = *start;
+                while current.month == start.month {
+                    if weekdays.contains(&current.day_of_week())
+                        || (weekdays.is_empty() && current.day == self.first.day)
+                    {
+                        dates.push(current);
+                    }
+                    current.increment();
+                }
+            }
+        }
+        return dates;
+    }
+
+    /// Check if the event occurs on `date`.
+    pub fn occurs_on(&self, date: &date::Date) -> bool {
+        return !self.occurrences(date, date).is_empty();
+    }
+
+    /// Add the event to `agenda` as a full day or timed event.
+    ///
+    /// Events that are not full day events, but have no start time either, are added as full day
+    /// events.
+    pub fn add_to_agenda(&self, agenda: &mut parser::Agenda) {
+        match self.start {
+            Some(start) if !self.full_day => {
+                agenda.timestamp.push(parser::TimedEvent {
+                    start,
+                    end: self.end,
+                    event: self.event.clone(),
+                    uid: None,
+                });
+            }
+            _ => {
+                agenda.day.push(parser::DayEvent {
+                    event: self.event.clone(),
+                    uid: None,
+                });
+            }
+        }
+    }
+}
+
+/// Check that the weekday rules `by_day` can be used by events repeating with `frequency`.
+///
+/// Ordinals of weekdays, such as `2TU`, select a weekday within a month, so only monthly and
+/// yearly events take them.
+pub fn validate_by_day(frequency: Frequency, by_day: &[ByDay]) -> Result<(), String> {
+    if matches!(frequency, Frequency::Daily | Frequency::Weekly) {
+        if let Some(b) = by_day.iter().find(|b| b.ordinal.is_some()) {
+            return Err(format!(
+                "`{}` selects a weekday of the month, which only monthly and yearly events can \
+                 use",
+                b
+            ));
+        }
+    }
+    return Ok(());
+}
+
+/// Check if `date` is the n-th occurrence of its weekday in its month, counting from the end of
+/// the month when `ordinal` is negative.
+fn nth_weekday_matches(date: &date::Date, ordinal: i32) -> bool {
+    if ordinal > 0 {
+        return (date.day - 1) / 7 + 1 == ordinal;
+    } else {
+        return (date.max_day() - date.day) / 7 + 1 == -ordinal;
+    }
+}
+
+/// Create a short identifier for a new recurring event.
+pub fn new_id(event: &str) -> String {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    event.hash(&mut hasher);
+    if let Ok(d) = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        d.as_nanos().hash(&mut hasher);
+    }
+    return format!("{:08x}", hasher.finish() as u32);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(day: i32, month: i32, year: i32) -> date::Date {
+        return date::Date { day, month, year };
+    }
+
+    fn rule(frequency: Frequency, first: date::Date) -> Recurrence {
+        return Recurrence {
+            id: "test".to_string(),
+            event: "Test".to_string(),
+            full_day: true,
+            start: None,
+            end: None,
+            frequency,
+            interval: 1,
+            first,
+            by_day: Vec::new(),
+            count: None,
+            until: None,
+            exceptions: Vec::new(),
+        };
+    }
+
+    fn by_day(codes: &str) -> Vec<ByDay> {
+        return codes.split(',').map(|c| c.parse().unwrap()).collect();
+    }
+
+    #[test]
+    fn matches_rule_rejects_dates_before_first() {
+        let r = rule(Frequency::Daily, d(5, 1, 2026));
+        assert!(!r.matches_rule(&d(4, 1, 2026)));
+        assert!(r.matches_rule(&d(5, 1, 2026)));
+    }
+
+    #[test]
+    fn matches_rule_daily_interval() {
+        let mut r = rule(Frequency::Daily, d(30, 12, 2025));
+        r.interval = 3;
+        assert!(r.matches_rule(&d(2, 1, 2026)));
+        assert!(!r.matches_rule(&d(3, 1, 2026)));
+        assert!(r.matches_rule(&d(5, 1, 2026)));
+    }
+
+    #[test]
+    fn weekly_on_listed_weekdays() {
+        // 5 January 2026 is a Monday.
+        let mut r = rule(Frequency::Weekly, d(5, 1, 2026));
+        r.by_day = by_day("MO,TH");
+        assert_eq!(
+            r.occurrences(&d(1, 1, 2026), &d(18, 1, 2026)),
+            vec![d(5, 1, 2026), d(8, 1, 2026), d(12, 1, 2026), d(15, 1, 2026)]
+        );
+    }
+
+    #[test]
+    fn weekly_interval_counts_weeks() {
+        let mut r = rule(Frequency::Weekly, d(5, 1, 2026));
+        r.interval = 2;
+        assert_eq!(
+            r.occurrences(&d(1, 1, 2026), &d(31, 1, 2026)),
+            vec![d(5, 1, 2026), d(19, 1, 2026)]
+        );
+    }
+
+    #[test]
+    fn monthly_last_weekday() {
+        let mut r = rule(Frequency::Monthly, d(1, 1, 2026));
+        r.by_day = by_day("-1FR");
+        assert_eq!(
+            r.occurrences(&d(1, 1, 2026), &d(31, 3, 2026)),
+            vec![d(30, 1, 2026), d(27, 2, 2026), d(27, 3, 2026)]
+        );
+    }
+
+    #[test]
+    fn monthly_skips_months_without_the_day() {
+        let r = rule(Frequency::Monthly, d(31, 1, 2026));
+        assert_eq!(
+            r.occurrences(&d(1, 1, 2026), &d(30, 4, 2026)),
+            vec![d(31, 1, 2026), d(31, 3, 2026)]
+        );
+    }
+
+    #[test]
+    fn yearly_on_leap_day() {
+        let r = rule(Frequency::Yearly, d(29, 2, 2024));
+        assert_eq!(
+            r.occurrences(&d(1, 1, 2024), &d(31, 12, 2028)),
+            vec![d(29, 2, 2024), d(29, 2, 2028)]
+        );
+    }
+
+    #[test]
+    fn exceptions_count_towards_count() {
+        let mut r = rule(Frequency::Daily, d(1, 1, 2026));
+        r.count = Some(3);
+        r.exceptions = vec![d(2, 1, 2026)];
+        assert_eq!(
+            r.occurrences(&d(1, 1, 2026), &d(31, 1, 2026)),
+            vec![d(1, 1, 2026), d(3, 1, 2026)]
+        );
+        // Occurrences before `from` still count.
+        assert_eq!(
+            r.occurrences(&d(3, 1, 2026), &d(31, 1, 2026)),
+            vec![d(3, 1, 2026)]
+        );
+    }
+
+    #[test]
+    fn until_is_inclusive() {
+        let mut r = rule(Frequency::Daily, d(1, 1, 2026));
+        r.until = Some(d(3, 1, 2026));
+        assert_eq!(
+            r.occurrences(&d(1, 1, 2026), &d(31, 1, 2026)),
+            vec![d(1, 1, 2026), d(2, 1, 2026), d(3, 1, 2026)]
+        );
+        assert!(!r.occurs_on(&d(4, 1, 2026)));
+    }
+
+    /// Occurrences found by checking every day from `first`, to compare the faster walk against.
+    fn naive_occurrences(r: &Recurrence, from: &date::Date, to: &date::Date) -> Vec<date::Date> {
+        let mut dates = Vec::new();
+        let mut counted = 0;
+        let mut current = r.first;
+        while current <= *to && r.until.is_none_or(|until| current <= until) {
+            if r.matches_rule(&current) {
+                counted += 1;
+                if r.count.is_some_and(|count| counted > count) {
+                    break;
+                }
+                if current >= *from && !r.exceptions.contains(&current) {
+                    dates.push(current);
+                }
+            }
+            current.increment();
+        }
+        return dates;
+    }
+
+    #[test]
+    fn occurrences_match_a_day_by_day_walk() {
+        let mut rules: Vec<Recurrence> = Vec::new();
+        for frequency in [
+            Frequency::Daily,
+            Frequency::Weekly,
+            Frequency::Monthly,
+            Frequency::Yearly,
+        ] {
+            for interval in [1, 2, 3] {
+                for days in ["", "MO,TH", "SU"] {
+                    let mut r = rule(frequency, d(31, 1, 2024));
+                    r.interval = interval;
+                    if !days.is_empty() {
+                        r.by_day = by_day(days);
+                    }
+                    rules.push(r.clone());
+                    r.count = Some(7);
+                    r.exceptions = vec![d(1, 2, 2024), d(5, 2, 2024)];
+                    rules.push(r);
+                }
+            }
+        }
+        let mut r = rule(Frequency::Monthly, d(29, 2, 2024));
+        r.by_day = by_day("-1FR,2TU");
+        rules.push(r);
+        for r in rules.iter() {
+            for (from, to) in [
+                (d(1, 1, 2024), d(31, 12, 2024)),
+                (d(10, 3, 2025), d(20, 9, 2027)),
+                (d(1, 2, 2024), d(1, 2, 2024)),
+            ] {
+                assert_eq!(
+                    r.occurrences(&from, &to),
+                    naive_occurrences(r, &from, &to),
+                    "{:?} from {} to {}",
+                    r,
+                    from,
+                    to
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn counted_occurrences_far_from_first() {
+        let mut r = rule(Frequency::Daily, d(1, 1, 1900));
+        r.count = Some(3);
+        assert!(r.occurrences(&d(1, 1, 2026), &d(31, 12, 2026)).is_empty());
+        assert!(r.occurs_on(&d(3, 1, 1900)));
+        assert!(!r.occurs_on(&d(4, 1, 1900)));
+    }
+
+    #[test]
+    fn validate_rejects_ordinals_of_daily_and_weekly_events() {
+        let mut r = rule(Frequency::Weekly, d(5, 1, 2026));
+        r.by_day = by_day("MO,2TU");
+        assert!(r.validate().is_err());
+        r.frequency = Frequency::Daily;
+        assert!(r.validate().is_err());
+        r.frequency = Frequency::Monthly;
+        assert!(r.validate().is_ok());
+        r.frequency = Frequency::Weekly;
+        r.by_day = by_day("MO,TU");
+        assert!(r.validate().is_ok());
+    }
+}
@@ -0,0 +1,457 @@
+// External crates
+extern crate chrono;
+
+use std::collections::BTreeMap;
+
+// Local files
+use crate::date::date;
+use crate::date::time;
+use crate::file::recurrence;
+use crate::output::records;
+use crate::storage::calendar;
+use crate::storage::storage;
+
+/// Maximum length of a content line in octets, excluding the line break.
+const MAX_LINE_OCTETS: usize = 75;
+
+/// Export all agenda entries between `from` and `to`, both inclusive, as an iCalendar file.
+///
+/// Full day events are exported with `VALUE=DATE` start and end dates, timed events with floating
+/// local start and end times. Recurring events with an occurrence in the range are exported once
+/// with their `RRULE`, starting at their first occurrence. Events that cannot be read are left
+/// out, and reported in the returned vector of warnings. The entries of all `calendars` are
+/// exported together.
+pub fn export_ics(
+    calendars: &[calendar::Calendar],
+    from: &date::Date,
+    to: &date::Date,
+) -> (String, Vec<String>) {
+    let mut lines: Vec<String> = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:-//crust//crust {}//EN", env!("CARGO_PKG_VERSION")),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+    let mut warnings: Vec<String> = Vec::new();
+    let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    for cal in calendars.iter() {
+        push_events(
+            cal.storage.as_ref(),
+            from,
+            to,
+            &stamp,
+            &mut lines,
+            &mut warnings,
+        );
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut output = String::new();
+    for line in lines.iter() {
+        output.push_str(&fold_line(line));
+    }
+    return (output, warnings);
+}
+
+/// Add the events of `storage` with an occurrence between `from` and `to` to `lines`, see
+/// `export_ics`.
+fn push_events(
+    storage: &dyn storage::Storage,
+    from: &date::Date,
+    to: &date::Date,
+    stamp: &str,
+    lines: &mut Vec<String>,
+    warnings: &mut Vec<String>,
+) {
+    let mut imported_days: BTreeMap<(String, String), Vec<date::Date>> = BTreeMap::new();
+
+    let stored_dates = match storage.list_dates(from, to) {
+        Ok(d) => d,
+        Err(e) => {
+            warnings.push(e);
+            Vec::new()
+        }
+    };
+    for entry_date in stored_dates.iter() {
+        let agenda = match storage.load_day(entry_date) {
+            Ok(a) => a,
+            Err(e) => {
+                warnings.push(format!("{}: {}", entry_date, e));
+                continue;
+            }
+        };
+
+        for day_event in agenda.day.iter().filter(|e| !e.event.is_empty()) {
+            match &day_event.uid {
+                // Imported events spanning several days are stored on each day, they are
+                // combined again below.
+                Some(uid) => imported_days
+                    .entry((uid.clone(), day_event.event.clone()))
+                    .or_default()
+                    .push(*entry_date),
+                None => {
+                    let uid = generated_uid(&[&entry_date.to_string(), &day_event.event]);
+                    push_day_event(lines, &uid, stamp, entry_date, entry_date, &day_event.event);
+                }
+            }
+        }
+
+        for timed_event in agenda.timestamp.iter().filter(|e| !e.event.is_empty()) {
+            let (start, end) = (timed_event.start, timed_event.end);
+            let uid = match &timed_event.uid {
+                Some(u) => u.clone(),
+                None => generated_uid(&[
+                    &entry_date.to_string(),
+                    &start.to_string(),
+                    &end.map(|e| e.to_string()).unwrap_or_default(),
+                    &timed_event.event,
+                ]),
+            };
+            lines.push("BEGIN:VEVENT".to_string());
+            lines.push(format!("UID:{}", escape_text(&uid)));
+            lines.push(format!("DTSTAMP:{}", stamp));
+            lines.push(format!("DTSTART:{}", ics_date_time(entry_date, start)));
+            lines.push(format!("DTEND:{}", ics_end(entry_date, start, end)));
+            lines.push(format!("SUMMARY:{}", escape_text(&timed_event.event)));
+            lines.push("END:VEVENT".to_string());
+        }
+    }
+
+    // Emit a single event for every run of consecutive days. If the days of an imported event are
+    // not consecutive, e.g. when only part of it lies in the range, the later runs get a UID of
+    // their own.
+    for ((uid, event), dates) in imported_days.iter() {
+        let mut run_start: date::Date = dates[0];
+        let mut run_end: date::Date = dates[0];
+        let mut run_uid: String = uid.clone();
+        for d in dates.iter().skip(1) {
+            if run_end.days_until(d) == 1 {
+                run_end = *d;
+                continue;
+            }
+            push_day_event(lines, &run_uid, stamp, &run_start, &run_end, event);
+            run_start = *d;
+            run_end = *d;
+            run_uid = format!("{}-{}", uid, ics_date(d));
+        }
+        push_day_event(lines, &run_uid, stamp, &run_start, &run_end, event);
+    }
+
+    let recurring = match storage.load_recurring() {
+        Ok(r) => r,
+        Err(e) => {
+            warnings.push(e);
+            Vec::new()
+        }
+    };
+    for definition in recurring.iter() {
+        if definition.occurrences(from, to).is_empty() {
+            continue;
+        }
+        let uid = if definition.id.contains('@') {
+            definition.id.clone()
+        } else {
+            format!("{}@crust", definition.id)
+        };
+        // DTSTART is always an occurrence in iCalendar, which `first` does not have to be.
+        let first = definition.first_occurrence().unwrap_or(definition.first);
+        // Repeating events without a start time are exported as full day events.
+        let times: Option<(time::Time, Option<time::Time>)> = match definition.start {
+            Some(start) if !definition.full_day => Some((start, definition.end)),
+            _ => None,
+        };
+
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", escape_text(&uid)));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        match times {
+            Some((start, end)) => {
+                lines.push(format!("DTSTART:{}", ics_date_time(&first, start)));
+                lines.push(format!("DTEND:{}", ics_end(&first, start, end)));
+            }
+            _ => {
+                let mut next_day = first;
+                next_day.increment();
+                lines.push(format!("DTSTART;VALUE=DATE:{}", ics_date(&first)));
+                lines.push(format!("DTEND;VALUE=DATE:{}", ics_date(&next_day)));
+            }
+        }
+        lines.push(format!("RRULE:{}", rrule(definition, times)));
+        if !definition.exceptions.is_empty() {
+            let exdates: Vec<String> = definition
+                .exceptions
+                .iter()
+                .map(|d| match times {
+                    Some((start, _)) => ics_date_time(d, start),
+                    _ => ics_date(d),
+                })
+                .collect();
+            if times.is_none() {
+                lines.push(format!("EXDATE;VALUE=DATE:{}", exdates.join(",")));
+            } else {
+                lines.push(format!("EXDATE:{}", exdates.join(",")));
+            }
+        }
+        lines.push(format!("SUMMARY:{}", escape_text(&definition.event)));
+        lines.push("END:VEVENT".to_string());
+    }
+}
+
+/// Add a full day event lasting from `first` up to and including `last` to `lines`.
+fn push_day_event(
+    lines: &mut Vec<String>,
+    uid: &str,
+    stamp: &str,
+    first: &date::Date,
+    last: &date::Date,
+    event: &str,
+) {
+    // The end date of all-day events is exclusive.
+    let mut end = *last;
+    end.increment();
+    lines.push("BEGIN:VEVENT".to_string());
+    lines.push(format!("UID:{}", escape_text(uid)));
+    lines.push(format!("DTSTAMP:{}", stamp));
+    lines.push(format!("DTSTART;VALUE=DATE:{}", ics_date(first)));
+    lines.push(format!("DTEND;VALUE=DATE:{}", ics_date(&end)));
+    lines.push(format!("SUMMARY:{}", escape_text(event)));
+    lines.push("END:VEVENT".to_string());
+}
+
+/// Build the `RRULE` value of a recurring event.
+///
+/// `COUNT` and `UNTIL` may not occur together, when both are set only the one that ends the event
+/// first is used. Ordinals of weekdays are only used by monthly and yearly events, they are left
+/// out of daily and weekly rules where iCalendar does not allow them.
+fn rrule(
+    definition: &recurrence::Recurrence,
+    times: Option<(time::Time, Option<time::Time>)>,
+) -> String {
+    let frequency = match definition.frequency {
+        recurrence::Frequency::Daily => "DAILY",
+        recurrence::Frequency::Weekly => "WEEKLY",
+        recurrence::Frequency::Monthly => "MONTHLY",
+        recurrence::Frequency::Yearly => "YEARLY",
+    };
+    let mut parts: Vec<String> = vec![format!("FREQ={}", frequency)];
+    if definition.interval > 1 {
+        parts.push(format!("INTERVAL={}", definition.interval));
+    }
+    if !definition.by_day.is_empty() {
+        let ordinals = matches!(
+            definition.frequency,
+            recurrence::Frequency::Monthly | recurrence::Frequency::Yearly
+        );
+        let mut by_day: Vec<String> = Vec::new();
+        for b in definition.by_day.iter() {
+            let mut value = *b;
+            if !ordinals {
+                value.ordinal = None;
+            }
+            if !by_day.contains(&value.to_string()) {
+                by_day.push(value.to_string());
+            }
+        }
+        parts.push(format!("BYDAY={}", by_day.join(",")));
+        // Yearly events with weekday rules repeat in the month of the first occurrence.
+        if definition.frequency == recurrence::Frequency::Yearly {
+            parts.push(format!("BYMONTH={}", definition.first.month));
+        }
+    }
+    let mut until = definition.until;
+    if let Some(count) = definition.count {
+        if let Some(last) = until {
+            // Exceptions count towards `count`, so they are included to find the last occurrence.
+            let mut rule = definition.clone();
+            rule.exceptions.clear();
+            if rule.occurrences(&rule.first, &last).len() >= count as usize {
+                until = None;
+            }
+        }
+        if until.is_none() {
+            parts.push(format!("COUNT={}", count));
+        }
+    }
+    if let Some(until) = until {
+        // UNTIL has the same value type as DTSTART.
+        match times {
+            Some(_) => parts.push(format!(
+                "UNTIL={}",
+                ics_date_time(&until, time::Time::new(23, 59).unwrap_or_default())
+            )),
+            None => parts.push(format!("UNTIL={}", ics_date(&until))),
+        }
+    }
+    return parts.join(";");
+}
+
+/// Format a `Date` as an iCalendar `DATE` value.
+fn ics_date(date: &date::Date) -> String {
+    return format!("{:04}{:02}{:02}", date.year, date.month, date.day);
+}
+
+/// Format a `Date` and time as a floating iCalendar `DATE-TIME` value.
+fn ics_date_time(date: &date::Date, time: time::Time) -> String {
+    return format!("{}T{:02}{:02}00", ics_date(date), time.hour, time.minute);
+}
+
+/// Format the end of a timed event. Events without an end time end when they start, events that
+/// end before they start, which older agenda files may contain, are assumed to end on the next
+/// day.
+fn ics_end(date: &date::Date, start: time::Time, end: Option<time::Time>) -> String {
+    return match end {
+        Some(e) if e < start => {
+            let mut next_day = *date;
+            next_day.increment();
+            ics_date_time(&next_day, e)
+        }
+        Some(e) => ics_date_time(date, e),
+        None => ics_date_time(date, start),
+    };
+}
+
+/// Create a `UID` for events that were not imported, derived from the event itself.
+///
+/// The UID does not change between exports, so other calendars recognise the event.
+fn generated_uid(parts: &[&str]) -> String {
+    return format!("{:016x}@crust", records::stable_hash(parts));
+}
+
+/// Escape an iCalendar `TEXT` value.
+pub fn escape_text(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    return escaped;
+}
+
+/// Fold a content line into lines of at most 75 octets, terminated by CRLF.
+///
+/// Continuation lines start with a single space. Lines are never split inside a multi-byte
+/// character.
+pub fn fold_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut octets = 0;
+    for c in line.chars() {
+        let length = c.len_utf8();
+        if octets + length > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            // The leading space counts towards the length of the continuation line.
+            octets = 1;
+        }
+        folded.push(c);
+        octets += length;
+    }
+    folded.push_str("\r\n");
+    return folded;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory;
+
+    fn d(day: i32, month: i32, year: i32) -> date::Date {
+        return date::Date { day, month, year };
+    }
+
+    fn rule(frequency: recurrence::Frequency, first: date::Date) -> recurrence::Recurrence {
+        return recurrence::Recurrence {
+            id: "test".to_string(),
+            event: "Test".to_string(),
+            full_day: true,
+            start: None,
+            end: None,
+            frequency,
+            interval: 1,
+            first,
+            by_day: Vec::new(),
+            count: None,
+            until: None,
+            exceptions: Vec::new(),
+        };
+    }
+
+    fn by_day(codes: &str) -> Vec<recurrence::ByDay> {
+        return codes.split(',').map(|c| c.parse().unwrap()).collect();
+    }
+
+    #[test]
+    fn rrule_uses_count_when_it_ends_first() {
+        let mut r = rule(recurrence::Frequency::Daily, d(1, 1, 2026));
+        r.count = Some(3);
+        r.until = Some(d(31, 1, 2026));
+        assert_eq!(rrule(&r, None), "FREQ=DAILY;COUNT=3");
+    }
+
+    #[test]
+    fn rrule_uses_until_when_it_ends_first() {
+        let mut r = rule(recurrence::Frequency::Daily, d(1, 1, 2026));
+        r.count = Some(10);
+        r.until = Some(d(3, 1, 2026));
+        assert_eq!(rrule(&r, None), "FREQ=DAILY;UNTIL=20260103");
+        let time = time::Time::new(9, 0).unwrap();
+        assert_eq!(
+            rrule(&r, Some((time, None))),
+            "FREQ=DAILY;UNTIL=20260103T235900"
+        );
+    }
+
+    #[test]
+    fn rrule_counts_exceptions() {
+        // The exception on the 2nd is one of the three occurrences, so the event ends on the 3rd.
+        let mut r = rule(recurrence::Frequency::Daily, d(1, 1, 2026));
+        r.count = Some(3);
+        r.until = Some(d(3, 1, 2026));
+        r.exceptions = vec![d(2, 1, 2026)];
+        assert_eq!(rrule(&r, None), "FREQ=DAILY;COUNT=3");
+    }
+
+    #[test]
+    fn rrule_drops_ordinals_of_weekly_rules() {
+        let mut r = rule(recurrence::Frequency::Weekly, d(5, 1, 2026));
+        r.by_day = by_day("2TU,TU,-1FR");
+        assert_eq!(rrule(&r, None), "FREQ=WEEKLY;BYDAY=TU,FR");
+        r.frequency = recurrence::Frequency::Monthly;
+        assert_eq!(rrule(&r, None), "FREQ=MONTHLY;BYDAY=2TU,TU,-1FR");
+    }
+
+    #[test]
+    fn recurring_event_starts_at_first_occurrence() {
+        // 7 January 2026 is a Wednesday, the first Monday is the 12th.
+        let mut r = rule(recurrence::Frequency::Weekly, d(7, 1, 2026));
+        r.by_day = by_day("MO");
+        let store = memory::MemoryStore::new();
+        storage::Storage::save_recurring(&store, &[r]).unwrap();
+        let calendars = [calendar::Calendar {
+            name: "default".to_string(),
+            dir: std::path::PathBuf::new(),
+            storage: std::rc::Rc::new(store),
+        }];
+        let (output, warnings) = export_ics(&calendars, &d(1, 1, 2026), &d(31, 1, 2026));
+        assert!(warnings.is_empty());
+        assert!(output.contains("DTSTART;VALUE=DATE:20260112\r\n"));
+        assert!(output.contains("DTEND;VALUE=DATE:20260113\r\n"));
+    }
+
+    #[test]
+    fn generated_uid_is_stable() {
+        assert_eq!(
+            generated_uid(&["2026-01-01", "Test"]),
+            format!(
+                "{:016x}@crust",
+                records::stable_hash(&["2026-01-01", "Test"])
+            )
+        );
+        assert_eq!(generated_uid(&["a"]), "089be207b544f1e4@crust");
+    }
+}
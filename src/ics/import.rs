@@ -0,0 +1,911 @@
+// External crates
+extern crate chrono;
+extern crate chrono_tz;
+
+use chrono::{TimeZone, Timelike};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+// Local files
+use super::parser;
+use crate::date::date;
+use crate::date::time;
+use crate::error::error;
+use crate::file;
+use crate::file::recurrence;
+use crate::storage::storage;
+
+/// Days an all-day event may span at most. Every day of an all-day event is written to its own
+/// day file, so longer events are rejected as malformed.
+const MAX_EVENT_DAYS: i64 = 366;
+
+/// Summary of an `.ics` import.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// Number of events that were not present in the agenda before.
+    pub imported: usize,
+    /// Number of previously imported events that changed and were rewritten.
+    pub updated: usize,
+    /// Number of previously imported events that did not change.
+    pub unchanged: usize,
+    /// Components that were not imported, together with the reason why.
+    pub skipped: Vec<String>,
+    /// Events that were imported, but possibly not exactly as described in the file.
+    pub warnings: Vec<String>,
+}
+
+impl std::fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Imported {} new, {} updated and {} unchanged events, skipped {} components.",
+            self.imported,
+            self.updated,
+            self.unchanged,
+            self.skipped.len()
+        )?;
+        for skipped in self.skipped.iter() {
+            writeln!(f, "  skipped: {}", skipped)?;
+        }
+        for warning in self.warnings.iter() {
+            writeln!(f, "  warning: {}", warning)?;
+        }
+        return Ok(());
+    }
+}
+
+/// Moment in time of a `DTSTART`, `DTEND`, ... property.
+///
+/// Date-times are converted to the local time zone, floating date-times are kept as they are.
+#[derive(Debug, Clone, Copy)]
+enum Moment {
+    Day(chrono::NaiveDate),
+    Time(chrono::NaiveDateTime),
+}
+
+impl Moment {
+    fn date(&self) -> chrono::NaiveDate {
+        match self {
+            Moment::Day(d) => *d,
+            Moment::Time(t) => t.date(),
+        }
+    }
+}
+
+/// Single entry written into a day file, used to compare old and new versions of an event.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct StoredEntry {
+    date: date::Date,
+    start: Option<time::Time>,
+    end: Option<time::Time>,
+    event: String,
+}
+
+/// Import the `VEVENT` components of the iCalendar file at `path` into `storage`.
+///
+/// All-day events become `DayEvent` entries on every day they span, timed events become
+/// `TimedEvent` entries on the day they start. Events with a supported `RRULE` become recurring
+/// events. Every entry remembers the `UID` of the event, so importing the same file again
+/// replaces the earlier import instead of duplicating it.
+///
+/// An all-day event spanning more than `MAX_EVENT_DAYS` days makes the file invalid, nothing is
+/// imported from it.
+pub fn import_file(
+    storage: &dyn storage::Storage,
+    path: &std::path::Path,
+) -> Result<ImportReport, error::Error> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => {
+            return Err(error::Error::Invalid(format!(
+                "Error reading {}: {}",
+                path.display(),
+                e
+            )));
+        }
+    };
+    let calendars = match parser::parse_calendar(&contents) {
+        Ok(c) => c,
+        Err(e) => {
+            return Err(error::Error::Invalid(format!(
+                "Error parsing {}: {}",
+                path.display(),
+                e
+            )));
+        }
+    };
+    let _lock = storage.lock()?;
+
+    let mut report = ImportReport::default();
+    let mut events: Vec<&parser::Component> = Vec::new();
+    for calendar in calendars.iter() {
+        if calendar.name != "VCALENDAR" {
+            report
+                .skipped
+                .push(format!("{} component outside of VCALENDAR", calendar.name));
+            continue;
+        }
+        for component in calendar.components.iter() {
+            match component.name.as_str() {
+                "VEVENT" => events.push(component),
+                // Time zone definitions are resolved through the IANA database instead.
+                "VTIMEZONE" => {}
+                other => report
+                    .skipped
+                    .push(format!("{} component, only VEVENT is supported", other)),
+            }
+        }
+    }
+
+    // Find where earlier imports of each UID were stored.
+    let mut previous: HashMap<String, Vec<date::Date>> = HashMap::new();
+    for stored_date in storage::stored_dates(storage)?.iter() {
+        for uid in storage.load_day(stored_date).unwrap_or_default().uids() {
+            previous.entry(uid).or_default().push(*stored_date);
+        }
+    }
+
+    let mut agendas: BTreeMap<date::Date, file::parser::Agenda> = BTreeMap::new();
+    let mut recurring: Vec<recurrence::Recurrence> = storage.load_recurring()?;
+    let mut seen: HashSet<String> = HashSet::new();
+
+    // Dates of modified occurrences, which are excluded from the recurring event they belong to
+    // and imported as separate events instead.
+    let mut overridden: HashMap<String, Vec<date::Date>> = HashMap::new();
+    for event in events.iter() {
+        if let (Some(uid), Some(recurrence_id)) =
+            (event.property("UID"), event.property("RECURRENCE-ID"))
+        {
+            if let Ok(m) = parse_moment(recurrence_id, &mut Vec::new()) {
+                overridden
+                    .entry(uid.value.trim().to_string())
+                    .or_default()
+                    .push(date::Date::from_naive_date(&m.date()));
+            }
+        }
+    }
+
+    for event in events.iter() {
+        let summary: String = match event.property("SUMMARY") {
+            Some(p) => parser::unescape_text(&p.value).replace(['\r', '\n'], " "),
+            None => "(no title)".to_string(),
+        };
+        let description = format!("`{}`", summary);
+
+        if let Some(status) = event.property("STATUS") {
+            if status.value.eq_ignore_ascii_case("CANCELLED") {
+                report.skipped.push(format!("{}: cancelled", description));
+                continue;
+            }
+        }
+
+        let start: Moment = match event.property("DTSTART") {
+            Some(p) => match parse_moment(p, &mut report.warnings) {
+                Ok(m) => m,
+                Err(e) => {
+                    report.skipped.push(format!("{}: {}", description, e));
+                    continue;
+                }
+            },
+            None => {
+                report
+                    .skipped
+                    .push(format!("{}: missing DTSTART", description));
+                continue;
+            }
+        };
+        let end: Moment = match end_moment(event, &start, &mut report.warnings) {
+            Ok(m) => m,
+            Err(e) => {
+                report.skipped.push(format!("{}: {}", description, e));
+                continue;
+            }
+        };
+
+        // Without a UID, fall back to a key derived from the event itself so re-imports of the
+        // same file are still recognised.
+        let mut uid: String = match event.property("UID") {
+            Some(p) => p.value.trim().to_string(),
+            None => format!("{}-{}", summary, start.date()),
+        };
+        if let Some(recurrence_id) = event.property("RECURRENCE-ID") {
+            if let Err(e) = parse_moment(recurrence_id, &mut report.warnings) {
+                report.skipped.push(format!("{}: {}", description, e));
+                continue;
+            }
+            uid = format!("{}/{}", uid, recurrence_id.value.trim());
+        }
+        if !seen.insert(uid.clone()) {
+            report
+                .skipped
+                .push(format!("{}: duplicate UID {}", description, uid));
+            continue;
+        }
+
+        // Remove the earlier import of single events before adding the new version.
+        let mut old_entries: Vec<StoredEntry> = Vec::new();
+        if let Some(dates) = previous.get(&uid) {
+            for stored_date in dates.iter() {
+                let agenda = stored_agenda(storage, &mut agendas, stored_date)?;
+                old_entries.append(&mut remove_uid(agenda, stored_date, &uid));
+            }
+        }
+
+        // Recurring events are stored as `Recurrence` definitions.
+        if let Some(rrule) = event.property("RRULE") {
+            let mut definition = match parse_rrule(&rrule.value, &start, &mut report.warnings) {
+                Ok(r) => r,
+                Err(e) => {
+                    report.skipped.push(format!("{}: {}", description, e));
+                    continue;
+                }
+            };
+            definition.id = uid.clone();
+            definition.event = summary.clone();
+            set_times(&mut definition, &start, &end);
+            for exdate in event.properties_named("EXDATE") {
+                match parse_moment_list(exdate, &mut report.warnings) {
+                    Ok(dates) => definition.exceptions.extend(dates),
+                    Err(e) => report
+                        .warnings
+                        .push(format!("{}: ignored EXDATE, {}", description, e)),
+                }
+            }
+            if let Some(dates) = overridden.get(&uid) {
+                for d in dates.iter() {
+                    if !definition.exceptions.contains(d) {
+                        definition.exceptions.push(*d);
+                    }
+                }
+            }
+            match (start, end) {
+                (Moment::Day(s), Moment::Day(e)) if e.signed_duration_since(s).num_days() > 1 => {
+                    report.warnings.push(format!(
+                        "{}: multi-day recurring event only shown on its first day",
+                        description
+                    ));
+                }
+                (Moment::Time(s), Moment::Time(e)) if e.date() > s.date() => {
+                    report.warnings.push(format!(
+                        "{}: multi-day recurring event only shown until 23:59 on its first day",
+                        description
+                    ));
+                }
+                _ => {}
+            }
+
+            match recurring.iter().position(|r| r.id == uid) {
+                Some(i) => {
+                    if recurring[i] == definition && old_entries.is_empty() {
+                        report.unchanged += 1;
+                    } else {
+                        recurring[i] = definition;
+                        report.updated += 1;
+                    }
+                }
+                None => {
+                    recurring.push(definition);
+                    if old_entries.is_empty() {
+                        report.imported += 1;
+                    } else {
+                        report.updated += 1;
+                    }
+                }
+            }
+            continue;
+        }
+
+        // The event might have been recurring when it was imported before.
+        let was_recurring = recurring.iter().any(|r| r.id == uid);
+        if was_recurring {
+            recurring.retain(|r| r.id != uid);
+        }
+
+        // Single events are added to the day files they occur on.
+        let mut new_entries: Vec<StoredEntry> = Vec::new();
+        match (start, end) {
+            (Moment::Day(s), Moment::Day(e)) => {
+                let days = e.signed_duration_since(s).num_days();
+                if days > MAX_EVENT_DAYS {
+                    return Err(error::Error::Invalid(format!(
+                        "Error importing {}: {} spans {} days, events may span at most {} days",
+                        path.display(),
+                        description,
+                        days,
+                        MAX_EVENT_DAYS
+                    )));
+                }
+                // The end date of all-day events is exclusive.
+                let mut day = s;
+                loop {
+                    let entry_date = date::Date::from_naive_date(&day);
+                    stored_agenda(storage, &mut agendas, &entry_date)?.day.push(
+                        file::parser::DayEvent {
+                            event: summary.clone(),
+                            uid: Some(uid.clone()),
+                        },
+                    );
+                    new_entries.push(StoredEntry {
+                        date: entry_date,
+                        start: None,
+                        end: None,
+                        event: summary.clone(),
+                    });
+                    day = match day.succ_opt() {
+                        Some(d) => d,
+                        None => break,
+                    };
+                    if day >= e {
+                        break;
+                    }
+                }
+            }
+            _ => {
+                let entry_date = date::Date::from_naive_date(&start.date());
+                let (start_time, end_time) = event_times(&start, &end);
+                if end.date() > start.date() {
+                    report.warnings.push(format!(
+                        "{}: ends on {}, only shown until 23:59 on its first day",
+                        description,
+                        date::Date::from_naive_date(&end.date())
+                    ));
+                }
+                let agenda = stored_agenda(storage, &mut agendas, &entry_date)?;
+                agenda.timestamp.push(file::parser::TimedEvent {
+                    start: start_time,
+                    end: end_time,
+                    event: summary.clone(),
+                    uid: Some(uid.clone()),
+                });
+                agenda.timestamp.sort();
+                new_entries.push(StoredEntry {
+                    date: entry_date,
+                    start: Some(start_time),
+                    end: end_time,
+                    event: summary.clone(),
+                });
+            }
+        }
+
+        old_entries.sort();
+        new_entries.sort();
+        if old_entries.is_empty() && !was_recurring {
+            report.imported += 1;
+        } else if old_entries == new_entries {
+            report.unchanged += 1;
+        } else {
+            report.updated += 1;
+        }
+    }
+
+    for (agenda_date, agenda) in agendas.iter() {
+        storage.save_day(agenda_date, agenda)?;
+    }
+    storage.save_recurring(&recurring)?;
+    return Ok(report);
+}
+
+/// Obtain the agenda of `date` in `agendas`, loading the events stored on `date` the first time.
+///
+/// Events that cannot be read are an error, so they are never overwritten by the import.
+fn stored_agenda<'a>(
+    storage: &dyn storage::Storage,
+    agendas: &'a mut BTreeMap<date::Date, file::parser::Agenda>,
+    date: &date::Date,
+) -> Result<&'a mut file::parser::Agenda, String> {
+    return match agendas.entry(*date) {
+        std::collections::btree_map::Entry::Occupied(e) => Ok(e.into_mut()),
+        std::collections::btree_map::Entry::Vacant(e) => Ok(e.insert(storage.load_day(date)?)),
+    };
+}
+
+/// Remove all events with the given `uid` from `agenda`, returning what was removed.
+fn remove_uid(agenda: &mut file::parser::Agenda, date: &date::Date, uid: &str) -> Vec<StoredEntry> {
+    let mut removed: Vec<StoredEntry> = Vec::new();
+    let matches = |other: &Option<String>| other.as_deref() == Some(uid);
+    for e in agenda.day.iter().filter(|e| matches(&e.uid)) {
+        removed.push(StoredEntry {
+            date: *date,
+            start: None,
+            end: None,
+            event: e.event.clone(),
+        });
+    }
+    for e in agenda.timestamp.iter().filter(|e| matches(&e.uid)) {
+        removed.push(StoredEntry {
+            date: *date,
+            start: Some(e.start),
+            end: e.end,
+            event: e.event.clone(),
+        });
+    }
+    agenda.day.retain(|e| !matches(&e.uid));
+    agenda.timestamp.retain(|e| !matches(&e.uid));
+    return removed;
+}
+
+/// Obtain the start and end time of a timed event.
+///
+/// Events that end on the day they start keep their end time, events that end when they start
+/// have no end time. Events that last until a later day are cut off at 23:59, the end of a timed
+/// event cannot lie before its start.
+fn event_times(start: &Moment, end: &Moment) -> (time::Time, Option<time::Time>) {
+    let time_of =
+        |t: &chrono::NaiveDateTime| time::Time::new(t.hour(), t.minute()).unwrap_or_default();
+    let start_time = match start {
+        Moment::Time(t) => time_of(t),
+        Moment::Day(_) => time::Time::default(),
+    };
+    let end_time = match (start, end) {
+        (Moment::Time(s), Moment::Time(e)) if s == e => None,
+        (Moment::Time(s), Moment::Time(e)) if s.date() == e.date() => Some(time_of(e)),
+        _ => time::Time::new(23, 59),
+    };
+    return (start_time, end_time);
+}
+
+/// Fill in the full day flag and the start and end times of a recurring event.
+fn set_times(definition: &mut recurrence::Recurrence, start: &Moment, end: &Moment) {
+    match start {
+        Moment::Day(_) => {
+            definition.full_day = true;
+        }
+        Moment::Time(_) => {
+            let (start_time, end_time) = event_times(start, end);
+            definition.full_day = false;
+            definition.start = Some(start_time);
+            definition.end = end_time;
+        }
+    }
+}
+
+/// Parse a date or date-time property value.
+///
+/// `VALUE=DATE` values and 8 digit values are dates. Date-times ending in `Z` are UTC, date-times
+/// with a `TZID` parameter are in that time zone, both are converted to the local time zone.
+/// Date-times without either are floating and kept as-is.
+fn parse_moment(property: &parser::Property, warnings: &mut Vec<String>) -> Result<Moment, String> {
+    return parse_moment_value(property.value.trim(), property, warnings);
+}
+
+/// Parse a comma-separated list of dates or date-times, as used by `EXDATE`.
+fn parse_moment_list(
+    property: &parser::Property,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<date::Date>, String> {
+    let mut dates: Vec<date::Date> = Vec::new();
+    for value in property.value.split(',') {
+        let moment = parse_moment_value(value.trim(), property, warnings)?;
+        dates.push(date::Date::from_naive_date(&moment.date()));
+    }
+    return Ok(dates);
+}
+
+fn parse_moment_value(
+    value: &str,
+    property: &parser::Property,
+    warnings: &mut Vec<String>,
+) -> Result<Moment, String> {
+    let is_date = property.param("VALUE") == Some("DATE") || value.len() == 8;
+    if is_date {
+        return match chrono::NaiveDate::parse_from_str(value, "%Y%m%d") {
+            Ok(d) => Ok(Moment::Day(d)),
+            Err(_) => Err(format!("invalid date `{}` in {}", value, property.name)),
+        };
+    }
+
+    let (naive_str, utc) = match value.strip_suffix('Z') {
+        Some(v) => (v, true),
+        None => (value, false),
+    };
+    let naive = match chrono::NaiveDateTime::parse_from_str(naive_str, "%Y%m%dT%H%M%S") {
+        Ok(n) => n,
+        Err(_) => {
+            return Err(format!(
+                "invalid date-time `{}` in {}",
+                value, property.name
+            ));
+        }
+    };
+
+    if utc {
+        let local = chrono::Utc
+            .from_utc_datetime(&naive)
+            .with_timezone(&chrono::Local);
+        return Ok(Moment::Time(local.naive_local()));
+    }
+    if let Some(tzid) = property.param("TZID") {
+        let tz: chrono_tz::Tz = match tzid.trim_start_matches('/').parse() {
+            Ok(tz) => tz,
+            Err(_) => {
+                let warning = format!("unknown time zone `{}`, using local time", tzid);
+                if !warnings.contains(&warning) {
+                    warnings.push(warning);
+                }
+                return Ok(Moment::Time(naive));
+            }
+        };
+        return match tz.from_local_datetime(&naive).earliest() {
+            Some(t) => Ok(Moment::Time(t.with_timezone(&chrono::Local).naive_local())),
+            None => Err(format!("date-time `{}` does not exist in {}", value, tzid)),
+        };
+    }
+    return Ok(Moment::Time(naive));
+}
+
+/// Obtain the end of an event from its `DTEND` or `DURATION`.
+///
+/// Without either, all-day events last a single day and timed events end when they start.
+fn end_moment(
+    event: &parser::Component,
+    start: &Moment,
+    warnings: &mut Vec<String>,
+) -> Result<Moment, String> {
+    if let Some(p) = event.property("DTEND") {
+        return parse_moment(p, warnings);
+    }
+    if let Some(p) = event.property("DURATION") {
+        let duration = parse_duration(p.value.trim())?;
+        let end = match start {
+            Moment::Day(d) => d.checked_add_signed(duration).map(Moment::Day),
+            Moment::Time(t) => t.checked_add_signed(duration).map(Moment::Time),
+        };
+        return match end {
+            Some(m) => Ok(m),
+            None => Err(format!("DURATION `{}` ends out of range", p.value.trim())),
+        };
+    }
+    return match start {
+        Moment::Day(d) => match d.succ_opt() {
+            Some(next) => Ok(Moment::Day(next)),
+            None => Err(format!("DTSTART `{}` out of range", d)),
+        },
+        Moment::Time(t) => Ok(Moment::Time(*t)),
+    };
+}
+
+/// Parse an iCalendar duration such as `PT1H30M`, `P1D` or `P2W`.
+///
+/// Durations too long to be represented are invalid.
+fn parse_duration(value: &str) -> Result<chrono::Duration, String> {
+    let invalid = || format!("invalid DURATION `{}`", value);
+    let (sign, rest) = match value.strip_prefix('-') {
+        Some(r) => (-1, r),
+        None => (1, value.trim_start_matches('+')),
+    };
+    let rest = match rest.strip_prefix('P') {
+        Some(r) => r,
+        None => {
+            return Err(invalid());
+        }
+    };
+
+    let mut total = chrono::Duration::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in rest.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        if c == 'T' {
+            in_time = true;
+            continue;
+        }
+        let n: i64 = match number.parse() {
+            Ok(n) => n,
+            Err(_) => {
+                return Err(invalid());
+            }
+        };
+        number.clear();
+        let part = match (c, in_time) {
+            ('W', false) => chrono::Duration::try_weeks(n),
+            ('D', false) => chrono::Duration::try_days(n),
+            ('H', true) => chrono::Duration::try_hours(n),
+            ('M', true) => chrono::Duration::try_minutes(n),
+            ('S', true) => chrono::Duration::try_seconds(n),
+            _ => {
+                return Err(invalid());
+            }
+        };
+        total = match part.and_then(|p| total.checked_add(&p)) {
+            Some(t) => t,
+            None => {
+                return Err(invalid());
+            }
+        };
+    }
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+    return Ok(total * sign);
+}
+
+/// Convert an `RRULE` value into a `Recurrence` starting at `start`.
+///
+/// Only the parts and combinations that map onto `Recurrence` are supported, other rules are
+/// rejected so the event is reported as skipped instead of showing up on the wrong dates.
+fn parse_rrule(
+    value: &str,
+    start: &Moment,
+    warnings: &mut Vec<String>,
+) -> Result<recurrence::Recurrence, String> {
+    let first = date::Date::from_naive_date(&start.date());
+    let mut definition = recurrence::Recurrence {
+        id: String::new(),
+        event: String::new(),
+        full_day: false,
+        start: None,
+        end: None,
+        frequency: recurrence::Frequency::Daily,
+        interval: 1,
+        first,
+        by_day: Vec::new(),
+        count: None,
+        until: None,
+        exceptions: Vec::new(),
+    };
+    let mut has_frequency = false;
+    // The part and its value, checked once the whole rule is known.
+    let mut by_day: Option<&str> = None;
+    let mut by_month: Option<(&str, &str)> = None;
+    let mut by_month_day: Option<(&str, &str)> = None;
+
+    for part in value.split(';').filter(|p| !p.is_empty()) {
+        let (key, part_value) = match part.split_once('=') {
+            Some(kv) => kv,
+            None => {
+                return Err(format!("invalid RRULE part `{}`", part));
+            }
+        };
+        match key.to_uppercase().as_str() {
+            "FREQ" => {
+                definition.frequency = match part_value.parse::<recurrence::Frequency>() {
+                    Ok(f) => f,
+                    Err(_) => {
+                        return Err(format!("unsupported RRULE frequency {}", part_value));
+                    }
+                };
+                has_frequency = true;
+            }
+            "INTERVAL" => {
+                definition.interval = match part_value.parse::<u32>() {
+                    Ok(i) if i > 0 => i,
+                    _ => {
+                        return Err(format!("invalid RRULE interval {}", part_value));
+                    }
+                };
+            }
+            "COUNT" => {
+                definition.count = match part_value.parse::<u32>() {
+                    Ok(c) => Some(c),
+                    Err(_) => {
+                        return Err(format!("invalid RRULE count {}", part_value));
+                    }
+                };
+            }
+            "UNTIL" => {
+                let property = parser::Property {
+                    name: "UNTIL".to_string(),
+                    params: Vec::new(),
+                    value: part_value.to_string(),
+                };
+                let until = parse_moment(&property, warnings)?;
+                definition.until = Some(date::Date::from_naive_date(&until.date()));
+            }
+            "BYDAY" => {
+                by_day = Some(part);
+                for weekday in part_value.split(',') {
+                    match weekday.parse::<recurrence::ByDay>() {
+                        Ok(b) => definition.by_day.push(b),
+                        Err(e) => {
+                            return Err(e);
+                        }
+                    }
+                }
+            }
+            "BYMONTH" => by_month = Some((part, part_value)),
+            "BYMONTHDAY" => by_month_day = Some((part, part_value)),
+            // Weeks always start on Monday in crust.
+            "WKST" => {}
+            _ => {
+                return Err(format!("unsupported RRULE part {}", part));
+            }
+        }
+    }
+
+    if !has_frequency {
+        return Err("RRULE without FREQ".to_string());
+    }
+
+    // `Recurrence` only repeats the month and the day of the month of `first`, so BYMONTH and
+    // BYMONTHDAY are supported where they select those, and yearly rules need BYMONTH to keep
+    // BYDAY and BYMONTHDAY within that month instead of the whole year.
+    let frequency = definition.frequency;
+    let yearly = frequency == recurrence::Frequency::Yearly;
+    if let Some((part, value)) = by_month {
+        if !yearly || value != first.month.to_string() {
+            return Err(format!("unsupported RRULE part {}", part));
+        }
+    }
+    if let Some((part, value)) = by_month_day {
+        if !(yearly || frequency == recurrence::Frequency::Monthly)
+            || by_day.is_some()
+            || (yearly && by_month.is_none())
+            || value != first.day.to_string()
+        {
+            return Err(format!("unsupported RRULE part {}", part));
+        }
+    }
+    if let Some(part) = by_day {
+        if (yearly && by_month.is_none())
+            || recurrence::validate_by_day(frequency, &definition.by_day).is_err()
+        {
+            return Err(format!("unsupported RRULE part {}", part));
+        }
+    }
+    return Ok(definition);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory;
+
+    /// Import the events of `body` into an empty `MemoryStore`.
+    fn import(name: &str, body: &str) -> (memory::MemoryStore, Result<ImportReport, error::Error>) {
+        let path =
+            std::env::temp_dir().join(format!("crust-test-{}-{}.ics", name, std::process::id()));
+        let contents = format!(
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\n{}END:VEVENT\r\nEND:VCALENDAR\r\n",
+            body
+        );
+        std::fs::write(&path, contents).unwrap();
+        let store = memory::MemoryStore::new();
+        let result = import_file(&store, &path);
+        let _ = std::fs::remove_file(&path);
+        return (store, result);
+    }
+
+    /// Import an event on Friday 13 March 2026 repeating with `rule`, and return whether it
+    /// was imported.
+    fn import_rule(rule: &str) -> bool {
+        let (store, result) = import(
+            &rule.replace([';', '=', ','], "-"),
+            &format!(
+                "UID:a\r\nSUMMARY:A\r\nDTSTART;VALUE=DATE:20260313\r\nRRULE:{}\r\n",
+                rule
+            ),
+        );
+        let report = result.unwrap();
+        let recurring = storage::Storage::load_recurring(&store).unwrap();
+        assert_eq!(report.imported, recurring.len(), "{}", rule);
+        if report.imported == 0 {
+            assert_eq!(report.skipped.len(), 1, "{}", rule);
+            assert!(
+                report.skipped[0].contains("unsupported RRULE part"),
+                "{}",
+                rule
+            );
+        }
+        return report.imported == 1;
+    }
+
+    #[test]
+    fn parse_duration_reads_every_unit() {
+        assert_eq!(parse_duration("PT1H30M"), Ok(chrono::Duration::minutes(90)));
+        assert_eq!(parse_duration("P1D"), Ok(chrono::Duration::days(1)));
+        assert_eq!(parse_duration("P2W"), Ok(chrono::Duration::weeks(2)));
+        assert_eq!(parse_duration("P1DT12H"), Ok(chrono::Duration::hours(36)));
+        assert_eq!(parse_duration("-PT15M"), Ok(chrono::Duration::minutes(-15)));
+        assert_eq!(parse_duration("+PT10S"), Ok(chrono::Duration::seconds(10)));
+    }
+
+    #[test]
+    fn parse_duration_rejects_invalid_values() {
+        for value in ["1D", "P1H", "PT1D", "P1", "P1X", "PD"] {
+            assert!(parse_duration(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn parse_duration_rejects_overflow() {
+        assert!(parse_duration("P99999999999999W").is_err());
+        assert!(parse_duration("P9223372036854775807D").is_err());
+        assert!(parse_duration("P99999999999999999999D").is_err());
+    }
+
+    #[test]
+    fn import_skips_duration_out_of_range() {
+        let (store, result) = import(
+            "overflow",
+            "UID:a\r\nSUMMARY:A\r\nDTSTART;VALUE=DATE:99991230\r\nDURATION:P20000000W\r\n",
+        );
+        let report = result.unwrap();
+        assert_eq!(report.imported, 0);
+        assert_eq!(report.skipped.len(), 1);
+        assert!(storage::stored_dates(&store).unwrap().is_empty());
+    }
+
+    #[test]
+    fn import_rejects_long_all_day_events() {
+        let (store, result) = import(
+            "long",
+            "UID:a\r\nSUMMARY:A\r\nDTSTART;VALUE=DATE:20260101\r\nDURATION:P1000W\r\n",
+        );
+        assert!(matches!(result, Err(error::Error::Invalid(_))));
+        assert!(storage::stored_dates(&store).unwrap().is_empty());
+    }
+
+    #[test]
+    fn import_keeps_rules_repeating_the_first_date() {
+        for rule in [
+            "FREQ=MONTHLY;BYMONTHDAY=13",
+            "FREQ=MONTHLY;BYDAY=2FR",
+            "FREQ=YEARLY;BYMONTH=3",
+            "FREQ=YEARLY;BYMONTH=3;BYMONTHDAY=13",
+            "FREQ=YEARLY;BYMONTH=3;BYDAY=2FR",
+            "FREQ=WEEKLY;BYDAY=MO,FR",
+        ] {
+            assert!(import_rule(rule), "{}", rule);
+        }
+    }
+
+    #[test]
+    fn import_skips_monthly_rules_with_bymonth() {
+        assert!(!import_rule("FREQ=MONTHLY;BYMONTH=3"));
+    }
+
+    #[test]
+    fn import_skips_daily_and_weekly_rules_with_bymonth() {
+        assert!(!import_rule("FREQ=DAILY;BYMONTH=3"));
+        assert!(!import_rule("FREQ=WEEKLY;BYMONTH=3"));
+    }
+
+    #[test]
+    fn import_skips_daily_and_weekly_rules_with_bymonthday() {
+        assert!(!import_rule("FREQ=DAILY;BYMONTHDAY=13"));
+        assert!(!import_rule("FREQ=WEEKLY;BYMONTHDAY=13"));
+    }
+
+    #[test]
+    fn import_skips_rules_with_byday_and_bymonthday() {
+        assert!(!import_rule("FREQ=MONTHLY;BYDAY=FR;BYMONTHDAY=13"));
+        assert!(!import_rule("FREQ=YEARLY;BYMONTH=3;BYDAY=FR;BYMONTHDAY=13"));
+    }
+
+    #[test]
+    fn import_skips_yearly_rules_without_bymonth() {
+        assert!(!import_rule("FREQ=YEARLY;BYDAY=2FR"));
+        assert!(!import_rule("FREQ=YEARLY;BYMONTHDAY=13"));
+    }
+
+    #[test]
+    fn import_skips_rules_in_other_months_or_days() {
+        assert!(!import_rule("FREQ=YEARLY;BYMONTH=4"));
+        assert!(!import_rule("FREQ=MONTHLY;BYMONTHDAY=14"));
+    }
+
+    #[test]
+    fn import_skips_weekday_ordinals_of_weekly_rules() {
+        assert!(!import_rule("FREQ=WEEKLY;BYDAY=2FR"));
+    }
+
+    #[test]
+    fn import_warns_about_multi_day_timed_events() {
+        let (store, result) = import(
+            "timed",
+            "UID:a\r\nSUMMARY:Night\r\nDTSTART:20260101T220000\r\nDTEND:20260102T060000\r\n",
+        );
+        let report = result.unwrap();
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.warnings.len(), 1);
+        let day = date::Date {
+            day: 1,
+            month: 1,
+            year: 2026,
+        };
+        let agenda = storage::Storage::load_day(&store, &day).unwrap();
+        assert_eq!(agenda.timestamp[0].end, time::Time::new(23, 59));
+    }
+}
@@ -0,0 +1,230 @@
+/// Single content line of an iCalendar file, e.g. `DTSTART;TZID=Europe/Brussels:20240721T090000`.
+///
+/// Property and parameter names are stored in uppercase. Parameter values are stored without the
+/// surrounding quotes, the property value is stored as-is.
+#[derive(Debug, Clone)]
+pub struct Property {
+    pub name: String,
+    pub params: Vec<(String, String)>,
+    pub value: String,
+}
+
+impl Property {
+    /// Obtain the value of the parameter `name`, if present.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        return self
+            .params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str());
+    }
+}
+
+/// Component of an iCalendar file, i.e. everything between `BEGIN:NAME` and `END:NAME`.
+#[derive(Debug, Clone, Default)]
+pub struct Component {
+    pub name: String,
+    pub properties: Vec<Property>,
+    pub components: Vec<Component>,
+}
+
+impl Component {
+    /// Obtain the first property with the given `name`, if present.
+    pub fn property(&self, name: &str) -> Option<&Property> {
+        return self.properties.iter().find(|p| p.name == name);
+    }
+
+    /// Obtain all properties with the given `name`.
+    pub fn properties_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Property> {
+        return self.properties.iter().filter(move |p| p.name == name);
+    }
+}
+
+/// Unfold the content lines of an iCalendar file.
+///
+/// Lines starting with a space or a tab are continuations of the previous line. Empty lines
+/// are dropped.
+pub fn unfold(contents: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for raw_line in contents.split('\n') {
+        let line = raw_line.strip_suffix('\r').unwrap_or(raw_line);
+        if line.starts_with(' ') || line.starts_with('\t') {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(&line[1..]);
+                continue;
+            }
+        }
+        if !line.is_empty() {
+            lines.push(line.to_string());
+        }
+    }
+    return lines;
+}
+
+/// Parse a single unfolded content line into a `Property`.
+///
+/// Returns `None` if the line has no `:` separating the name and parameters from the value.
+pub fn parse_property(line: &str) -> Option<Property> {
+    let mut name = String::new();
+    let mut params: Vec<(String, String)> = Vec::new();
+    let mut current_key = String::new();
+    let mut current_value = String::new();
+    let mut in_param = false;
+    let mut in_value = false;
+    let mut quoted = false;
+
+    for (i, c) in line.char_indices() {
+        if quoted {
+            if c == '"' {
+                quoted = false;
+            } else {
+                current_value.push(c);
+            }
+            continue;
+        }
+        match c {
+            '"' if in_value => quoted = true,
+            ';' | ':' => {
+                if in_param {
+                    params.push((current_key.to_uppercase(), current_value.clone()));
+                    current_key.clear();
+                    current_value.clear();
+                }
+                if c == ':' {
+                    return Some(Property {
+                        name: name.to_uppercase(),
+                        params,
+                        value: line[i + 1..].to_string(),
+                    });
+                }
+                in_param = true;
+                in_value = false;
+            }
+            '=' if in_param && !in_value => in_value = true,
+            ',' if in_value => current_value.push(c),
+            _ => {
+                if !in_param {
+                    name.push(c);
+                } else if in_value {
+                    current_value.push(c);
+                } else {
+                    current_key.push(c);
+                }
+            }
+        }
+    }
+    return None;
+}
+
+/// Parse the contents of an iCalendar file into its top-level components.
+///
+/// Usually this is a single `VCALENDAR` component containing `VEVENT`, `VTIMEZONE`, ...
+/// components. An error is returned if the `BEGIN` and `END` lines do not match up.
+pub fn parse_calendar(contents: &str) -> Result<Vec<Component>, String> {
+    let mut stack: Vec<Component> = Vec::new();
+    let mut top_level: Vec<Component> = Vec::new();
+
+    for (number, line) in unfold(contents).iter().enumerate() {
+        let property = match parse_property(line) {
+            Some(p) => p,
+            None => {
+                return Err(format!("invalid content line {}: `{}`", number + 1, line));
+            }
+        };
+        if property.name == "BEGIN" {
+            stack.push(Component {
+                name: property.value.trim().to_uppercase(),
+                ..Component::default()
+            });
+        } else if property.name == "END" {
+            let component = match stack.pop() {
+                Some(c) => c,
+                None => {
+                    return Err(format!("unexpected END:{}", property.value));
+                }
+            };
+            if component.name != property.value.trim().to_uppercase() {
+                return Err(format!(
+                    "expected END:{}, found END:{}",
+                    component.name, property.value
+                ));
+            }
+            match stack.last_mut() {
+                Some(parent) => parent.components.push(component),
+                None => top_level.push(component),
+            }
+        } else {
+            match stack.last_mut() {
+                Some(current) => current.properties.push(property),
+                None => {
+                    return Err(format!("property {} outside of a component", property.name));
+                }
+            }
+        }
+    }
+
+    if let Some(component) = stack.last() {
+        return Err(format!("missing END:{}", component.name));
+    }
+    return Ok(top_level);
+}
+
+/// Undo the escaping of iCalendar `TEXT` values.
+pub fn unescape_text(value: &str) -> String {
+    let mut text = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') | Some('N') => text.push('\n'),
+                Some(other) => text.push(other),
+                None => text.push('\\'),
+            }
+        } else {
+            text.push(c);
+        }
+    }
+    return text;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unfold_joins_continuation_lines() {
+        let contents = "SUMMARY:Long\r\n  title\r\nDESCRIPTION:a\r\n\tb\r\n";
+        assert_eq!(
+            unfold(contents),
+            vec!["SUMMARY:Long title", "DESCRIPTION:ab"]
+        );
+    }
+
+    #[test]
+    fn unfold_drops_empty_lines() {
+        assert_eq!(
+            unfold("BEGIN:VEVENT\n\n\r\nEND:VEVENT"),
+            vec!["BEGIN:VEVENT", "END:VEVENT"]
+        );
+    }
+
+    #[test]
+    fn unfold_keeps_leading_continuation() {
+        // A continuation without a previous line is kept as a line of its own.
+        assert_eq!(unfold(" X:1"), vec![" X:1"]);
+    }
+
+    #[test]
+    fn parse_property_reads_quoted_parameters() {
+        let property = parse_property("DTSTART;TZID=\"Europe/Brussels\":20240721T090000").unwrap();
+        assert_eq!(property.name, "DTSTART");
+        assert_eq!(property.param("TZID"), Some("Europe/Brussels"));
+        assert_eq!(property.value, "20240721T090000");
+        assert!(parse_property("no separator").is_none());
+    }
+
+    #[test]
+    fn unescape_text_undoes_escapes() {
+        assert_eq!(unescape_text(r"a\, b\; c\nd\\"), "a, b; c\nd\\");
+    }
+}
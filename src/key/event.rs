@@ -1,5 +1,3 @@
-// TODO: Add by week and by month movements while the UI is running.
-
 extern crate crossterm;
 extern crate ratatui;
 
@@ -7,52 +5,165 @@ extern crate ratatui;
 use crate::configuration::config;
 
 /// Descriptors for possible key events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum KeyEvents {
     NoEvent,
     Quit,
     Next,
     Previous,
+    NextWeek,
+    PreviousWeek,
+    NextMonth,
+    PreviousMonth,
+    NextYear,
+    PreviousYear,
+    /// Go back to the current day.
+    Today,
+    /// Go to the next day with an entry in the shown calendars.
+    NextEntry,
+    /// Go to the previous day with an entry in the shown calendars.
+    PreviousEntry,
+    /// Go to the first day of the month.
+    FirstDay,
+    /// Go to the last day of the month.
+    LastDay,
+    /// Open the prompt in which the date to go to is typed.
+    GoToDate,
+    /// Open the form to add an event to the shown date.
+    AddEntry,
+    /// Open the form to edit the selected entry.
+    EditEntry,
+    /// Ask to delete the selected entry.
+    DeleteEntry,
+    /// Show or hide the calendar with the given index, selected with the keys `1` to `9`.
+    ToggleCalendar(usize),
+    /// Move the focus between the calendar and the agenda, with the tab key.
+    SwitchFocus,
+    /// Select the next entry in the focused agenda, with the down arrow.
+    SelectNext,
+    /// Select the previous entry in the focused agenda, with the up arrow.
+    SelectPrevious,
+    /// The terminal was resized and the UI should be drawn again.
+    Resize,
+}
+
+/// Input read from the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    /// Nothing happened within the polling time.
+    NoInput,
+    /// A key was pressed.
+    Key(crossterm::event::KeyCode),
+    /// The terminal was resized.
+    Resize,
 }
 
-/// Get key press event if special character is pressed.
+/// Read the input of the terminal.
 ///
-/// Collect events via `crossterm`, determine if that event is a key press and return a
-/// `KeyEvents` code for special characters determined by the configuration. If a key is pressed
-/// with no attached functionality, or an error occurs `NoEvent` is returned.
-pub fn get_key_event(conf: &config::Config) -> KeyEvents {
-    // Get events every 50 ms, return None if an error occurred.
+/// Collect events via `crossterm` and return the key that was pressed or the resize of the
+/// terminal. If no event occurs within 50 ms, the event is of another kind, or an error occurs
+/// `NoInput` is returned.
+pub fn read_input() -> Input {
+    // Get events every 50 ms, return NoInput if an error occurred.
     match crossterm::event::poll(std::time::Duration::from_millis(50)) {
-        Ok(_) => {
+        Ok(false) => {
+            return Input::NoInput;
+        }
+        Ok(true) => {
             // Read the event that occurred.
             let event_read = match crossterm::event::read() {
                 Ok(event) => event,
                 Err(_) => {
-                    return KeyEvents::NoEvent;
+                    return Input::NoInput;
                 }
             };
-            // Check if the event was a key-related event and store that event in `key`.
-            if let crossterm::event::Event::Key(key) = event_read {
-                // Check that the key-related event was a key press.
-                if key.kind == crossterm::event::KeyEventKind::Press {
-                    // Return KeyEvents code for special key-presses.
-                    if key.code == crossterm::event::KeyCode::Char(conf.key_quit) {
-                        return KeyEvents::Quit;
-                    } else if key.code == crossterm::event::KeyCode::Char(conf.key_next) {
-                        return KeyEvents::Next;
-                    } else if key.code == crossterm::event::KeyCode::Char(conf.key_previous) {
-                        return KeyEvents::Previous;
-                    } else {
-                        return KeyEvents::NoEvent;
-                    }
-                } else {
-                    return KeyEvents::NoEvent;
+            return match event_read {
+                // Only key presses count, not releases or repeats.
+                crossterm::event::Event::Key(key)
+                    if key.kind == crossterm::event::KeyEventKind::Press =>
+                {
+                    Input::Key(key.code)
                 }
-            } else {
-                return KeyEvents::NoEvent;
-            }
+                crossterm::event::Event::Resize(_, _) => Input::Resize,
+                _ => Input::NoInput,
+            };
         }
         Err(_) => {
-            return KeyEvents::NoEvent;
+            return Input::NoInput;
+        }
+    }
+}
+
+/// Get the `KeyEvents` code of the key `code` pressed after the keys in `pending`.
+///
+/// Characters are collected in `pending` until they form one of the key sequences of the
+/// configuration, which is returned, or no longer start one. In the latter case the sequence
+/// starts again from the last key, so a mistyped prefix does not swallow the next key. The keys
+/// `1` to `9`, tab and the arrows act immediately and discard `pending`, as does escape.
+/// `NoEvent` is returned while a sequence is incomplete and for keys without functionality.
+pub fn key_event(
+    conf: &config::Config,
+    pending: &mut String,
+    code: crossterm::event::KeyCode,
+) -> KeyEvents {
+    if let crossterm::event::KeyCode::Char(c) = code {
+        if !(c.is_ascii_digit() && c != '0') {
+            return sequence_event(conf, pending, c);
+        }
+    }
+    pending.clear();
+    return match code {
+        crossterm::event::KeyCode::Char(c @ '1'..='9') => {
+            KeyEvents::ToggleCalendar(c as usize - '1' as usize)
         }
+        crossterm::event::KeyCode::Tab => KeyEvents::SwitchFocus,
+        crossterm::event::KeyCode::Down => KeyEvents::SelectNext,
+        crossterm::event::KeyCode::Up => KeyEvents::SelectPrevious,
+        _ => KeyEvents::NoEvent,
+    };
+}
+
+/// Add `c` to the `pending` keys and obtain the `KeyEvents` code of the sequence they form, see
+/// `key_event`.
+fn sequence_event(conf: &config::Config, pending: &mut String, c: char) -> KeyEvents {
+    let bindings = [
+        (&conf.key_quit, KeyEvents::Quit),
+        (&conf.key_next, KeyEvents::Next),
+        (&conf.key_previous, KeyEvents::Previous),
+        (&conf.key_next_week, KeyEvents::NextWeek),
+        (&conf.key_previous_week, KeyEvents::PreviousWeek),
+        (&conf.key_next_month, KeyEvents::NextMonth),
+        (&conf.key_previous_month, KeyEvents::PreviousMonth),
+        (&conf.key_next_year, KeyEvents::NextYear),
+        (&conf.key_previous_year, KeyEvents::PreviousYear),
+        (&conf.key_today, KeyEvents::Today),
+        (&conf.key_next_entry, KeyEvents::NextEntry),
+        (&conf.key_previous_entry, KeyEvents::PreviousEntry),
+        (&conf.key_first_day, KeyEvents::FirstDay),
+        (&conf.key_last_day, KeyEvents::LastDay),
+        (&conf.key_go_to_date, KeyEvents::GoToDate),
+        (&conf.key_add, KeyEvents::AddEntry),
+        (&conf.key_edit, KeyEvents::EditEntry),
+        (&conf.key_delete, KeyEvents::DeleteEntry),
+    ];
+    pending.push(c);
+    if let Some((_, event)) = bindings
+        .iter()
+        .find(|(seqs, _)| seqs.iter().any(|seq| seq == pending))
+    {
+        pending.clear();
+        return *event;
+    }
+    if bindings
+        .iter()
+        .any(|(seqs, _)| seqs.iter().any(|seq| seq.starts_with(pending.as_str())))
+    {
+        return KeyEvents::NoEvent;
+    }
+    let restart = pending.chars().count() > 1;
+    pending.clear();
+    if restart {
+        return sequence_event(conf, pending, c);
     }
+    return KeyEvents::NoEvent;
 }
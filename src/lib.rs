@@ -1,6 +1,13 @@
+// Explicit returns are the style of the code base.
+#![allow(clippy::needless_return)]
+
 pub mod args;
 pub mod configuration;
 pub mod date;
+pub mod error;
 pub mod file;
+pub mod ics;
 pub mod key;
+pub mod output;
+pub mod storage;
 pub mod ui;
@@ -1,32 +1,323 @@
+// Explicit returns are the style of the code base.
+#![allow(clippy::needless_return)]
+
+use std::io::{IsTerminal, Write};
+
 // Local files
 use crust::args;
 use crust::configuration::config;
+use crust::date::date;
+use crust::error::error;
 use crust::file;
+use crust::ics;
+use crust::output;
+use crust::storage;
 use crust::ui::window;
 
-extern crate dirs;
+/// Exit code for errors while reading or writing the agenda, problems left by `crust check` and
+/// other errors while running a command.
+const EXIT_FAILURE: i32 = 1;
+/// Exit code for invalid arguments.
+const EXIT_USAGE: i32 = 2;
+/// Exit code for an invalid configuration file.
+const EXIT_CONFIG: i32 = 3;
+/// Exit code when the selected entry or calendar does not exist.
+const EXIT_NOT_FOUND: i32 = 4;
+/// Exit code for invalid input other than the arguments, such as a file to import.
+const EXIT_INVALID: i32 = 5;
+/// Exit code when `$EDITOR` is missing or fails.
+const EXIT_EDITOR: i32 = 6;
+/// Exit code when the entries changed while they were being edited.
+const EXIT_CONFLICT: i32 = 7;
 
 fn main() {
-    // Create $HOME/.cache/crust/ if it does not exist.
-    let mut folderdir = dirs::cache_dir().expect("Error obtaining $HOME/.cache/");
-    folderdir.push("crust");
-    if !folderdir.exists() {
-        std::fs::create_dir(folderdir).expect("Error creating $HOME/.cache/crust/")
+    let args: Vec<String> = std::env::args().collect();
+    let prog_args = match args::parser::parse_arguments(&args) {
+        Ok(a) => a,
+        Err(args::parser::ArgumentError::Help(message)) => {
+            println!("{}", message);
+            return;
+        }
+        Err(args::parser::ArgumentError::Invalid(message)) => {
+            eprintln!("{}", message);
+            std::process::exit(EXIT_USAGE);
+        }
+    };
+
+    if let Err(e) = run(prog_args) {
+        eprintln!("{}", e);
+        std::process::exit(exit_code(&e));
     }
+}
 
-    let prog_args = args::parser::parse_arguments();
-    let prog_args_ui = prog_args.clone();
-    let conf = config::Config::default();
+/// Obtain the exit code of the error `e`.
+fn exit_code(e: &error::Error) -> i32 {
+    return match e {
+        error::Error::Config(_) => EXIT_CONFIG,
+        error::Error::NotFound(_) => EXIT_NOT_FOUND,
+        error::Error::Invalid(_) => EXIT_INVALID,
+        error::Error::Editor(_) => EXIT_EDITOR,
+        error::Error::Conflict(_) => EXIT_CONFLICT,
+        // Problems that are left make entries disappear, scripts can check for them.
+        error::Error::Storage(_) | error::Error::Terminal(_) | error::Error::Problems(_) => {
+            EXIT_FAILURE
+        }
+    };
+}
 
-    if prog_args.flags.open_calendar {
-        let _ = window::create_window(prog_args_ui, conf);
+/// Run the command given by the program arguments.
+fn run(mut prog_args: args::parser::ProgramArguments) -> Result<(), error::Error> {
+    let conf = config::Config::load()?;
+    let data_dir = storage::storage::data_dir(conf.data_dir.as_deref())?;
+    // Older versions stored the agenda in $HOME/.cache/crust/, move it out of the cache. The
+    // command can still run on the data directory when that fails.
+    if let Some(old_dir) = storage::relocate::old_cache_dir() {
+        match storage::relocate::move_from_cache(&old_dir, &data_dir) {
+            Ok(Some(message)) => eprintln!("{}", message),
+            Ok(None) => {}
+            Err(e) => eprintln!(
+                "warning: {}, the remaining agenda files are left in {}.",
+                e,
+                old_dir.display()
+            ),
+        }
+    }
+
+    // Migrating replaces the storage, so it runs before the storage is opened.
+    if prog_args.command == args::parser::Command::Migrate {
+        println!(
+            "{}",
+            storage::migrate::migrate_calendars(&data_dir, prog_args.backend)?
+        );
+        return Ok(());
+    }
+
+    // Adding and importing write to a single calendar, created when needed, and the recurring
+    // events are edited in a single calendar. The other commands use every calendar given with
+    // --calendar.
+    let calendars: Vec<storage::calendar::Calendar> = if matches!(
+        prog_args.command,
+        args::parser::Command::Add | args::parser::Command::Import
+    ) {
+        let name = match prog_args.calendars.first() {
+            Some(n) => n.as_str(),
+            None => storage::calendar::DEFAULT_CALENDAR,
+        };
+        vec![storage::calendar::open_calendar(&data_dir, name)?]
+    } else if prog_args.flags.recurring && prog_args.calendars.is_empty() {
+        storage::calendar::open_calendars(
+            &data_dir,
+            &[storage::calendar::DEFAULT_CALENDAR.to_string()],
+        )?
     } else {
-        if prog_args.flags.edit {
-            file::parser::open_editor(&prog_args);
-        } else if prog_args.flags.remove {
-            file::parser::remove_file(&prog_args);
-        } else {
-            file::parser::write_entry(&prog_args);
+        storage::calendar::open_calendars(&data_dir, &prog_args.calendars)?
+    };
+
+    // The calendar column of CSV and TSV output is only added when calendars are selected or in
+    // use, so the columns stay the same for users of a single calendar.
+    let with_calendar = !prog_args.calendars.is_empty() || calendars.len() > 1;
+
+    match prog_args.command {
+        args::parser::Command::Add => {
+            file::parser::write_entry(calendars[0].storage.as_ref(), &prog_args)?;
         }
+        args::parser::Command::List => match prog_args.output {
+            output::records::OutputFormat::Text => print!(
+                "{}",
+                output::text::render_range(
+                    &calendars,
+                    &prog_args.from,
+                    &prog_args.to,
+                    &conf,
+                    output::text::use_color()
+                )
+            ),
+            format => print!(
+                "{}",
+                output::records::render_records(
+                    &output::records::collect_records(&calendars, &prog_args.from, &prog_args.to),
+                    format,
+                    with_calendar
+                )
+            ),
+        },
+        args::parser::Command::Search => {
+            let found = output::records::search_records(
+                &calendars,
+                &prog_args.query,
+                &prog_args.from,
+                &prog_args.to,
+            );
+            match prog_args.output {
+                output::records::OutputFormat::Text => {
+                    for record in found.iter() {
+                        let mut text = record.text.clone();
+                        if record.calendar != storage::calendar::DEFAULT_CALENDAR {
+                            text.push_str(&format!("  [{}]", record.calendar));
+                        }
+                        println!(
+                            "{}  {:<13}  {}",
+                            record.date,
+                            output::records::record_times(record),
+                            text
+                        );
+                    }
+                }
+                format => print!(
+                    "{}",
+                    output::records::render_records(&found, format, with_calendar)
+                ),
+            }
+        }
+        args::parser::Command::Show => {
+            let records =
+                output::records::collect_records(&calendars, &prog_args.date, &prog_args.date);
+            match prog_args.output {
+                output::records::OutputFormat::Text if records.is_empty() => {
+                    println!("No entry for {}.", prog_args.date)
+                }
+                output::records::OutputFormat::Text => print!(
+                    "{}",
+                    output::text::render_day(
+                        &prog_args.date,
+                        &records,
+                        &conf,
+                        output::text::use_color()
+                    )
+                ),
+                format => print!(
+                    "{}",
+                    output::records::render_records(&records, format, with_calendar)
+                ),
+            }
+        }
+        args::parser::Command::Edit | args::parser::Command::Remove => {
+            let choose = !prog_args.flags.recurring
+                && !prog_args.flags.all
+                && prog_args.index.is_none()
+                && prog_args.id.is_none();
+            if choose && std::io::stdin().is_terminal() {
+                let action = match prog_args.command {
+                    args::parser::Command::Edit => "edit",
+                    _ => "remove",
+                };
+                prog_args.id = Some(choose_entry(&calendars, &prog_args.date, action)?);
+            }
+            let message = if prog_args.flags.recurring {
+                file::modify::edit_recurring(calendars[0].storage.as_ref())?
+            } else if prog_args.command == args::parser::Command::Edit {
+                file::modify::edit_entry(&calendars, &prog_args)?
+            } else {
+                file::modify::remove_entry(&calendars, &prog_args)?
+            };
+            println!("{}", message);
+        }
+        args::parser::Command::Open => {
+            window::create_window(prog_args.date, conf, calendars)?;
+        }
+        args::parser::Command::Import => {
+            let path = std::path::Path::new(&prog_args.file);
+            let report = if prog_args.format == "json" {
+                output::records::import_json(&calendars[0], path)?
+            } else {
+                ics::import::import_file(calendars[0].storage.as_ref(), path)?
+            };
+            print!("{}", report);
+        }
+        args::parser::Command::Export => {
+            let (output, warnings) =
+                ics::export::export_ics(&calendars, &prog_args.from, &prog_args.to);
+            print!("{}", output);
+            for warning in warnings.iter() {
+                eprintln!("warning: {}", warning);
+            }
+        }
+        args::parser::Command::Check => {
+            let mut found = 0;
+            let mut fixed = 0;
+            for calendar in calendars.iter() {
+                let problems = storage::check::check(calendar, prog_args.flags.fix)?;
+                for problem in problems.iter() {
+                    println!("{}", problem);
+                }
+                found += problems.len();
+                fixed += problems.iter().filter(|p| p.fixed).count();
+            }
+            match (found, prog_args.flags.fix) {
+                (0, _) => println!("No problems found."),
+                (_, true) => println!(
+                    "{} {} found, {} fixed.",
+                    found,
+                    if found == 1 { "problem" } else { "problems" },
+                    fixed
+                ),
+                (_, false) => println!(
+                    "{} {} found.",
+                    found,
+                    if found == 1 { "problem" } else { "problems" }
+                ),
+            }
+            if fixed < found {
+                let left = found - fixed;
+                return Err(error::Error::Problems(format!(
+                    "{} {} left, see above.",
+                    left,
+                    if left == 1 {
+                        "problem is"
+                    } else {
+                        "problems are"
+                    }
+                )));
+            }
+        }
+        // Handled before the storage is opened.
+        args::parser::Command::Migrate => {}
     }
+    return Ok(());
+}
+
+/// Ask the user to choose one of the entries of `entry_date` in the `calendars` for the
+/// `action`, returning the identifier of the chosen entry.
+fn choose_entry(
+    calendars: &[storage::calendar::Calendar],
+    entry_date: &date::Date,
+    action: &str,
+) -> Result<String, error::Error> {
+    let entries = file::modify::calendar_entries(entry_date, calendars)?;
+    if entries.is_empty() {
+        return Err(error::Error::NotFound(format!(
+            "No entry to {} for {}.",
+            action, entry_date
+        )));
+    }
+    for (index, (_, entry)) in entries.iter().enumerate() {
+        let mut text = entry.record.text.clone();
+        if entry.record.calendar != storage::calendar::DEFAULT_CALENDAR {
+            text.push_str(&format!("  [{}]", entry.record.calendar));
+        }
+        println!(
+            "  {:>2}  {:<13}  {}",
+            index + 1,
+            output::records::record_times(&entry.record),
+            text
+        );
+    }
+    print!("Entry to {} [1-{}]: ", action, entries.len());
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    if let Err(e) = std::io::stdin().read_line(&mut answer) {
+        return Err(error::Error::Invalid(format!(
+            "Error reading the selection: {}",
+            e
+        )));
+    }
+    return match answer.trim().parse::<usize>() {
+        Ok(index) if index >= 1 && index <= entries.len() => {
+            Ok(entries[index - 1].1.record.id.clone())
+        }
+        _ => Err(error::Error::Invalid(format!(
+            "No entry selected, nothing to {}.",
+            action
+        ))),
+    };
 }
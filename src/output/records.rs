@@ -0,0 +1,649 @@
+// External crates
+extern crate chrono;
+extern crate serde;
+extern crate serde_json;
+
+use serde::{Deserialize, Serialize};
+
+// Local files
+use crate::date::date;
+use crate::date::time;
+use crate::error::error;
+use crate::file;
+use crate::file::recurrence;
+use crate::ics::import::ImportReport;
+use crate::storage::calendar;
+use crate::storage::storage;
+
+/// Value of `Record::kind` for full day events.
+pub const KIND_FULL_DAY: &str = "full_day";
+/// Value of `Record::kind` for timed events.
+pub const KIND_TIMED: &str = "timed";
+
+/// Column names of the CSV and TSV output, in order.
+///
+/// Scripts read the columns by position, so new columns are only added at the end.
+const COLUMNS: [&str; 6] = ["date", "kind", "start", "end", "text", "id"];
+
+/// Name of the column holding the calendar of an entry, after the `COLUMNS`.
+const CALENDAR_COLUMN: &str = "calendar";
+
+/// Format used to write agenda entries to stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Csv,
+    Tsv,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            _ => Err(format!(
+                "unknown output format `{}`, expected text, json, csv or tsv",
+                s
+            )),
+        }
+    }
+}
+
+/// Single agenda entry on a single date, as written by `--output json|csv|tsv`.
+///
+/// The schema is stable:
+/// - `date`: date of the entry as `yyyy-mm-dd`.
+/// - `kind`: `full_day` or `timed`.
+/// - `start`, `end`: times of timed events, `null` (JSON) or empty (CSV, TSV) otherwise. `end`
+///   is also empty for timed events without an end time.
+/// - `text`: text of the entry.
+/// - `id`: identifier of the entry. This is the `uid` of imported entries, `ID/yyyy-mm-dd` for an
+///   occurrence of the recurring event `ID` and a hash of the other fields for the remaining
+///   entries.
+/// - `calendar`: name of the calendar the entry belongs to, `default` for the calendar in the
+///   data directory itself.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Record {
+    pub date: String,
+    pub kind: String,
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub text: String,
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub calendar: String,
+}
+
+impl Record {
+    /// Create a `Record` for the full day event `event` on `date`.
+    fn full_day(entry_date: &date::Date, event: &str, uid: Option<String>) -> Self {
+        let mut record = Record {
+            date: iso_date(entry_date),
+            kind: KIND_FULL_DAY.to_string(),
+            start: None,
+            end: None,
+            text: event.to_string(),
+            id: String::new(),
+            calendar: String::new(),
+        };
+        record.id = uid.unwrap_or_else(|| record.content_id());
+        return record;
+    }
+
+    /// Create a `Record` for the timed event `event` on `date`.
+    fn timed(
+        entry_date: &date::Date,
+        start: time::Time,
+        end: Option<time::Time>,
+        event: &str,
+        uid: Option<String>,
+    ) -> Self {
+        let mut record = Record {
+            date: iso_date(entry_date),
+            kind: KIND_TIMED.to_string(),
+            start: Some(start.to_string()),
+            end: end.map(|e| e.to_string()),
+            text: event.to_string(),
+            id: String::new(),
+            calendar: String::new(),
+        };
+        record.id = uid.unwrap_or_else(|| record.content_id());
+        return record;
+    }
+
+    /// Identifier derived from the contents of the `Record`.
+    ///
+    /// The calendar is left out, so moving an entry to another calendar keeps its identifier.
+    pub fn content_id(&self) -> String {
+        let fields = [
+            self.date.as_str(),
+            self.kind.as_str(),
+            self.start.as_deref().unwrap_or(""),
+            self.end.as_deref().unwrap_or(""),
+            self.text.as_str(),
+        ];
+        return format!("{:016x}", stable_hash(&fields));
+    }
+
+    /// Obtain the `Date` of the `Record`, reading `yyyy-mm-dd`.
+    pub fn parsed_date(&self) -> Result<date::Date, String> {
+        let naive = match chrono::NaiveDate::parse_from_str(&self.date, "%Y-%m-%d") {
+            Ok(n) => n,
+            Err(_) => {
+                return Err(format!("invalid date `{}`, expected yyyy-mm-dd", self.date));
+            }
+        };
+        return Ok(date::Date::from_naive_date(&naive));
+    }
+}
+
+/// Hash `fields` with the 64-bit FNV-1a hash.
+///
+/// Unlike the hashers of the standard library, the hash does not change between versions of crust
+/// or of the compiler, so it can be used for identifiers that are stored or exported.
+pub fn stable_hash(fields: &[&str]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for field in fields.iter() {
+        // Separate the fields, so moving text from one field to the next changes the hash.
+        for byte in field.bytes().chain(std::iter::once(0)) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    return hash;
+}
+
+/// Origin of an agenda entry, used to modify the entry it was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// Index in the `day` events of the stored agenda.
+    Day(usize),
+    /// Index in the `timestamp` events of the stored agenda.
+    Timed(usize),
+    /// Index in the recurring event definitions.
+    Occurrence(usize),
+}
+
+/// Agenda entry on a single date together with its origin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub record: Record,
+    pub source: Source,
+}
+
+/// List the entries on `entry_date` from the `stored` agenda of that date and the `recurring`
+/// event definitions.
+///
+/// Full day events come before timed events, which are ordered chronologically. This is the
+/// order of every listing, the position of an entry in it is the index shown to the user. The
+/// entries of several calendars are merged with `listing_order`.
+pub fn date_entries(
+    entry_date: &date::Date,
+    stored: &file::parser::Agenda,
+    recurring: &[recurrence::Recurrence],
+) -> Vec<Entry> {
+    let mut day_entries: Vec<Entry> = Vec::new();
+    let mut timed_entries: Vec<Entry> = Vec::new();
+    for (i, day_event) in stored.day.iter().enumerate() {
+        if day_event.event.is_empty() {
+            continue;
+        }
+        day_entries.push(Entry {
+            record: Record::full_day(entry_date, &day_event.event, day_event.uid.clone()),
+            source: Source::Day(i),
+        });
+    }
+    for (i, r) in recurring.iter().enumerate() {
+        if !r.occurs_on(entry_date) {
+            continue;
+        }
+        let id = Some(occurrence_id(r, entry_date));
+        match r.start {
+            Some(start) if !r.full_day => timed_entries.push(Entry {
+                record: Record::timed(entry_date, start, r.end, &r.event, id),
+                source: Source::Occurrence(i),
+            }),
+            _ => day_entries.push(Entry {
+                record: Record::full_day(entry_date, &r.event, id),
+                source: Source::Occurrence(i),
+            }),
+        }
+    }
+    for (i, timed_event) in stored.timestamp.iter().enumerate() {
+        if timed_event.event.is_empty() {
+            continue;
+        }
+        timed_entries.push(Entry {
+            record: Record::timed(
+                entry_date,
+                timed_event.start,
+                timed_event.end,
+                &timed_event.event,
+                timed_event.uid.clone(),
+            ),
+            source: Source::Timed(i),
+        });
+    }
+    // Times are written as `hh:mm`, so comparing the strings orders them chronologically.
+    timed_entries.sort_by(|a, b| {
+        let key = |e: &Entry| {
+            (
+                e.record.start.clone(),
+                e.record.end.clone(),
+                e.record.text.clone(),
+            )
+        };
+        key(a).cmp(&key(b))
+    });
+    day_entries.append(&mut timed_entries);
+    return day_entries;
+}
+
+/// Order of `record` in a listing of the entries of several calendars.
+///
+/// Full day events compare equal, so a stable sort keeps them in the order of the calendars and
+/// of `date_entries`. Timed events follow, ordered as in `date_entries`.
+pub fn listing_order(record: &Record) -> (&str, bool, Option<&str>, Option<&str>, &str) {
+    if record.kind == KIND_FULL_DAY {
+        return (&record.date, false, None, None, "");
+    }
+    return (
+        &record.date,
+        true,
+        record.start.as_deref(),
+        record.end.as_deref(),
+        &record.text,
+    );
+}
+
+/// Collect the entries of every date between `from` and `to`, both inclusive, in the
+/// `calendars` as `Record`s.
+///
+/// Entries are ordered by date and then as in `date_entries`, see `listing_order`. Events that
+/// cannot be read are left out.
+pub fn collect_records(
+    calendars: &[calendar::Calendar],
+    from: &date::Date,
+    to: &date::Date,
+) -> Vec<Record> {
+    let mut records: Vec<Record> = Vec::new();
+    for cal in calendars.iter() {
+        let storage = cal.storage.as_ref();
+        let recurring: Vec<recurrence::Recurrence> = storage.load_recurring().unwrap_or_default();
+        records.append(&mut dates_records(
+            cal,
+            &storage::agenda_dates(storage, from, to),
+            &recurring,
+        ));
+    }
+    records.sort_by(|a, b| listing_order(a).cmp(&listing_order(b)));
+    return records;
+}
+
+/// Collect the entries between `from` and `to` in the `calendars` whose text contains `query`,
+/// ignoring case.
+///
+/// Only the dates found by `Storage::search_dates` and the occurrences of matching recurring
+/// events are read.
+pub fn search_records(
+    calendars: &[calendar::Calendar],
+    query: &str,
+    from: &date::Date,
+    to: &date::Date,
+) -> Vec<Record> {
+    let query = query.to_lowercase();
+    let mut records: Vec<Record> = Vec::new();
+    for cal in calendars.iter() {
+        let storage = cal.storage.as_ref();
+        let recurring: Vec<recurrence::Recurrence> = storage.load_recurring().unwrap_or_default();
+        let mut dates: Vec<date::Date> = storage.search_dates(&query, from, to).unwrap_or_default();
+        for recurrence in recurring.iter() {
+            if recurrence.event.to_lowercase().contains(&query) {
+                dates.append(&mut recurrence.occurrences(from, to));
+            }
+        }
+        dates.sort();
+        dates.dedup();
+        records.extend(
+            dates_records(cal, &dates, &recurring)
+                .into_iter()
+                .filter(|r| r.text.to_lowercase().contains(&query)),
+        );
+    }
+    records.sort_by(|a, b| listing_order(a).cmp(&listing_order(b)));
+    return records;
+}
+
+/// Collect the entries of the sorted `dates` in `cal` as `Record`s, given its `recurring`
+/// events.
+fn dates_records(
+    cal: &calendar::Calendar,
+    dates: &[date::Date],
+    recurring: &[recurrence::Recurrence],
+) -> Vec<Record> {
+    let mut records: Vec<Record> = Vec::new();
+    for entry_date in dates.iter() {
+        let stored = cal.storage.load_day(entry_date).unwrap_or_default();
+        for mut entry in date_entries(entry_date, &stored, recurring).into_iter() {
+            entry.record.calendar = cal.name.clone();
+            records.push(entry.record);
+        }
+    }
+    return records;
+}
+
+/// Describe the times of `record` for text output, e.g. `09:00 - 10:00` or `all day`.
+pub fn record_times(record: &Record) -> String {
+    return match (&record.start, &record.end) {
+        (Some(start), Some(end)) => format!("{} - {}", start, end),
+        (Some(start), None) => start.clone(),
+        _ => "all day".to_string(),
+    };
+}
+
+/// Write `records` in the JSON, CSV or TSV `format`.
+///
+/// JSON is written as an array of objects, CSV and TSV start with a header line naming the
+/// columns. CSV and TSV only have a `calendar` column if `with_calendar` is set, JSON always
+/// contains the calendar. The `Text` format is not handled here and gives an empty string.
+pub fn render_records(records: &[Record], format: OutputFormat, with_calendar: bool) -> String {
+    match format {
+        OutputFormat::Json => match serde_json::to_string_pretty(records) {
+            Ok(mut json) => {
+                json.push('\n');
+                json
+            }
+            Err(_) => String::new(),
+        },
+        OutputFormat::Csv => render_separated(records, ',', csv_field, with_calendar),
+        OutputFormat::Tsv => render_separated(records, '\t', tsv_field, with_calendar),
+        OutputFormat::Text => String::new(),
+    }
+}
+
+/// Write `records` as lines of fields separated by `separator`, escaped with `escape`, with a
+/// `calendar` column if `with_calendar` is set.
+fn render_separated(
+    records: &[Record],
+    separator: char,
+    escape: fn(&str) -> String,
+    with_calendar: bool,
+) -> String {
+    let mut columns: Vec<&str> = COLUMNS.to_vec();
+    if with_calendar {
+        columns.push(CALENDAR_COLUMN);
+    }
+    let mut output = columns.join(&separator.to_string());
+    output.push('\n');
+    for record in records.iter() {
+        let mut fields: Vec<&str> = vec![
+            record.date.as_str(),
+            record.kind.as_str(),
+            record.start.as_deref().unwrap_or(""),
+            record.end.as_deref().unwrap_or(""),
+            record.text.as_str(),
+            record.id.as_str(),
+        ];
+        if with_calendar {
+            fields.push(record.calendar.as_str());
+        }
+        let fields: Vec<String> = fields.iter().map(|field| escape(field)).collect();
+        output.push_str(&fields.join(&separator.to_string()));
+        output.push('\n');
+    }
+    return output;
+}
+
+/// Quote a CSV field if it contains a comma, quote or line break, doubling the quotes.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        return format!("\"{}\"", field.replace('"', "\"\""));
+    }
+    return field.to_string();
+}
+
+/// Escape backslashes, tabs and line breaks of a TSV field.
+fn tsv_field(field: &str) -> String {
+    return field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r");
+}
+
+/// Import the records of a JSON file written by `--output json`.
+///
+/// Every record is added to the agenda of its date with its `id` as `uid`. Records whose `id` is
+/// already present on that date, including occurrences of recurring events, are left unchanged
+/// when their contents are the same and replaced otherwise. Records without `id` get the hash of
+/// their contents. Invalid records are skipped and listed in the report. Every record is added to
+/// `cal`, whichever calendar it was listed from.
+pub fn import_json(
+    cal: &calendar::Calendar,
+    path: &std::path::Path,
+) -> Result<ImportReport, error::Error> {
+    let storage = cal.storage.as_ref();
+    let contents = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => {
+            return Err(error::Error::Invalid(format!(
+                "Error reading {}: {}",
+                path.display(),
+                e
+            )));
+        }
+    };
+    let records: Vec<Record> = match serde_json::from_str(&contents) {
+        Ok(r) => r,
+        Err(e) => {
+            return Err(error::Error::Invalid(format!(
+                "Error parsing {}: {}",
+                path.display(),
+                e
+            )));
+        }
+    };
+    let _lock = storage.lock()?;
+
+    let mut report = ImportReport::default();
+    for (index, mut record) in records.into_iter().enumerate() {
+        let entry_date = match record.parsed_date() {
+            Ok(d) => d,
+            Err(e) => {
+                report.skipped.push(format!("record {}: {}", index + 1, e));
+                continue;
+            }
+        };
+        let is_timed = match record.kind.as_str() {
+            KIND_FULL_DAY => false,
+            KIND_TIMED => true,
+            other => {
+                report
+                    .skipped
+                    .push(format!("record {}: unknown kind `{}`", index + 1, other));
+                continue;
+            }
+        };
+        let (start, end) = match record_times_parsed(&record, is_timed) {
+            Ok(t) => t,
+            Err(e) => {
+                report.skipped.push(format!("record {}: {}", index + 1, e));
+                continue;
+            }
+        };
+        // Compare the times in the same format as they are written.
+        record.start = start.map(|s| s.to_string());
+        record.end = end.map(|e| e.to_string());
+        if record.text.is_empty() {
+            report
+                .skipped
+                .push(format!("record {}: event without text", index + 1));
+            continue;
+        }
+        if record.id.is_empty() {
+            record.id = record.content_id();
+        }
+        record.calendar = cal.name.clone();
+
+        let mut agenda: file::parser::Agenda = storage.load_day(&entry_date)?;
+
+        // Compare with the entries already present on the date.
+        let existing: Vec<Record> =
+            collect_records(std::slice::from_ref(cal), &entry_date, &entry_date);
+        match existing.iter().find(|r| r.id == record.id) {
+            Some(r) if *r == record => {
+                report.unchanged += 1;
+                continue;
+            }
+            Some(_) if !agenda.uids().contains(&record.id) => {
+                report.skipped.push(format!(
+                    "record {}: `{}` is an occurrence of a repeating entry, edit it with \
+                     `crust edit --recurring`",
+                    index + 1,
+                    record.id
+                ));
+                continue;
+            }
+            Some(_) => report.updated += 1,
+            None => report.imported += 1,
+        }
+
+        let uid = Some(record.id.clone());
+        agenda.day.retain(|e| e.uid != uid);
+        agenda.timestamp.retain(|e| e.uid != uid);
+        match start {
+            Some(start) => agenda.timestamp.push(file::parser::TimedEvent {
+                start,
+                end,
+                event: record.text.clone(),
+                uid,
+            }),
+            None => agenda.day.push(file::parser::DayEvent {
+                event: record.text.clone(),
+                uid,
+            }),
+        }
+        storage.save_day(&entry_date, &agenda)?;
+    }
+    return Ok(report);
+}
+
+/// Parse the start and end time of `record`, both `None` for full day events.
+///
+/// Timed events need a start time, and their end time cannot lie before the start time.
+fn record_times_parsed(
+    record: &Record,
+    is_timed: bool,
+) -> Result<(Option<time::Time>, Option<time::Time>), String> {
+    if !is_timed {
+        return Ok((None, None));
+    }
+    let start: time::Time = match record.start.as_deref() {
+        Some(s) if !s.trim().is_empty() => s.parse()?,
+        _ => return Err("timed event without start".to_string()),
+    };
+    let end: Option<time::Time> = match record.end.as_deref() {
+        Some(e) if !e.trim().is_empty() => Some(e.parse()?),
+        _ => None,
+    };
+    if end.is_some_and(|e| e < start) {
+        return Err(format!(
+            "end {} lies before start {}",
+            end.unwrap_or_default(),
+            start
+        ));
+    }
+    return Ok((Some(start), end));
+}
+
+/// Identifier of the occurrence of the recurring event `recurrence` on `entry_date`.
+fn occurrence_id(recurrence: &recurrence::Recurrence, entry_date: &date::Date) -> String {
+    return format!("{}/{}", recurrence.id, iso_date(entry_date));
+}
+
+/// Format `entry_date` as `yyyy-mm-dd`.
+pub fn iso_date(entry_date: &date::Date) -> String {
+    return format!(
+        "{:04}-{:02}-{:02}",
+        entry_date.year, entry_date.month, entry_date.day
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(day: i32, month: i32, year: i32) -> date::Date {
+        return date::Date { day, month, year };
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("plain text"), "plain text");
+        assert_eq!(csv_field("a, b"), "\"a, b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn tsv_fields_are_escaped() {
+        assert_eq!(tsv_field("a\tb\nc\\d"), "a\\tb\\nc\\\\d");
+    }
+
+    #[test]
+    fn csv_rows_quote_the_text() {
+        let record = Record::full_day(&d(1, 2, 2026), "Lunch, with \"Bob\"", None);
+        let output = render_records(std::slice::from_ref(&record), OutputFormat::Csv, false);
+        let row = output.lines().nth(1).unwrap();
+        assert_eq!(
+            row,
+            format!(
+                "2026-02-01,full_day,,,\"Lunch, with \"\"Bob\"\"\",{}",
+                record.id
+            )
+        );
+    }
+
+    #[test]
+    fn calendar_column_is_optional() {
+        let mut record = Record::full_day(&d(1, 2, 2026), "Holiday", None);
+        record.calendar = "work".to_string();
+        let records = std::slice::from_ref(&record);
+        let without = render_records(records, OutputFormat::Tsv, false);
+        assert_eq!(
+            without.lines().next(),
+            Some("date\tkind\tstart\tend\ttext\tid")
+        );
+        let with = render_records(records, OutputFormat::Tsv, true);
+        assert_eq!(
+            with.lines().next(),
+            Some("date\tkind\tstart\tend\ttext\tid\tcalendar")
+        );
+        assert!(with.lines().nth(1).unwrap().ends_with("\twork"));
+    }
+
+    #[test]
+    fn content_id_is_stable() {
+        let record = Record::timed(
+            &d(1, 2, 2026),
+            time::Time::new(9, 30).unwrap(),
+            None,
+            "Meeting",
+            None,
+        );
+        // The identifier is stored in exported files, so it may never change.
+        assert_eq!(record.id, "aa3b6e9b9abe8e24");
+        let mut other = record.clone();
+        other.calendar = "work".to_string();
+        assert_eq!(other.content_id(), record.id);
+        other.text = "Meeting 2".to_string();
+        assert_ne!(other.content_id(), record.id);
+    }
+}
@@ -0,0 +1,94 @@
+// External crates
+extern crate crossterm;
+extern crate ratatui;
+
+use crossterm::style::Stylize;
+use std::io::IsTerminal;
+
+// Local files
+use crate::configuration::config;
+use crate::date::date;
+use crate::output::records;
+use crate::storage::calendar;
+
+/// Check if the text written to stdout should be colored.
+///
+/// Colors are used only when stdout is a terminal and the `NO_COLOR` environment variable is not
+/// set to a non-empty value, see <https://no-color.org>.
+pub fn use_color() -> bool {
+    if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        return false;
+    }
+    return std::io::stdout().is_terminal();
+}
+
+/// Render the agenda entries of `date` as text.
+///
+/// The title of the date is shown as a heading followed by the `records` of that date, one
+/// entry per line, numbered in the order of `records::date_entries`. The numbers select entries
+/// in `crust rm` and `crust edit`. Entries are prefixed by the icon of their calendar in `conf`,
+/// and entries outside the default calendar end with the name of their calendar. If `color` is
+/// set, the colors of the agenda and of the calendars in `conf` are used.
+pub fn render_day(
+    date: &date::Date,
+    records: &[records::Record],
+    conf: &config::Config,
+    color: bool,
+) -> String {
+    let mut text = paint(date.agenda_title().trim(), conf.agenda_title, color, false);
+    text.push('\n');
+    for (index, record) in records.iter().enumerate() {
+        let mut line = format!("{:<13}  ", records::record_times(record));
+        if let Some(icon) = conf.calendar_icon(&record.calendar) {
+            line.push_str(&format!("{} ", icon));
+        }
+        line.push_str(&record.text);
+        if record.calendar != calendar::DEFAULT_CALENDAR {
+            line.push_str(&format!("  [{}]", record.calendar));
+        }
+        let fg = conf.entry_color(&record.calendar, &record.kind);
+        text.push_str(&format!(
+            "  {:>2}  {}\n",
+            index + 1,
+            paint(&line, fg, color, true)
+        ));
+    }
+    return text;
+}
+
+/// Render the agenda entries of every date between `from` and `to`, both inclusive, that has
+/// entries in the `calendars` as text, separated by empty lines.
+pub fn render_range(
+    calendars: &[calendar::Calendar],
+    from: &date::Date,
+    to: &date::Date,
+    conf: &config::Config,
+    color: bool,
+) -> String {
+    let mut text = String::new();
+    let all_records = records::collect_records(calendars, from, to);
+    // Records are ordered by date, render every run of records with the same date.
+    for day_records in all_records.chunk_by(|a, b| a.date == b.date) {
+        let entry_date = match day_records[0].parsed_date() {
+            Ok(d) => d,
+            Err(_) => continue,
+        };
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&render_day(&entry_date, day_records, conf, color));
+    }
+    return text;
+}
+
+/// Wrap `text` in the escape codes for `fg` and optionally italics, if `color` is set.
+fn paint(text: &str, fg: ratatui::style::Color, color: bool, italic: bool) -> String {
+    if !color {
+        return text.to_string();
+    }
+    let styled = text.with(crossterm::style::Color::from(fg));
+    if italic {
+        return styled.italic().to_string();
+    }
+    return styled.to_string();
+}
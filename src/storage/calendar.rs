@@ -0,0 +1,112 @@
+// Local files
+use super::storage;
+use crate::error::error;
+
+/// Name of the calendar stored in the data directory itself.
+pub const DEFAULT_CALENDAR: &str = "default";
+
+/// Named agenda, stored in a subdirectory of the data directory.
+///
+/// The `default` calendar is stored in the data directory itself, so the agenda of versions
+/// without named calendars becomes the default calendar. Every calendar has its own storage
+/// backend and lock.
+#[derive(Clone)]
+pub struct Calendar {
+    pub name: String,
+    pub dir: std::path::PathBuf,
+    pub storage: std::rc::Rc<dyn storage::Storage>,
+}
+
+/// Check that `name` can be used as the name of a calendar.
+///
+/// Names consist of letters, digits, `-` and `_`, so they are valid directory names on every
+/// platform and cannot be mistaken for the files of the storage backends.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "invalid calendar name `{}`, use only letters, digits, - and _",
+            name
+        ));
+    }
+    return Ok(());
+}
+
+/// Obtain the directory of the calendar `name` in the data directory `data_dir`.
+pub fn calendar_dir(data_dir: &std::path::Path, name: &str) -> std::path::PathBuf {
+    if name == DEFAULT_CALENDAR {
+        return data_dir.to_path_buf();
+    }
+    return data_dir.join(name);
+}
+
+/// List the names of the calendars in `data_dir`, the default calendar first and the others
+/// sorted by name.
+pub fn calendar_names(data_dir: &std::path::Path) -> Result<Vec<String>, String> {
+    let mut names: Vec<String> = Vec::new();
+    if data_dir.is_dir() {
+        let entries = match std::fs::read_dir(data_dir) {
+            Ok(e) => e,
+            Err(e) => {
+                return Err(format!("Error reading {}: {}", data_dir.display(), e));
+            }
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if entry.path().is_dir() && name != DEFAULT_CALENDAR && validate_name(&name).is_ok() {
+                names.push(name);
+            }
+        }
+    }
+    names.sort();
+    names.insert(0, DEFAULT_CALENDAR.to_string());
+    return Ok(names);
+}
+
+/// Open the calendar `name` in `data_dir`, creating it if it does not exist yet.
+pub fn open_calendar(data_dir: &std::path::Path, name: &str) -> Result<Calendar, error::Error> {
+    if let Err(e) = validate_name(name) {
+        return Err(error::Error::Invalid(e));
+    }
+    let dir = calendar_dir(data_dir, name);
+    let storage = storage::open(&dir)?;
+    return Ok(Calendar {
+        name: name.to_string(),
+        dir,
+        storage: std::rc::Rc::from(storage),
+    });
+}
+
+/// Open the calendars `names` in `data_dir`, or every calendar if `names` is empty.
+///
+/// Unlike `open_calendar`, calendars that do not exist are an error, so a misspelled name is
+/// reported instead of showing an empty agenda.
+pub fn open_calendars(
+    data_dir: &std::path::Path,
+    names: &[String],
+) -> Result<Vec<Calendar>, error::Error> {
+    let existing = calendar_names(data_dir)?;
+    if names.is_empty() {
+        return existing
+            .iter()
+            .map(|name| open_calendar(data_dir, name))
+            .collect();
+    }
+    let mut calendars: Vec<Calendar> = Vec::new();
+    for name in names.iter() {
+        if !existing.contains(name) {
+            return Err(error::Error::NotFound(format!(
+                "Unknown calendar `{}`, the calendars are: {}.",
+                name,
+                existing.join(", ")
+            )));
+        }
+        if calendars.iter().all(|c| c.name != *name) {
+            calendars.push(open_calendar(data_dir, name)?);
+        }
+    }
+    return Ok(calendars);
+}
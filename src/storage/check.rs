@@ -0,0 +1,697 @@
+// External crates
+extern crate regex;
+extern crate toml;
+
+// Local files
+use super::calendar;
+use super::relocate;
+use super::storage;
+use crate::date::date;
+use crate::date::time;
+use crate::file::parser;
+use crate::file::recurrence;
+
+/// Keys of the full day events in a day file.
+const DAY_KEYS: [&str; 2] = ["event", "uid"];
+/// Keys of the timed events in a day file.
+const TIMED_KEYS: [&str; 4] = ["start", "end", "event", "uid"];
+
+/// Problem with a file of a calendar, found by `check`.
+#[derive(Debug, Clone)]
+pub struct Problem {
+    pub path: std::path::PathBuf,
+    /// Line of the file the problem is on, starting at 1, if it is about a single line.
+    pub line: Option<usize>,
+    pub message: String,
+    /// The problem was repaired by `check` with `fix` set.
+    pub fixed: bool,
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        write!(f, ": {}", self.message)?;
+        if self.fixed {
+            write!(f, " (fixed)")?;
+        }
+        return Ok(());
+    }
+}
+
+impl Problem {
+    /// Create a `Problem` that is not fixed.
+    fn new(path: &std::path::Path, line: Option<usize>, message: String) -> Self {
+        return Problem {
+            path: path.to_path_buf(),
+            line,
+            message,
+            fixed: false,
+        };
+    }
+}
+
+/// Problems found in a single day file, and whether `--fix` can repair all of them.
+struct FileProblems {
+    problems: Vec<Problem>,
+    repairable: bool,
+}
+
+impl FileProblems {
+    /// Add a problem that is repaired by writing the entries of the file again.
+    fn repairable(&mut self, path: &std::path::Path, line: Option<usize>, message: String) {
+        self.problems.push(Problem::new(path, line, message));
+    }
+
+    /// Add a problem that has to be repaired by hand.
+    fn manual(&mut self, path: &std::path::Path, line: Option<usize>, message: String) {
+        self.problems.push(Problem::new(path, line, message));
+        self.repairable = false;
+    }
+}
+
+/// Check the files of the calendar `cal` and list the problems found.
+///
+/// Reported are files that cannot be read, invalid TOML, unknown keys, invalid times, events
+/// that end before they start, invalid dates such as `31-02-2024.toml` and files that are not
+/// agenda files, all of which make entries disappear without a hint. With `fix`, the mechanical
+/// problems are repaired while the calendar is locked:
+/// - times in other formats than `hh:mm`, and empty end times, are rewritten,
+/// - timed events with an empty or missing start time become full day events,
+/// - timed events are put in chronological order,
+/// - events without text, and files without events, are removed,
+/// - day files named like `1-2-2024.toml` are renamed to `01-02-2024.toml`.
+///
+/// Files with problems that have to be repaired by hand are never rewritten, so nothing in them
+/// is lost.
+pub fn check(cal: &calendar::Calendar, fix: bool) -> Result<Vec<Problem>, String> {
+    let _lock = if fix { Some(cal.storage.lock()?) } else { None };
+    let mut problems: Vec<Problem> = Vec::new();
+    let backend = storage::current_backend(&cal.dir);
+    check_dir(cal, backend, fix, &mut problems)?;
+
+    // Day files are checked above, the database is checked through the storage.
+    if backend == storage::Backend::Sqlite {
+        let database = cal.dir.join(storage::DATABASE_FILENAME);
+        for entry_date in storage::stored_dates(cal.storage.as_ref())?.iter() {
+            let result = cal
+                .storage
+                .load_day(entry_date)
+                .and_then(|agenda| agenda.validate());
+            if let Err(e) = result {
+                problems.push(Problem::new(
+                    &database,
+                    None,
+                    format!("{}: {}", entry_date, e),
+                ));
+            }
+        }
+    }
+    if backend == storage::Backend::Toml {
+        let path = cal.dir.join(recurrence::RECURRING_FILENAME);
+        if let Some(problem) = check_recurring_file(&path) {
+            problems.push(problem);
+        }
+    } else if let Err(e) = cal.storage.load_recurring() {
+        let database = cal.dir.join(storage::DATABASE_FILENAME);
+        problems.push(Problem::new(
+            &database,
+            None,
+            format!("the repeating entries cannot be read: {}", e),
+        ));
+    }
+    return Ok(problems);
+}
+
+/// Check that the repeating entries in the file at `path` can be read, if the file exists.
+fn check_recurring_file(path: &std::path::Path) -> Option<Problem> {
+    if !path.exists() {
+        return None;
+    }
+    let contents = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => {
+            return Some(Problem::new(path, None, format!("cannot be read: {}", e)));
+        }
+    };
+    return match toml::from_str::<recurrence::RecurringEvents>(&contents) {
+        Ok(_) => None,
+        Err(e) => Some(Problem::new(
+            path,
+            e.span().map(|span| line_of(&contents, span.start)),
+            format!(
+                "invalid repeating entries, none of them are shown: {}",
+                e.message().replace('\n', ", ")
+            ),
+        )),
+    };
+}
+
+/// Check the names of the files in the directory of `cal`, and the contents of its day files
+/// when it is stored in the `backend` TOML.
+fn check_dir(
+    cal: &calendar::Calendar,
+    backend: storage::Backend,
+    fix: bool,
+    problems: &mut Vec<Problem>,
+) -> Result<(), String> {
+    let mut paths: Vec<std::path::PathBuf> = match std::fs::read_dir(&cal.dir) {
+        Ok(entries) => entries.flatten().map(|e| e.path()).collect(),
+        Err(e) => {
+            return Err(format!("Error listing {}: {}", cal.dir.display(), e));
+        }
+    };
+    paths.sort();
+
+    let day_file = regex_or_err(r"^([0-9]{2})-([0-9]{2})-([0-9]{4})\.toml$")?;
+    let loose_day_file = regex_or_err(r"^([0-9]{1,2})-([0-9]{1,2})-([0-9]{4})\.(?i:toml)$")?;
+    for path in paths.iter() {
+        let name = match path.file_name() {
+            Some(n) => n.to_string_lossy().to_string(),
+            None => continue,
+        };
+        // Hidden files are the lock file and temporary files.
+        if name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            if cal.name != calendar::DEFAULT_CALENDAR {
+                problems.push(Problem::new(
+                    path,
+                    None,
+                    "directory inside a calendar, it is ignored".to_string(),
+                ));
+            } else if let Err(e) = calendar::validate_name(&name) {
+                problems.push(Problem::new(path, None, format!("not a calendar, {}", e)));
+            }
+            continue;
+        }
+        if name == recurrence::RECURRING_FILENAME
+            || name == relocate::REPORT_FILENAME
+            || name.starts_with(storage::DATABASE_FILENAME)
+        {
+            continue;
+        }
+
+        let (caps, exact) = match day_file.captures(&name) {
+            Some(c) => (c, true),
+            None => match loose_day_file.captures(&name) {
+                Some(c) => (c, false),
+                None => {
+                    problems.push(Problem::new(
+                        path,
+                        None,
+                        "not an agenda file, it is ignored".to_string(),
+                    ));
+                    continue;
+                }
+            },
+        };
+        let file_date = date::Date {
+            day: caps[1].parse().unwrap_or(0),
+            month: caps[2].parse().unwrap_or(0),
+            year: caps[3].parse().unwrap_or(0),
+        };
+        if !file_date.validate() {
+            problems.push(Problem::new(
+                path,
+                None,
+                "invalid date, the entries in this file are never shown".to_string(),
+            ));
+            continue;
+        }
+        if backend == storage::Backend::Sqlite {
+            problems.push(Problem::new(
+                path,
+                None,
+                format!(
+                    "agenda file next to {}, it is ignored",
+                    storage::DATABASE_FILENAME
+                ),
+            ));
+            continue;
+        }
+
+        let mut path = path.clone();
+        if !exact {
+            let target = cal.dir.join(format!("{}.toml", file_date));
+            let mut problem = Problem::new(
+                &path,
+                None,
+                format!(
+                    "should be named {}.toml, the entries in this file are never shown",
+                    file_date
+                ),
+            );
+            if fix && !target.exists() {
+                if let Err(e) = std::fs::rename(&path, &target) {
+                    return Err(format!("Error renaming {}: {}", path.display(), e));
+                }
+                problem.fixed = true;
+                path = target;
+            }
+            let renamed = problem.fixed;
+            problems.push(problem);
+            if !renamed {
+                continue;
+            }
+        }
+        problems.append(&mut check_day_file(cal, &path, &file_date, fix));
+    }
+    return Ok(());
+}
+
+/// Check the contents of the day file at `path` of `file_date`, repairing it with `fix` if every
+/// problem is mechanical.
+fn check_day_file(
+    cal: &calendar::Calendar,
+    path: &std::path::Path,
+    file_date: &date::Date,
+    fix: bool,
+) -> Vec<Problem> {
+    let mut found = FileProblems {
+        problems: Vec::new(),
+        repairable: true,
+    };
+    let contents = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => {
+            found.manual(path, None, format!("cannot be read: {}", e));
+            return found.problems;
+        }
+    };
+    let table: toml::Table = match toml::from_str(&contents) {
+        Ok(t) => t,
+        Err(e) => {
+            let line = e.span().map(|span| line_of(&contents, span.start));
+            found.manual(
+                path,
+                line,
+                format!("invalid TOML: {}", e.message().replace('\n', ", ")),
+            );
+            return found.problems;
+        }
+    };
+
+    for (key, value) in table.iter() {
+        let keys: &[&str] = match key.as_str() {
+            "day" => &DAY_KEYS,
+            "timestamp" => &TIMED_KEYS,
+            _ => {
+                found.manual(
+                    path,
+                    key_line(&contents, 0, key),
+                    format!("unknown key `{}`, it is ignored", key),
+                );
+                continue;
+            }
+        };
+        let items = match value.as_array() {
+            Some(a) => a,
+            None => {
+                found.manual(
+                    path,
+                    key_line(&contents, 0, key),
+                    format!(
+                        "`{}` should be a list of [[{}]] tables, the entries of this date are not shown",
+                        key, key
+                    ),
+                );
+                continue;
+            }
+        };
+        let headers = header_lines(&contents, key);
+        for (i, item) in items.iter().enumerate() {
+            let header = headers.get(i).copied();
+            check_event(path, &contents, key, keys, item, header, &mut found);
+        }
+    }
+
+    // The remaining checks need the entries, which only exist for readable files.
+    let agenda = match read_agenda(&table) {
+        Some(a) => a,
+        None => {
+            if found.problems.is_empty() {
+                found.manual(path, None, "cannot be read".to_string());
+            }
+            return found.problems;
+        }
+    };
+    let stored_order: Option<Vec<(time::Time, Option<time::Time>)>> = table
+        .get("timestamp")
+        .and_then(|t| t.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| {
+                    let start = item.get("start")?.as_str()?.parse::<time::Time>().ok()?;
+                    let end = item
+                        .get("end")
+                        .and_then(|e| e.as_str())
+                        .and_then(|e| e.parse::<time::Time>().ok());
+                    Some((start, end))
+                })
+                .collect()
+        });
+    if let Some(order) = stored_order {
+        if order.windows(2).any(|pair| pair[0] > pair[1]) {
+            found.repairable(
+                path,
+                header_lines(&contents, "timestamp").first().copied(),
+                "timed events are not in chronological order".to_string(),
+            );
+        }
+    }
+    if agenda.is_empty() {
+        found.repairable(path, None, "file without events".to_string());
+    }
+
+    found.problems.sort_by_key(|problem| problem.line);
+    if fix && found.repairable && !found.problems.is_empty() {
+        match cal.storage.save_day(file_date, &agenda) {
+            Ok(_) => {
+                for problem in found.problems.iter_mut() {
+                    problem.fixed = true;
+                }
+            }
+            Err(e) => found.manual(path, None, format!("cannot be repaired: {}", e)),
+        }
+    }
+    return found.problems;
+}
+
+/// Check a single event `item` of the `kind` `day` or `timestamp`, with the allowed `keys`, whose
+/// `[[kind]]` header is on the line `header`.
+fn check_event(
+    path: &std::path::Path,
+    contents: &str,
+    kind: &str,
+    keys: &[&str],
+    item: &toml::Value,
+    header: Option<usize>,
+    found: &mut FileProblems,
+) {
+    let event = match item.as_table() {
+        Some(t) => t,
+        None => {
+            found.manual(
+                path,
+                header,
+                format!(
+                    "`{}` should contain tables, the entries of this date are not shown",
+                    kind
+                ),
+            );
+            return;
+        }
+    };
+    let from = header.unwrap_or(0);
+    let line = |key: &str| key_line(contents, from, key).or(header);
+
+    for key in event.keys() {
+        if !keys.contains(&key.as_str()) {
+            found.manual(
+                path,
+                line(key),
+                format!("unknown key `{}`, it is ignored", key),
+            );
+        }
+    }
+    match event.get("event") {
+        Some(toml::Value::String(text)) if text.is_empty() => {
+            found.repairable(path, line("event"), "event without text".to_string());
+        }
+        Some(toml::Value::String(_)) => {}
+        _ => found.manual(
+            path,
+            line("event").or(header),
+            "`event` should be the text of the event, the entries of this date are not shown"
+                .to_string(),
+        ),
+    }
+    if let Some(uid) = event.get("uid") {
+        if !uid.is_str() {
+            found.manual(
+                path,
+                line("uid"),
+                "`uid` should be text, the entries of this date are not shown".to_string(),
+            );
+        }
+    }
+    if kind != "timestamp" {
+        return;
+    }
+
+    let start = check_time(path, event, "start", &line, found);
+    let end = check_time(path, event, "end", &line, found);
+    if start.is_none() && !event.contains_key("start") {
+        found.repairable(
+            path,
+            header,
+            "timed event without `start`, the entries of this date are not shown until the \
+             event is made a full day event"
+                .to_string(),
+        );
+    }
+    if let (Some(start), Some(end)) = (start, end) {
+        if end < start {
+            found.manual(
+                path,
+                line("end"),
+                format!("ends at {} before it starts at {}", end, start),
+            );
+        }
+    }
+}
+
+/// Check the time `key` of the timed `event`, returning the time if it is valid.
+///
+/// Times in other formats than `hh:mm` are read, but are repaired to `hh:mm`. An empty `end`
+/// means there is no end time and is removed. Events with an empty `start`, which older versions
+/// wrote for events without a start time, are read as full day events.
+fn check_time(
+    path: &std::path::Path,
+    event: &toml::Table,
+    key: &str,
+    line: &dyn Fn(&str) -> Option<usize>,
+    found: &mut FileProblems,
+) -> Option<time::Time> {
+    let value = match event.get(key) {
+        Some(toml::Value::String(v)) => v,
+        Some(_) => {
+            found.manual(
+                path,
+                line(key),
+                format!(
+                    "`{}` should be a time like \"09:30\", the entries of this date are not shown",
+                    key
+                ),
+            );
+            return None;
+        }
+        None => return None,
+    };
+    if key == "end" && value.trim().is_empty() {
+        found.repairable(path, line(key), "empty `end`".to_string());
+        return None;
+    }
+    if key == "start" && value.trim().is_empty() {
+        found.repairable(
+            path,
+            line(key),
+            "empty `start`, the event is shown as a full day event".to_string(),
+        );
+        return None;
+    }
+    return match value.parse::<time::Time>() {
+        Ok(t) => {
+            if t.to_string() != *value {
+                found.repairable(
+                    path,
+                    line(key),
+                    format!("`{}` is written as \"{}\" instead of \"{}\"", key, value, t),
+                );
+            }
+            Some(t)
+        }
+        Err(e) => {
+            found.manual(
+                path,
+                line(key),
+                format!(
+                    "invalid `{}`: {}, the entries of this date are not shown",
+                    key, e
+                ),
+            );
+            None
+        }
+    };
+}
+
+/// Read the entries of the day file `table`, as they are written back by `--fix`.
+///
+/// Timed events without `start` are read as full day events, like events with an empty `start`.
+/// Returns `None` if the entries cannot be read.
+fn read_agenda(table: &toml::Table) -> Option<parser::Agenda> {
+    let mut table = table.clone();
+    if let Some(toml::Value::Array(items)) = table.get_mut("timestamp") {
+        for event in items.iter_mut().filter_map(|item| item.as_table_mut()) {
+            if !event.contains_key("start") {
+                event.insert("start".to_string(), toml::Value::String(String::new()));
+            }
+        }
+    }
+    let mut agenda: parser::Agenda = match toml::Value::Table(table).try_into() {
+        Ok(a) => a,
+        Err(_) => {
+            return None;
+        }
+    };
+    agenda.timestamp.sort();
+    return Some(agenda);
+}
+
+/// Compile the regular expression `pattern`.
+fn regex_or_err(pattern: &str) -> Result<regex::Regex, String> {
+    return match regex::Regex::new(pattern) {
+        Ok(r) => Ok(r),
+        Err(e) => Err(format!("Error creating the file name pattern: {}", e)),
+    };
+}
+
+/// Obtain the line, starting at 1, of the byte `offset` in `contents`.
+fn line_of(contents: &str, offset: usize) -> usize {
+    return contents[..offset.min(contents.len())].matches('\n').count() + 1;
+}
+
+/// Obtain the lines, starting at 1, of the `[[key]]` table headers in `contents`.
+fn header_lines(contents: &str, key: &str) -> Vec<usize> {
+    let header = format!("[[{}]]", key);
+    return contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.trim() == header)
+        .map(|(i, _)| i + 1)
+        .collect();
+}
+
+/// Find the line, starting at 1, of `key` in the table whose header is on line `from`, or of the
+/// top-level `key` if `from` is 0.
+fn key_line(contents: &str, from: usize, key: &str) -> Option<usize> {
+    let mut in_table = from > 0;
+    for (i, line) in contents.lines().enumerate().skip(from) {
+        let trimmed = line.trim_start();
+        if let Some(header) = trimmed.strip_prefix('[') {
+            // Keys after the next header belong to another table.
+            if from > 0 {
+                return None;
+            }
+            if header.trim_start_matches('[').starts_with(key) {
+                return Some(i + 1);
+            }
+            in_table = true;
+            continue;
+        }
+        let assigned = trimmed
+            .strip_prefix(key)
+            .is_some_and(|rest| rest.trim_start().starts_with('='));
+        if assigned && (from > 0 || !in_table) {
+            return Some(i + 1);
+        }
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a calendar in a new temporary directory, with the day files `files`.
+    fn calendar_with(test: &str, files: &[(&str, &str)]) -> calendar::Calendar {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("crust-check-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, contents) in files.iter() {
+            std::fs::write(dir.join(name), contents).unwrap();
+        }
+        return calendar::open_calendar(&dir, calendar::DEFAULT_CALENDAR).unwrap();
+    }
+
+    #[test]
+    fn fix_makes_events_without_start_full_day_events() {
+        let cal = calendar_with(
+            "start",
+            &[
+                (
+                    "01-02-2026.toml",
+                    "[[timestamp]]\nstart = ''\nevent = 'Legacy'\n",
+                ),
+                (
+                    "02-02-2026.toml",
+                    "[[timestamp]]\nevent = 'Lunch'\n\n\
+                     [[timestamp]]\nstart = '09:00'\nevent = 'Stand-up'\n",
+                ),
+            ],
+        );
+        let problems = check(&cal, false).unwrap();
+        assert_eq!(problems.len(), 2);
+        assert!(problems.iter().all(|p| !p.fixed));
+
+        let problems = check(&cal, true).unwrap();
+        assert_eq!(problems.len(), 2);
+        assert!(problems.iter().all(|p| p.fixed));
+        assert!(check(&cal, false).unwrap().is_empty());
+
+        let lunch = cal
+            .storage
+            .load_day(&date::Date {
+                day: 2,
+                month: 2,
+                year: 2026,
+            })
+            .unwrap();
+        assert_eq!(lunch.day.len(), 1);
+        assert_eq!(lunch.day[0].event, "Lunch");
+        assert_eq!(lunch.timestamp.len(), 1);
+        let _ = std::fs::remove_dir_all(&cal.dir);
+    }
+
+    #[test]
+    fn fix_leaves_files_with_manual_problems() {
+        let contents = "[[timestamp]]\nstart = ''\nevent = 'Legacy'\ncolour = 'red'\n";
+        let cal = calendar_with("manual", &[("01-02-2026.toml", contents)]);
+        let problems = check(&cal, true).unwrap();
+        assert_eq!(problems.len(), 2);
+        assert!(problems.iter().all(|p| !p.fixed));
+        assert_eq!(
+            std::fs::read_to_string(cal.dir.join("01-02-2026.toml")).unwrap(),
+            contents
+        );
+        let _ = std::fs::remove_dir_all(&cal.dir);
+    }
+
+    #[test]
+    fn fix_leaves_events_with_invalid_start() {
+        let noon = "[[timestamp]]\nstart = 'noon'\nevent = 'Lunch'\n";
+        let number = "[[timestamp]]\nstart = 12\nevent = 'Lunch'\n";
+        let cal = calendar_with(
+            "invalid-start",
+            &[("01-02-2026.toml", noon), ("02-02-2026.toml", number)],
+        );
+        let problems = check(&cal, true).unwrap();
+        assert_eq!(problems.len(), 2);
+        assert!(problems.iter().all(|p| !p.fixed));
+        assert_eq!(
+            std::fs::read_to_string(cal.dir.join("01-02-2026.toml")).unwrap(),
+            noon
+        );
+        assert_eq!(
+            std::fs::read_to_string(cal.dir.join("02-02-2026.toml")).unwrap(),
+            number
+        );
+        let _ = std::fs::remove_dir_all(&cal.dir);
+    }
+}
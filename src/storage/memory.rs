@@ -0,0 +1,203 @@
+// Local files
+use super::storage;
+use crate::date::date;
+use crate::file::atomic;
+use crate::file::parser;
+use crate::file::recurrence;
+
+/// Storage of the agenda entries in memory, nothing is written to disk.
+///
+/// Used to run the commands against a known set of entries, for example in tests, without
+/// touching the entries of the user. The storage is used by a single process, so locking it
+/// does nothing.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    days: std::cell::RefCell<std::collections::BTreeMap<date::Date, parser::Agenda>>,
+    recurring: std::cell::RefCell<Vec<recurrence::Recurrence>>,
+}
+
+impl MemoryStore {
+    /// Create an empty `MemoryStore`.
+    pub fn new() -> Self {
+        return MemoryStore::default();
+    }
+}
+
+impl storage::Storage for MemoryStore {
+    fn list_dates(&self, from: &date::Date, to: &date::Date) -> Result<Vec<date::Date>, String> {
+        // `range` does not accept an empty range.
+        if from > to {
+            return Ok(Vec::new());
+        }
+        return Ok(self
+            .days
+            .borrow()
+            .range(*from..=*to)
+            .map(|(d, _)| *d)
+            .collect());
+    }
+
+    fn load_day(&self, date: &date::Date) -> Result<parser::Agenda, String> {
+        return Ok(self.days.borrow().get(date).cloned().unwrap_or_default());
+    }
+
+    fn save_day(&self, date: &date::Date, agenda: &parser::Agenda) -> Result<(), String> {
+        let cleaned = agenda.cleaned();
+        if cleaned.is_empty() {
+            self.days.borrow_mut().remove(date);
+        } else {
+            self.days.borrow_mut().insert(*date, cleaned);
+        }
+        return Ok(());
+    }
+
+    fn load_recurring(&self) -> Result<Vec<recurrence::Recurrence>, String> {
+        return Ok(self.recurring.borrow().clone());
+    }
+
+    fn save_recurring(&self, recurring: &[recurrence::Recurrence]) -> Result<(), String> {
+        *self.recurring.borrow_mut() = recurring.to_vec();
+        return Ok(());
+    }
+
+    fn lock(&self) -> Result<atomic::StoreLock, String> {
+        return Ok(atomic::StoreLock::unlocked());
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::date::time;
+    use crate::output::records;
+    use crate::storage::storage::Storage;
+
+    fn d(day: i32, month: i32, year: i32) -> date::Date {
+        return date::Date { day, month, year };
+    }
+
+    /// Create an agenda with the full day events `day` and the events `timed` at whole hours.
+    pub(crate) fn agenda(day: &[&str], timed: &[(u32, &str)]) -> parser::Agenda {
+        return parser::Agenda {
+            day: day
+                .iter()
+                .map(|e| parser::DayEvent {
+                    event: e.to_string(),
+                    uid: None,
+                })
+                .collect(),
+            timestamp: timed
+                .iter()
+                .map(|(hour, e)| parser::TimedEvent {
+                    start: time::Time::new(*hour, 0).unwrap(),
+                    end: None,
+                    event: e.to_string(),
+                    uid: None,
+                })
+                .collect(),
+        };
+    }
+
+    #[test]
+    fn save_and_load_day() {
+        let store = MemoryStore::new();
+        let stored = agenda(&["Holiday"], &[(9, "Meeting")]);
+        store.save_day(&d(1, 2, 2026), &stored).unwrap();
+        assert_eq!(store.load_day(&d(1, 2, 2026)).unwrap(), stored);
+        assert_eq!(
+            store.load_day(&d(2, 2, 2026)).unwrap(),
+            parser::Agenda::default()
+        );
+    }
+
+    #[test]
+    fn saving_an_empty_day_removes_it() {
+        let store = MemoryStore::new();
+        store
+            .save_day(&d(1, 2, 2026), &agenda(&["Holiday"], &[]))
+            .unwrap();
+        // Events without text are left out, so this agenda is empty.
+        store.save_day(&d(1, 2, 2026), &agenda(&[""], &[])).unwrap();
+        assert!(storage::stored_dates(&store).unwrap().is_empty());
+    }
+
+    #[test]
+    fn list_dates_in_range() {
+        let store = MemoryStore::new();
+        for day in [d(31, 1, 2026), d(1, 2, 2026), d(28, 2, 2026), d(1, 3, 2026)] {
+            store.save_day(&day, &agenda(&["Event"], &[])).unwrap();
+        }
+        assert_eq!(
+            store.list_dates(&d(1, 2, 2026), &d(28, 2, 2026)).unwrap(),
+            vec![d(1, 2, 2026), d(28, 2, 2026)]
+        );
+        assert!(store
+            .list_dates(&d(1, 3, 2026), &d(1, 2, 2026))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn delete_event_by_id() {
+        let store = MemoryStore::new();
+        let date = d(1, 2, 2026);
+        store
+            .save_day(
+                &date,
+                &agenda(&["Holiday"], &[(9, "Meeting"), (14, "Call")]),
+            )
+            .unwrap();
+        let ids: Vec<String> = records::date_entries(&date, &store.load_day(&date).unwrap(), &[])
+            .into_iter()
+            .filter(|e| e.record.text == "Meeting")
+            .map(|e| e.record.id)
+            .collect();
+        assert_eq!(ids.len(), 1);
+
+        assert!(store.delete_event(&date, &ids[0]).unwrap());
+        assert_eq!(
+            store.load_day(&date).unwrap(),
+            agenda(&["Holiday"], &[(14, "Call")])
+        );
+        // The event is gone, so deleting it again does nothing.
+        assert!(!store.delete_event(&date, &ids[0]).unwrap());
+        assert!(!store.delete_event(&d(2, 2, 2026), &ids[0]).unwrap());
+    }
+
+    #[test]
+    fn search_dates_lists_candidates_in_range() {
+        let store = MemoryStore::new();
+        store
+            .save_day(&d(1, 2, 2026), &agenda(&["Dentist"], &[]))
+            .unwrap();
+        store
+            .save_day(&d(5, 2, 2026), &agenda(&[], &[(9, "Meeting")]))
+            .unwrap();
+        store
+            .save_day(&d(5, 3, 2026), &agenda(&["Dentist"], &[]))
+            .unwrap();
+        // Every stored date in the range is a candidate, the events are matched afterwards.
+        assert_eq!(
+            store
+                .search_dates("dentist", &d(1, 2, 2026), &d(28, 2, 2026))
+                .unwrap(),
+            vec![d(1, 2, 2026), d(5, 2, 2026)]
+        );
+    }
+
+    #[test]
+    fn search_records_matches_the_text() {
+        let store = MemoryStore::new();
+        store
+            .save_day(&d(1, 2, 2026), &agenda(&["Dentist"], &[(9, "Meeting")]))
+            .unwrap();
+        let calendars = [crate::storage::calendar::Calendar {
+            name: "default".to_string(),
+            dir: std::path::PathBuf::new(),
+            storage: std::rc::Rc::new(store),
+        }];
+        let found = records::search_records(&calendars, "DENT", &d(1, 1, 2026), &d(31, 12, 2026));
+        let texts: Vec<&str> = found.iter().map(|r| r.text.as_str()).collect();
+        assert_eq!(texts, vec!["Dentist"]);
+    }
+}
@@ -0,0 +1,230 @@
+// Local files
+use super::calendar;
+use super::storage;
+use super::toml_store;
+
+/// Convert the agenda entries of every calendar in the data directory `data_dir` to the `to`
+/// backend, see `migrate`.
+///
+/// Calendars already stored in the `to` backend are left as they are. Returns a message with a
+/// line for every converted calendar.
+pub fn migrate_calendars(
+    data_dir: &std::path::Path,
+    to: storage::Backend,
+) -> Result<String, String> {
+    let mut messages: Vec<String> = Vec::new();
+    for name in calendar::calendar_names(data_dir)?.iter() {
+        let dir = calendar::calendar_dir(data_dir, name);
+        if storage::current_backend(&dir) == to {
+            continue;
+        }
+        match migrate(&dir, to) {
+            Ok(message) => messages.push(message),
+            Err(e) => {
+                // Report the calendars that did move, they stay in the new backend.
+                messages.push(e);
+                return Err(messages.join("\n"));
+            }
+        }
+    }
+    if messages.is_empty() {
+        return Err(format!(
+            "The agenda in {} is already stored as {}.",
+            data_dir.display(),
+            to
+        ));
+    }
+    return Ok(messages.join("\n"));
+}
+
+/// Convert the agenda entries in the data directory `dir` to the `to` backend.
+///
+/// Every stored date and the recurring event definitions are copied to the new backend, read
+/// back and compared with the originals. Only if they are equal, the old files are removed, so a
+/// failed migration leaves the agenda as it was. Returns a message describing what moved.
+pub fn migrate(dir: &std::path::Path, to: storage::Backend) -> Result<String, String> {
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        return Err(format!("Error creating {}: {}", dir.display(), e));
+    }
+    let from = storage::current_backend(dir);
+    if from == to {
+        return Err(format!(
+            "The agenda in {} is already stored as {}.",
+            dir.display(),
+            to
+        ));
+    }
+    if to == storage::Backend::Toml && !toml_files(dir)?.is_empty() {
+        return Err(format!(
+            "{} already contains agenda files next to {}, move them away before migrating.",
+            dir.display(),
+            storage::DATABASE_FILENAME
+        ));
+    }
+
+    let source = storage::open_backend(dir, from)?;
+    let _lock = source.lock()?;
+    let target = storage::open_backend(dir, to)?;
+    if let Err(e) = copy_all(source.as_ref(), target.as_ref()) {
+        // Drop the target before removing its files.
+        drop(target);
+        remove_backend_files(dir, to)?;
+        return Err(format!("{}, nothing changed.", e));
+    }
+    let dates = storage::stored_dates(source.as_ref())?;
+    let recurring = source.load_recurring()?;
+    drop(source);
+    drop(target);
+    remove_backend_files(dir, from)?;
+
+    return Ok(format!(
+        "Moved {} dates and {} repeating entries in {} from {} to {}.",
+        dates.len(),
+        recurring.len(),
+        dir.display(),
+        from,
+        to
+    ));
+}
+
+/// Copy every stored date and the recurring event definitions of `source` to `target`, and check
+/// that `target` reads back the same entries.
+fn copy_all(source: &dyn storage::Storage, target: &dyn storage::Storage) -> Result<(), String> {
+    let dates = storage::stored_dates(source)?;
+    for entry_date in dates.iter() {
+        target.save_day(entry_date, &source.load_day(entry_date)?)?;
+    }
+    let recurring = source.load_recurring()?;
+    target.save_recurring(&recurring)?;
+
+    if storage::stored_dates(target)? != dates {
+        return Err("The migrated dates differ from the original dates".to_string());
+    }
+    for entry_date in dates.iter() {
+        // Events without text are not stored, so they are not compared either.
+        if target.load_day(entry_date)? != source.load_day(entry_date)?.cleaned() {
+            return Err(format!(
+                "The migrated entries of {} differ from the original entries",
+                entry_date
+            ));
+        }
+    }
+    if target.load_recurring()? != recurring {
+        return Err(
+            "The migrated repeating entries differ from the original repeating entries".to_string(),
+        );
+    }
+    return Ok(());
+}
+
+/// List the `dd-mm-yyyy.toml` files and `recurring.toml` in `dir`.
+fn toml_files(dir: &std::path::Path) -> Result<Vec<std::path::PathBuf>, String> {
+    let store = toml_store::TomlStore::new(dir.to_path_buf());
+    let mut files: Vec<std::path::PathBuf> = storage::stored_dates(&store)?
+        .iter()
+        .map(|d| store.day_filepath(d))
+        .collect();
+    if store.recurring_filepath().exists() {
+        files.push(store.recurring_filepath());
+    }
+    return Ok(files);
+}
+
+/// List the existing files in `dir` that store the agenda entries with `backend`.
+pub fn backend_files(
+    dir: &std::path::Path,
+    backend: storage::Backend,
+) -> Result<Vec<std::path::PathBuf>, String> {
+    return match backend {
+        storage::Backend::Toml => toml_files(dir),
+        storage::Backend::Sqlite => Ok(["", "-wal", "-shm", "-journal"]
+            .iter()
+            .map(|suffix| dir.join(format!("{}{}", storage::DATABASE_FILENAME, suffix)))
+            .filter(|path| path.exists())
+            .collect()),
+    };
+}
+
+/// Remove the files of `backend` in `dir`.
+fn remove_backend_files(dir: &std::path::Path, backend: storage::Backend) -> Result<(), String> {
+    for file in backend_files(dir, backend)?.iter() {
+        if let Err(e) = std::fs::remove_file(file) {
+            return Err(format!("Error removing {}: {}", file.display(), e));
+        }
+    }
+    return Ok(());
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::date::date;
+    use crate::file::recurrence;
+    use crate::storage::memory::tests::agenda;
+    use crate::storage::storage::Storage;
+
+    #[test]
+    fn migrate_calendars_there_and_back() {
+        let mut data_dir = std::env::temp_dir();
+        data_dir.push(format!("crust-migrate-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&data_dir);
+        let work_dir = calendar::calendar_dir(&data_dir, "work");
+        std::fs::create_dir_all(&work_dir).unwrap();
+
+        let day = date::Date {
+            day: 1,
+            month: 2,
+            year: 2026,
+        };
+        let recurring = vec![recurrence::Recurrence {
+            id: "birthday".to_string(),
+            event: "Birthday".to_string(),
+            full_day: true,
+            start: None,
+            end: None,
+            frequency: recurrence::Frequency::Yearly,
+            interval: 1,
+            first: day,
+            by_day: Vec::new(),
+            count: None,
+            until: None,
+            exceptions: Vec::new(),
+        }];
+        let home = toml_store::TomlStore::new(data_dir.clone());
+        home.save_day(&day, &agenda(&["Holiday"], &[(9, "Dentist")]))
+            .unwrap();
+        home.save_recurring(&recurring).unwrap();
+        let work = toml_store::TomlStore::new(work_dir.clone());
+        work.save_day(&day, &agenda(&[], &[(10, "Meeting")]))
+            .unwrap();
+
+        let message = migrate_calendars(&data_dir, storage::Backend::Sqlite).unwrap();
+        assert_eq!(message.lines().count(), 2);
+        for dir in [&data_dir, &work_dir] {
+            assert_eq!(storage::current_backend(dir), storage::Backend::Sqlite);
+            assert!(backend_files(dir, storage::Backend::Toml)
+                .unwrap()
+                .is_empty());
+        }
+        assert!(migrate_calendars(&data_dir, storage::Backend::Sqlite).is_err());
+
+        migrate_calendars(&data_dir, storage::Backend::Toml).unwrap();
+        assert_eq!(
+            home.load_day(&day).unwrap(),
+            agenda(&["Holiday"], &[(9, "Dentist")])
+        );
+        assert_eq!(home.load_recurring().unwrap(), recurring);
+        assert_eq!(
+            work.load_day(&day).unwrap(),
+            agenda(&[], &[(10, "Meeting")])
+        );
+        // A calendar without recurring events gets no recurring.toml.
+        assert!(!work.recurring_filepath().exists());
+        for dir in [&data_dir, &work_dir] {
+            assert!(backend_files(dir, storage::Backend::Sqlite)
+                .unwrap()
+                .is_empty());
+        }
+        let _ = std::fs::remove_dir_all(&data_dir);
+    }
+}
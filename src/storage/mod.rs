@@ -0,0 +1,10 @@
+pub mod calendar;
+pub mod check;
+pub mod memory;
+pub mod migrate;
+pub mod relocate;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_store;
+#[allow(clippy::module_inception)]
+pub mod storage;
+pub mod toml_store;
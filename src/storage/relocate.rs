@@ -0,0 +1,133 @@
+// External crates
+extern crate chrono;
+extern crate dirs;
+
+use std::io::Write;
+
+// Local files
+use super::migrate;
+use super::storage;
+use crate::file::atomic;
+
+/// Name of the report written to the data directory after moving the agenda files into it.
+pub const REPORT_FILENAME: &str = "moved-from-cache.txt";
+
+/// Find $HOME/.cache/crust/, where older versions stored the agenda files, if it exists.
+pub fn old_cache_dir() -> Option<std::path::PathBuf> {
+    return dirs::cache_dir()
+        .map(|d| d.join("crust"))
+        .filter(|d| d.is_dir());
+}
+
+/// Move the agenda files from `old_dir`, see `old_cache_dir`, to the data directory `dir`.
+///
+/// Cache directories are cleaned by tools and users alike, so the files are moved as soon as
+/// `crust` runs with a different data directory. Nothing is moved if `old_dir` has no agenda
+/// files, or if `dir` already contains agenda files, in which case a warning is returned each
+/// time so the old files are not forgotten. After moving, a report listing the moved files is
+/// written to `dir`. Returns the message to show the user, if any.
+pub fn move_from_cache(
+    old_dir: &std::path::Path,
+    dir: &std::path::Path,
+) -> Result<Option<String>, String> {
+    if same_dir(old_dir, dir) {
+        return Ok(None);
+    }
+    let old_files = agenda_files(old_dir)?;
+    if old_files.is_empty() {
+        return Ok(None);
+    }
+    if !agenda_files(dir)?.is_empty() {
+        return Ok(Some(format!(
+            "warning: {} still contains agenda files of an older version of crust, but {} \
+             already contains agenda files. Move or merge them manually.",
+            old_dir.display(),
+            dir.display()
+        )));
+    }
+
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        return Err(format!("Error creating {}: {}", dir.display(), e));
+    }
+    // Lock both directories, so no other `crust` process changes the files while they move.
+    let _old_lock = atomic::StoreLock::acquire(&old_dir.join(atomic::LOCK_FILENAME))?;
+    let _lock = atomic::StoreLock::acquire(&dir.join(atomic::LOCK_FILENAME))?;
+    let mut moved: Vec<std::path::PathBuf> = Vec::new();
+    for old_file in agenda_files(old_dir)?.iter() {
+        let new_file = match old_file.file_name() {
+            Some(name) => dir.join(name),
+            None => continue,
+        };
+        move_file(old_file, &new_file)?;
+        moved.push(new_file);
+    }
+
+    let report_path = dir.join(REPORT_FILENAME);
+    write_report(&report_path, old_dir, dir, &moved)?;
+    return Ok(Some(format!(
+        "Moved {} agenda files from {} to {}, see {}.",
+        moved.len(),
+        old_dir.display(),
+        dir.display(),
+        report_path.display()
+    )));
+}
+
+/// List the agenda files of every backend in `dir`.
+fn agenda_files(dir: &std::path::Path) -> Result<Vec<std::path::PathBuf>, String> {
+    let mut files = migrate::backend_files(dir, storage::Backend::Toml)?;
+    files.append(&mut migrate::backend_files(dir, storage::Backend::Sqlite)?);
+    return Ok(files);
+}
+
+/// Check if `a` and `b` are the same directory, when `b` does not exist yet it is not.
+fn same_dir(a: &std::path::Path, b: &std::path::Path) -> bool {
+    return match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    };
+}
+
+/// Move the file at `from` to `to`, copying it when they lie on different file systems.
+fn move_file(from: &std::path::Path, to: &std::path::Path) -> Result<(), String> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    if let Err(e) = std::fs::copy(from, to) {
+        return Err(format!(
+            "Error moving {} to {}: {}",
+            from.display(),
+            to.display(),
+            e
+        ));
+    }
+    if let Err(e) = std::fs::remove_file(from) {
+        return Err(format!("Error removing {}: {}", from.display(), e));
+    }
+    return Ok(());
+}
+
+/// Write the report of moving the `moved` files from `old_dir` to `dir` to `path`.
+fn write_report(
+    path: &std::path::Path,
+    old_dir: &std::path::Path,
+    dir: &std::path::Path,
+    moved: &[std::path::PathBuf],
+) -> Result<(), String> {
+    let mut report = format!(
+        "On {} crust moved {} agenda files from {} to {}, as cache directories can be cleaned at \
+         any time:\n",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+        moved.len(),
+        old_dir.display(),
+        dir.display()
+    );
+    for file in moved.iter() {
+        report.push_str(&format!("{}\n", file.display()));
+    }
+    let result = std::fs::File::create(path).and_then(|mut file| file.write_all(report.as_bytes()));
+    if let Err(e) = result {
+        return Err(format!("Error writing {}: {}", path.display(), e));
+    }
+    return Ok(());
+}
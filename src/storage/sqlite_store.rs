@@ -0,0 +1,419 @@
+// External crates
+extern crate chrono;
+extern crate rusqlite;
+extern crate serde_json;
+
+// Local files
+use super::storage;
+use crate::date::date;
+use crate::date::time;
+use crate::file::atomic;
+use crate::file::parser;
+use crate::file::recurrence;
+use crate::output::records;
+
+/// Tables of the database, created when they do not exist yet.
+///
+/// Events are stored in `events` with their date as `yyyy-mm-dd`, so date ranges are found
+/// through the index on `date`. Full day events have no `start`. `events_text` indexes the text
+/// of the events in trigrams for searching, it is kept up to date by the triggers. Recurring
+/// event definitions are stored as JSON in `recurring`.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS events (
+    id INTEGER PRIMARY KEY,
+    date TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    start TEXT,
+    end TEXT,
+    event TEXT NOT NULL,
+    uid TEXT
+);
+CREATE INDEX IF NOT EXISTS events_date ON events (date, position);
+CREATE VIRTUAL TABLE IF NOT EXISTS events_text
+    USING fts5(event, content = 'events', content_rowid = 'id', tokenize = 'trigram');
+CREATE TRIGGER IF NOT EXISTS events_insert AFTER INSERT ON events BEGIN
+    INSERT INTO events_text (rowid, event) VALUES (new.id, new.event);
+END;
+CREATE TRIGGER IF NOT EXISTS events_delete AFTER DELETE ON events BEGIN
+    INSERT INTO events_text (events_text, rowid, event) VALUES ('delete', old.id, old.event);
+END;
+CREATE TABLE IF NOT EXISTS recurring (
+    position INTEGER PRIMARY KEY,
+    definition TEXT NOT NULL
+);
+";
+
+/// Storage of the agenda entries in a SQLite database.
+///
+/// Listing and searching a range of dates uses the indices of the database instead of reading
+/// every file. Changes are protected by the lock file `.lock` in the directory of the database,
+/// the same lock used by `TomlStore`.
+#[derive(Debug)]
+pub struct SqliteStore {
+    connection: rusqlite::Connection,
+    path: std::path::PathBuf,
+}
+
+impl SqliteStore {
+    /// Open the database at `path`, creating it and its tables if needed.
+    pub fn open(path: &std::path::Path) -> Result<Self, String> {
+        let connection = match rusqlite::Connection::open(path) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(format!("Error opening {}: {}", path.display(), e));
+            }
+        };
+        // Readers wait for a writing `crust` process instead of failing.
+        if let Err(e) = connection.busy_timeout(std::time::Duration::from_secs(5)) {
+            return Err(format!("Error opening {}: {}", path.display(), e));
+        }
+        if let Err(e) = connection.execute_batch(SCHEMA) {
+            return Err(format!(
+                "Error creating the tables in {}: {}",
+                path.display(),
+                e
+            ));
+        }
+        return Ok(SqliteStore {
+            connection,
+            path: path.to_path_buf(),
+        });
+    }
+
+    /// Describe the database error `e` that occurred while `action`.
+    fn error(&self, action: &str, e: rusqlite::Error) -> String {
+        return format!("Error {} {}: {}", action, self.path.display(), e);
+    }
+
+    /// Run `query` with `params` and parse the `yyyy-mm-dd` dates it selects.
+    fn query_dates(
+        &self,
+        query: &str,
+        params: &[&dyn rusqlite::ToSql],
+    ) -> Result<Vec<date::Date>, String> {
+        let mut statement = match self.connection.prepare(query) {
+            Ok(s) => s,
+            Err(e) => {
+                return Err(self.error("reading", e));
+            }
+        };
+        let rows = match statement.query_map(params, |row| row.get::<_, String>(0)) {
+            Ok(r) => r,
+            Err(e) => {
+                return Err(self.error("reading", e));
+            }
+        };
+        let mut dates: Vec<date::Date> = Vec::new();
+        for row in rows {
+            let value = match row {
+                Ok(v) => v,
+                Err(e) => {
+                    return Err(self.error("reading", e));
+                }
+            };
+            match chrono::NaiveDate::parse_from_str(&value, "%Y-%m-%d") {
+                Ok(naive) => dates.push(date::Date::from_naive_date(&naive)),
+                Err(_) => {
+                    return Err(format!(
+                        "Invalid date `{}` in {}",
+                        value,
+                        self.path.display()
+                    ));
+                }
+            }
+        }
+        return Ok(dates);
+    }
+}
+
+impl storage::Storage for SqliteStore {
+    fn list_dates(&self, from: &date::Date, to: &date::Date) -> Result<Vec<date::Date>, String> {
+        return self.query_dates(
+            "SELECT DISTINCT date FROM events WHERE date BETWEEN ?1 AND ?2 ORDER BY date",
+            &[&records::iso_date(from), &records::iso_date(to)],
+        );
+    }
+
+    fn load_day(&self, date: &date::Date) -> Result<parser::Agenda, String> {
+        let mut statement = match self
+            .connection
+            .prepare("SELECT start, end, event, uid FROM events WHERE date = ?1 ORDER BY position")
+        {
+            Ok(s) => s,
+            Err(e) => {
+                return Err(self.error("reading", e));
+            }
+        };
+        let rows = match statement.query_map([records::iso_date(date)], |row| {
+            Ok((
+                row.get::<_, Option<String>>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+            ))
+        }) {
+            Ok(r) => r,
+            Err(e) => {
+                return Err(self.error("reading", e));
+            }
+        };
+
+        let mut agenda = parser::Agenda::default();
+        for row in rows {
+            let (start, end, event, uid) = match row {
+                Ok(r) => r,
+                Err(e) => {
+                    return Err(self.error("reading", e));
+                }
+            };
+            let start = match start {
+                Some(s) => s,
+                None => {
+                    agenda.day.push(parser::DayEvent { event, uid });
+                    continue;
+                }
+            };
+            let invalid = |e: String| format!("{} in {} on {}", e, self.path.display(), date);
+            agenda.timestamp.push(parser::TimedEvent {
+                start: start.parse::<time::Time>().map_err(invalid)?,
+                end: match end {
+                    Some(e) => Some(e.parse::<time::Time>().map_err(invalid)?),
+                    None => None,
+                },
+                event,
+                uid,
+            });
+        }
+        agenda.timestamp.sort();
+        return Ok(agenda);
+    }
+
+    fn save_day(&self, date: &date::Date, agenda: &parser::Agenda) -> Result<(), String> {
+        let cleaned = agenda.cleaned();
+        let day = records::iso_date(date);
+        let write = || -> rusqlite::Result<()> {
+            let transaction = self.connection.unchecked_transaction()?;
+            transaction.execute("DELETE FROM events WHERE date = ?1", [&day])?;
+            let mut position: i64 = 0;
+            for day_event in cleaned.day.iter() {
+                transaction.execute(
+                    "INSERT INTO events (date, position, event, uid) VALUES (?1, ?2, ?3, ?4)",
+                    rusqlite::params![day, position, day_event.event, day_event.uid],
+                )?;
+                position += 1;
+            }
+            for timed_event in cleaned.timestamp.iter() {
+                transaction.execute(
+                    "INSERT INTO events (date, position, start, end, event, uid) \
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    rusqlite::params![
+                        day,
+                        position,
+                        timed_event.start.to_string(),
+                        timed_event.end.map(|e| e.to_string()),
+                        timed_event.event,
+                        timed_event.uid
+                    ],
+                )?;
+                position += 1;
+            }
+            return transaction.commit();
+        };
+        return write().map_err(|e| self.error("writing", e));
+    }
+
+    fn load_recurring(&self) -> Result<Vec<recurrence::Recurrence>, String> {
+        let mut statement = match self
+            .connection
+            .prepare("SELECT definition FROM recurring ORDER BY position")
+        {
+            Ok(s) => s,
+            Err(e) => {
+                return Err(self.error("reading", e));
+            }
+        };
+        let rows = match statement.query_map([], |row| row.get::<_, String>(0)) {
+            Ok(r) => r,
+            Err(e) => {
+                return Err(self.error("reading", e));
+            }
+        };
+        let mut recurring: Vec<recurrence::Recurrence> = Vec::new();
+        for row in rows {
+            let definition = match row {
+                Ok(d) => d,
+                Err(e) => {
+                    return Err(self.error("reading", e));
+                }
+            };
+            match serde_json::from_str(&definition) {
+                Ok(r) => recurring.push(r),
+                Err(e) => {
+                    return Err(format!(
+                        "Error parsing a recurring event in {}: {}",
+                        self.path.display(),
+                        e
+                    ));
+                }
+            }
+        }
+        return Ok(recurring);
+    }
+
+    fn save_recurring(&self, recurring: &[recurrence::Recurrence]) -> Result<(), String> {
+        let mut definitions: Vec<String> = Vec::new();
+        for definition in recurring.iter() {
+            match serde_json::to_string(definition) {
+                Ok(d) => definitions.push(d),
+                Err(e) => {
+                    return Err(format!("Error serializing recurring events: {}", e));
+                }
+            }
+        }
+        let write = || -> rusqlite::Result<()> {
+            let transaction = self.connection.unchecked_transaction()?;
+            transaction.execute("DELETE FROM recurring", [])?;
+            for (position, definition) in definitions.iter().enumerate() {
+                transaction.execute(
+                    "INSERT INTO recurring (position, definition) VALUES (?1, ?2)",
+                    rusqlite::params![position as i64, definition],
+                )?;
+            }
+            return transaction.commit();
+        };
+        return write().map_err(|e| self.error("writing", e));
+    }
+
+    fn lock(&self) -> Result<atomic::StoreLock, String> {
+        return atomic::StoreLock::acquire(&self.path.with_file_name(atomic::LOCK_FILENAME));
+    }
+
+    /// Find the dates through the trigram index on the text of the events.
+    ///
+    /// The index matches substrings of at least three characters, ignoring case. Shorter queries
+    /// cannot use it, every date in the range is a candidate then.
+    fn search_dates(
+        &self,
+        query: &str,
+        from: &date::Date,
+        to: &date::Date,
+    ) -> Result<Vec<date::Date>, String> {
+        if query.chars().count() < 3 {
+            return self.list_dates(from, to);
+        }
+        // Quote the query as a single phrase, so it is not read as query syntax.
+        let phrase = format!("\"{}\"", query.replace('"', "\"\""));
+        return self.query_dates(
+            "SELECT DISTINCT events.date FROM events_text \
+             JOIN events ON events.id = events_text.rowid \
+             WHERE events_text MATCH ?1 \
+             AND events.date BETWEEN ?2 AND ?3 ORDER BY events.date",
+            &[&phrase, &records::iso_date(from), &records::iso_date(to)],
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::tests::agenda;
+    use crate::storage::storage::Storage;
+
+    fn d(day: i32, month: i32, year: i32) -> date::Date {
+        return date::Date { day, month, year };
+    }
+
+    /// Open a database in a new temporary directory.
+    fn open_store(test: &str) -> SqliteStore {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("crust-sqlite-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        return SqliteStore::open(&dir.join(storage::DATABASE_FILENAME)).unwrap();
+    }
+
+    #[test]
+    fn save_and_load_day() {
+        let store = open_store("day");
+        let mut stored = agenda(&["Holiday"], &[(9, "Meeting"), (14, "Call")]);
+        stored.day[0].uid = Some("holiday@example.com".to_string());
+        stored.timestamp[0].end = time::Time::new(10, 30);
+        store.save_day(&d(1, 2, 2026), &stored).unwrap();
+        assert_eq!(store.load_day(&d(1, 2, 2026)).unwrap(), stored);
+        assert_eq!(
+            store.load_day(&d(2, 2, 2026)).unwrap(),
+            parser::Agenda::default()
+        );
+
+        // Saving replaces the events of the date, and an empty agenda removes the date.
+        let replaced = agenda(&[], &[(8, "Breakfast")]);
+        store.save_day(&d(1, 2, 2026), &replaced).unwrap();
+        assert_eq!(store.load_day(&d(1, 2, 2026)).unwrap(), replaced);
+        store
+            .save_day(&d(1, 2, 2026), &parser::Agenda::default())
+            .unwrap();
+        assert!(storage::stored_dates(&store).unwrap().is_empty());
+        let _ = std::fs::remove_dir_all(store.path.parent().unwrap());
+    }
+
+    #[test]
+    fn save_and_load_recurring() {
+        let store = open_store("recurring");
+        assert!(store.load_recurring().unwrap().is_empty());
+        let recurring = vec![recurrence::Recurrence {
+            id: "standup".to_string(),
+            event: "Stand-up".to_string(),
+            full_day: false,
+            start: time::Time::new(9, 0),
+            end: time::Time::new(9, 15),
+            frequency: recurrence::Frequency::Weekly,
+            interval: 1,
+            first: d(2, 2, 2026),
+            by_day: vec!["MO".parse().unwrap(), "TH".parse().unwrap()],
+            count: None,
+            until: Some(d(31, 12, 2026)),
+            exceptions: vec![d(5, 2, 2026)],
+        }];
+        store.save_recurring(&recurring).unwrap();
+        assert_eq!(store.load_recurring().unwrap(), recurring);
+        store.save_recurring(&[]).unwrap();
+        assert!(store.load_recurring().unwrap().is_empty());
+        let _ = std::fs::remove_dir_all(store.path.parent().unwrap());
+    }
+
+    #[test]
+    fn search_dates_uses_the_text_index() {
+        let store = open_store("search");
+        store
+            .save_day(&d(1, 2, 2026), &agenda(&["Dentist"], &[]))
+            .unwrap();
+        store
+            .save_day(&d(5, 2, 2026), &agenda(&[], &[(9, "Meeting")]))
+            .unwrap();
+        store
+            .save_day(&d(5, 3, 2026), &agenda(&["Dentist"], &[]))
+            .unwrap();
+        assert_eq!(
+            store
+                .search_dates("DENT", &d(1, 2, 2026), &d(28, 2, 2026))
+                .unwrap(),
+            vec![d(1, 2, 2026)]
+        );
+        // Replaced events leave the index.
+        store
+            .save_day(&d(1, 2, 2026), &agenda(&["Holiday"], &[]))
+            .unwrap();
+        assert!(store
+            .search_dates("dentist", &d(1, 2, 2026), &d(28, 2, 2026))
+            .unwrap()
+            .is_empty());
+        // Queries shorter than a trigram list every date in the range.
+        assert_eq!(
+            store
+                .search_dates("de", &d(1, 2, 2026), &d(28, 2, 2026))
+                .unwrap(),
+            vec![d(1, 2, 2026), d(5, 2, 2026)]
+        );
+        let _ = std::fs::remove_dir_all(store.path.parent().unwrap());
+    }
+}
@@ -0,0 +1,231 @@
+// External crates
+extern crate dirs;
+
+// Local files
+#[cfg(feature = "sqlite")]
+use super::sqlite_store;
+use super::toml_store;
+use crate::date::date;
+use crate::file::atomic;
+use crate::file::parser;
+use crate::file::recurrence;
+use crate::output::records;
+
+/// Storage of the agenda entries.
+///
+/// The events of a single date are stored per date as an `Agenda`, the recurring event
+/// definitions are stored separately. Every command and the TUI access the entries through this
+/// trait, so they do not depend on how the entries are stored. Changes read, modify and write
+/// the entries while holding the lock returned by `lock`.
+pub trait Storage {
+    /// List the dates between `from` and `to`, both inclusive, that have stored events, sorted.
+    ///
+    /// Occurrences of recurring events are not included, see `agenda_dates`.
+    fn list_dates(&self, from: &date::Date, to: &date::Date) -> Result<Vec<date::Date>, String>;
+
+    /// Load the events stored on `date`, without occurrences of recurring events.
+    ///
+    /// A date without events gives an empty agenda. Events that cannot be read are an error, so
+    /// they are never overwritten by an agenda missing them.
+    fn load_day(&self, date: &date::Date) -> Result<parser::Agenda, String>;
+
+    /// Replace the events stored on `date` by `agenda`.
+    ///
+    /// Events without text are left out. If no events remain, the date is removed from the
+    /// storage.
+    fn save_day(&self, date: &date::Date, agenda: &parser::Agenda) -> Result<(), String>;
+
+    /// Load the recurring event definitions.
+    fn load_recurring(&self) -> Result<Vec<recurrence::Recurrence>, String>;
+
+    /// Replace the recurring event definitions by `recurring`.
+    fn save_recurring(&self, recurring: &[recurrence::Recurrence]) -> Result<(), String>;
+
+    /// Lock the storage against changes by other `crust` processes until the returned lock is
+    /// dropped.
+    fn lock(&self) -> Result<atomic::StoreLock, String>;
+
+    /// List the dates between `from` and `to`, both inclusive, that may have a stored event
+    /// containing the lowercase `query`.
+    ///
+    /// The dates are only candidates, the events still have to be matched against `query`. By
+    /// default every date with stored events is a candidate.
+    fn search_dates(
+        &self,
+        _query: &str,
+        from: &date::Date,
+        to: &date::Date,
+    ) -> Result<Vec<date::Date>, String> {
+        return self.list_dates(from, to);
+    }
+
+    /// Delete the event stored on `date` whose `Record` has the identifier `id`.
+    ///
+    /// Returns whether an event was deleted. Occurrences of recurring events are not stored on a
+    /// date, they are skipped through the exceptions of their definition instead.
+    fn delete_event(&self, date: &date::Date, id: &str) -> Result<bool, String> {
+        let mut agenda = self.load_day(date)?;
+        let source = match records::date_entries(date, &agenda, &[])
+            .into_iter()
+            .find(|e| e.record.id == id)
+        {
+            Some(e) => e.source,
+            None => {
+                return Ok(false);
+            }
+        };
+        match source {
+            records::Source::Day(i) => {
+                agenda.day.remove(i);
+            }
+            records::Source::Timed(i) => {
+                agenda.timestamp.remove(i);
+            }
+            records::Source::Occurrence(_) => {
+                return Ok(false);
+            }
+        }
+        self.save_day(date, &agenda)?;
+        return Ok(true);
+    }
+}
+
+/// Name of the SQLite database in the data directory, see `Backend::Sqlite`.
+pub const DATABASE_FILENAME: &str = "agenda.db";
+
+/// Ways to store the agenda entries in the data directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// A TOML file per date, `dd-mm-yyyy.toml`, and `recurring.toml`.
+    Toml,
+    /// A single SQLite database, `agenda.db`. Only available with the `sqlite` feature.
+    Sqlite,
+}
+
+impl std::fmt::Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Backend::Toml => write!(f, "toml"),
+            Backend::Sqlite => write!(f, "sqlite"),
+        }
+    }
+}
+
+impl std::str::FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "toml" => Ok(Backend::Toml),
+            "sqlite" => Ok(Backend::Sqlite),
+            _ => Err("expected toml or sqlite".to_string()),
+        }
+    }
+}
+
+/// Environment variable overriding the directory in which the agenda entries are stored.
+pub const DATA_DIR_VARIABLE: &str = "CRUST_HOME";
+
+/// Obtain the directory in which the agenda entries are stored.
+///
+/// This is `$CRUST_HOME` if it is set, otherwise the `configured` directory, and otherwise
+/// `crust` in the XDG data directory, $HOME/.local/share/crust/ on Linux. A leading `~` in the
+/// directory stands for the home directory.
+pub fn data_dir(configured: Option<&std::path::Path>) -> Result<std::path::PathBuf, String> {
+    let dir: std::path::PathBuf = match std::env::var_os(DATA_DIR_VARIABLE) {
+        Some(value) if !value.is_empty() => std::path::PathBuf::from(value),
+        _ => match configured {
+            Some(path) => path.to_path_buf(),
+            None => match dirs::data_dir() {
+                Some(d) => d.join("crust"),
+                None => {
+                    return Err("Could not obtain the data directory, set $CRUST_HOME".to_string());
+                }
+            },
+        },
+    };
+    if let Ok(rest) = dir.strip_prefix("~") {
+        return match dirs::home_dir() {
+            Some(home) => Ok(home.join(rest)),
+            None => Err(format!(
+                "Could not obtain $HOME to expand {}",
+                dir.display()
+            )),
+        };
+    }
+    return Ok(dir);
+}
+
+/// Find the `Backend` used in `dir`, SQLite if it contains the database and TOML otherwise.
+pub fn current_backend(dir: &std::path::Path) -> Backend {
+    if dir.join(DATABASE_FILENAME).exists() {
+        return Backend::Sqlite;
+    }
+    return Backend::Toml;
+}
+
+/// Open the storage of the agenda entries in `dir`, with the backend in use there.
+///
+/// The directory is created if it does not exist yet.
+pub fn open(dir: &std::path::Path) -> Result<Box<dyn Storage>, String> {
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        return Err(format!("Error creating {}: {}", dir.display(), e));
+    }
+    return open_backend(dir, current_backend(dir));
+}
+
+/// Open the storage of the agenda entries in `dir` with `backend`.
+pub fn open_backend(dir: &std::path::Path, backend: Backend) -> Result<Box<dyn Storage>, String> {
+    match backend {
+        Backend::Toml => Ok(Box::new(toml_store::TomlStore::new(dir.to_path_buf()))),
+        Backend::Sqlite => open_sqlite(dir),
+    }
+}
+
+/// Open the SQLite database in `dir`.
+#[cfg(feature = "sqlite")]
+fn open_sqlite(dir: &std::path::Path) -> Result<Box<dyn Storage>, String> {
+    return Ok(Box::new(sqlite_store::SqliteStore::open(
+        &dir.join(DATABASE_FILENAME),
+    )?));
+}
+
+/// Report that the SQLite database in `dir` cannot be opened without the `sqlite` feature.
+#[cfg(not(feature = "sqlite"))]
+fn open_sqlite(dir: &std::path::Path) -> Result<Box<dyn Storage>, String> {
+    return Err(format!(
+        "Opening {} needs the `sqlite` feature, crust was built without it.",
+        dir.join(DATABASE_FILENAME).display()
+    ));
+}
+
+/// List every date with stored events, sorted.
+pub fn stored_dates(storage: &dyn Storage) -> Result<Vec<date::Date>, String> {
+    // Dates are stored with four digit years, so this range covers every stored date.
+    let first = date::Date {
+        day: 1,
+        month: 1,
+        year: 0,
+    };
+    let last = date::Date {
+        day: 31,
+        month: 12,
+        year: 9999,
+    };
+    return storage.list_dates(&first, &last);
+}
+
+/// Get a sorted vector of dates between `from` and `to`, both inclusive, that have an agenda
+/// entry.
+///
+/// Both the dates with stored events and the occurrences of the recurring events are included,
+/// without duplicates. Entries that cannot be read are left out.
+pub fn agenda_dates(storage: &dyn Storage, from: &date::Date, to: &date::Date) -> Vec<date::Date> {
+    let mut entries: Vec<date::Date> = storage.list_dates(from, to).unwrap_or_default();
+    for recurrence in storage.load_recurring().unwrap_or_default().iter() {
+        entries.append(&mut recurrence.occurrences(from, to));
+    }
+    entries.sort();
+    entries.dedup();
+    return entries;
+}
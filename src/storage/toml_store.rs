@@ -0,0 +1,198 @@
+// External crates
+extern crate regex;
+extern crate toml;
+
+use std::io::Read;
+
+// Local files
+use super::storage;
+use crate::date::date;
+use crate::file::atomic;
+use crate::file::parser;
+use crate::file::recurrence;
+
+/// Storage of the agenda entries as TOML files in a directory.
+///
+/// The events of every date are stored in a file `dd-mm-yyyy.toml`, the recurring event
+/// definitions in `recurring.toml`. Files are replaced atomically and changes are protected by
+/// the lock file `.lock` in the same directory.
+#[derive(Debug, Clone)]
+pub struct TomlStore {
+    dir: std::path::PathBuf,
+}
+
+impl TomlStore {
+    /// Create a `TomlStore` for the files in `dir`.
+    pub fn new(dir: std::path::PathBuf) -> Self {
+        return TomlStore { dir };
+    }
+
+    /// Obtain the path of the `dd-mm-yyyy.toml` file of `date`.
+    pub fn day_filepath(&self, date: &date::Date) -> std::path::PathBuf {
+        return self.dir.join(format!("{}.toml", date));
+    }
+
+    /// Obtain the path of the file with the recurring event definitions.
+    pub fn recurring_filepath(&self) -> std::path::PathBuf {
+        return self.dir.join(recurrence::RECURRING_FILENAME);
+    }
+}
+
+impl storage::Storage for TomlStore {
+    /// List the dates of the `dd-mm-yyyy.toml` files between `from` and `to`.
+    ///
+    /// Only files of the form `dd-mm-yyyy.toml` are converted into a `Date` structure, and only
+    /// the `Date` structures validated with the `validate` method on `Date` are included. A
+    /// missing directory contains no dates.
+    fn list_dates(&self, from: &date::Date, to: &date::Date) -> Result<Vec<date::Date>, String> {
+        let mut valid_entries: Vec<date::Date> = Vec::new();
+
+        // Get a list of files in the directory.
+        let paths = match std::fs::read_dir(&self.dir) {
+            Ok(v) => v,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(valid_entries);
+            }
+            Err(e) => {
+                return Err(format!("Error listing {}: {}", self.dir.display(), e));
+            }
+        };
+
+        // Define a regular expression to extract the correct file name structure with capture
+        // groups for the day, month and year.
+        let re = match regex::Regex::new(
+            r"^([0-3][0-9])(?:-)([0-1][0-9])(?:-)([0-9][0-9][0-9][0-9])(?:\.toml)$",
+        ) {
+            Ok(r) => r,
+            Err(e) => {
+                return Err(format!("Error creating the file name pattern: {}", e));
+            }
+        };
+
+        // Go through every file in the directory. If an error occurs during one of the steps in
+        // the loop, go to the next iteration.
+        for path in paths {
+            let path_os_string = match path {
+                Ok(p) => p.file_name(),
+                Err(_) => {
+                    continue;
+                }
+            };
+            let filename = match path_os_string.to_str() {
+                Some(f) => f,
+                None => {
+                    continue;
+                }
+            };
+
+            // Capture the day, month and year if the filename matches the regular expression.
+            let caps = match re.captures(filename) {
+                Some(c) => c,
+                None => {
+                    continue;
+                }
+            };
+            let (day, month, year) = match (
+                caps[1].parse::<i32>(),
+                caps[2].parse::<i32>(),
+                caps[3].parse::<i32>(),
+            ) {
+                (Ok(d), Ok(m), Ok(y)) => (d, m, y),
+                _ => {
+                    continue;
+                }
+            };
+
+            // Check if the `Date` is valid and in range before pushing it onto the vector.
+            let file_date: date::Date = date::Date { day, month, year };
+            if file_date.validate() && file_date >= *from && file_date <= *to {
+                valid_entries.push(file_date);
+            }
+        }
+        valid_entries.sort();
+        return Ok(valid_entries);
+    }
+
+    fn load_day(&self, date: &date::Date) -> Result<parser::Agenda, String> {
+        let mut filedir = self.day_filepath(date);
+        if !filedir.exists() {
+            return Ok(parser::Agenda::default());
+        }
+        return match parser::parse_agenda_toml(&mut filedir) {
+            Some(a) => Ok(a),
+            None => Err(format!(
+                "Could not read {}, fix the file before changing its entries.",
+                filedir.display()
+            )),
+        };
+    }
+
+    fn save_day(&self, date: &date::Date, agenda: &parser::Agenda) -> Result<(), String> {
+        let filedir = self.day_filepath(date);
+        let cleaned = agenda.cleaned();
+        if cleaned.is_empty() {
+            if filedir.exists() {
+                if let Err(e) = std::fs::remove_file(&filedir) {
+                    return Err(format!("Error removing {}: {}", filedir.display(), e));
+                }
+            }
+            return Ok(());
+        }
+
+        let contents = match toml::to_string(&cleaned) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(format!("Error serializing agenda for {}: {}", date, e));
+            }
+        };
+        return atomic::write_atomic(&filedir, &contents);
+    }
+
+    /// Read the recurring event definitions, a missing file contains no definitions.
+    fn load_recurring(&self) -> Result<Vec<recurrence::Recurrence>, String> {
+        let filedir = self.recurring_filepath();
+        if !filedir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut file = match std::fs::File::open(&filedir) {
+            Ok(f) => f,
+            Err(e) => {
+                return Err(format!("Error opening {}: {}", filedir.display(), e));
+            }
+        };
+        let mut contents = String::new();
+        if let Err(e) = file.read_to_string(&mut contents) {
+            return Err(format!("Error reading {}: {}", filedir.display(), e));
+        }
+        return match toml::from_str::<recurrence::RecurringEvents>(&contents) {
+            Ok(p) => Ok(p.recurring),
+            Err(e) => Err(format!("Error parsing {}: {}", filedir.display(), e)),
+        };
+    }
+
+    /// Write the recurring event definitions, without definitions the file is removed.
+    fn save_recurring(&self, recurring: &[recurrence::Recurrence]) -> Result<(), String> {
+        let filedir = self.recurring_filepath();
+        if recurring.is_empty() {
+            if filedir.exists() {
+                if let Err(e) = std::fs::remove_file(&filedir) {
+                    return Err(format!("Error removing {}: {}", filedir.display(), e));
+                }
+            }
+            return Ok(());
+        }
+        let contents = match toml::to_string(&recurrence::RecurringEvents {
+            recurring: recurring.to_vec(),
+        }) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(format!("Error serializing recurring events: {}", e));
+            }
+        };
+        return atomic::write_atomic(&filedir, &contents);
+    }
+
+    fn lock(&self) -> Result<atomic::StoreLock, String> {
+        return atomic::StoreLock::acquire(&self.dir.join(atomic::LOCK_FILENAME));
+    }
+}
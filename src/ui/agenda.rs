@@ -4,44 +4,71 @@ use ratatui::prelude::*;
 
 // Local files
 use crate::configuration::config;
-use crate::file;
+use crate::output::records;
+use crate::ui::index;
 
-/// Rendering of `Agenda` structures in the UI using `Config` for colors.
+/// Icon separating the times from the text of entries in calendars without an icon of their own.
+const DEFAULT_ICON: &str = "󰇙";
+
+/// Rendering of the `records` of a single date in the UI using `Config` for colors.
 ///
-/// Given the contents of a .toml file in $HOME/.cache/crust/ and a configuration, the text
-/// that should placed in the UI is returned.
-pub fn render(agenda: &file::parser::Agenda, conf: &config::Config) -> Vec<Line<'static>> {
+/// Given the entries of a date, in the order of `records::collect_records`, and a configuration,
+/// the text that should placed in the UI is returned. Entries use the color and icon of their
+/// calendar when the configuration sets them.
+pub fn render(records: &[records::Record], conf: &config::Config) -> Vec<Line<'static>> {
     // Initialise the text vector.
     let mut agenda_text: Vec<Line> = Vec::new();
 
-    // Loop through the events that last the entire day.
-    for day_event in agenda.day.iter() {
-        // Show the event if the event is not the default, i.e. empty.
-        if !day_event.event.is_empty() {
-            agenda_text.push(Line::from(Span::styled(
-                format!("󱃔 󰇙 {}", day_event.event),
-                Style::default()
-                    .fg(conf.agenda_entry_full_day_event)
-                    .add_modifier(Modifier::ITALIC),
-            )));
-        }
+    for record in records.iter() {
+        let icon = conf.calendar_icon(&record.calendar).unwrap_or(DEFAULT_ICON);
+        let text = match &record.start {
+            // Events that last the entire day.
+            None => format!("󱃔 {} {}", icon, record.text),
+            // Events that are timed.
+            Some(start) => format!(
+                "{}   {} {} {}",
+                start,
+                match &record.end {
+                    Some(end) => end.clone(),
+                    None => "     ".to_string(),
+                },
+                icon,
+                record.text
+            ),
+        };
+        agenda_text.push(Line::from(Span::styled(
+            text,
+            Style::default()
+                .fg(conf.entry_color(&record.calendar, &record.kind))
+                .add_modifier(Modifier::ITALIC),
+        )));
     }
 
-    // Loop through the events that are timed.
-    for timed_event in agenda.timestamp.iter() {
-        // Show the event if the event is not default, i.e. empty.
-        if !timed_event.event.is_empty() {
-            agenda_text.push(Line::from(Span::styled(
-                format!(
-                    "{}   {} 󰇙 {}",
-                    timed_event.start, timed_event.end, timed_event.event
-                ),
-                Style::default()
-                    .fg(conf.agenda_entry_timed_event)
-                    .add_modifier(Modifier::ITALIC),
-            )));
+    return agenda_text;
+}
+
+/// Line listing the calendars in the `index` with the key that toggles their visibility.
+///
+/// Hidden calendars are crossed out. Without named calendars there is nothing to
+/// toggle, and an empty line is returned.
+pub fn legend(index: &index::AgendaIndex, conf: &config::Config) -> Line<'static> {
+    let calendars = index.calendars();
+    let mut spans: Vec<Span> = Vec::new();
+    if calendars.len() < 2 {
+        return Line::from(spans);
+    }
+    // Only the first nine calendars have a key.
+    for (position, cal) in calendars.iter().enumerate().take(9) {
+        let mut style = Style::default().fg(conf.entry_color(&cal.name, records::KIND_TIMED));
+        if !index.is_shown(position) {
+            style = style
+                .fg(conf.calendar_days_of_week_bg)
+                .add_modifier(Modifier::CROSSED_OUT);
         }
+        spans.push(Span::styled(
+            format!(" {} {} ", position + 1, cal.name),
+            style,
+        ));
     }
-
-    return agenda_text;
+    return Line::from(spans);
 }
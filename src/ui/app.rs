@@ -0,0 +1,418 @@
+// External crates
+extern crate crossterm;
+
+// Local files
+use super::{form, index, watch};
+use crate::configuration::config;
+use crate::date::{date, expression};
+use crate::file::{modify, parser};
+use crate::key::event;
+use crate::storage;
+
+/// Time a notice stays in the status line.
+const STATUS_DURATION: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Part of the UI that is focused, its border is highlighted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Calendar,
+    /// The agenda of the selected date, in which an entry can be selected.
+    Agenda,
+}
+
+/// State of the UI.
+///
+/// Input and changes to the files are applied to the state by `handle` and `refresh`, the UI is
+/// drawn from the state alone by `ui::render`, which chooses the layout for the size of the
+/// terminal. New keys only need a branch in `update` and the code that draws their effect.
+pub struct App {
+    /// Date whose agenda is shown.
+    pub date: date::Date,
+    pub conf: config::Config,
+    /// Entries of the calendars around `date`.
+    pub index: index::AgendaIndex,
+    pub focus: Pane,
+    /// Position of the selected entry in the agenda of `date`.
+    pub selected: usize,
+    /// The UI keeps running until it is quit.
+    pub running: bool,
+    /// Keys typed so far of a key sequence that is not complete yet, see `event::key_event`.
+    pub pending: String,
+    /// Date expression typed in the go to date prompt, `None` if the prompt is closed.
+    pub prompt: Option<String>,
+    /// Form or question shown on top of the UI, `None` if there is none.
+    pub modal: Option<form::Modal>,
+    /// Notice shown in the status line, together with the moment it was shown.
+    status: Option<(String, std::time::Instant)>,
+    watcher: Option<watch::Watcher>,
+}
+
+impl App {
+    /// Create the state of the UI showing `date` in the `calendars`.
+    ///
+    /// Changes to the files are not noticed until `watch` is called.
+    pub fn new(
+        date: date::Date,
+        conf: config::Config,
+        calendars: Vec<storage::calendar::Calendar>,
+    ) -> Self {
+        return App {
+            date,
+            conf,
+            // Read the entries once, drawing the UI only reads the index.
+            index: index::AgendaIndex::new(calendars, &date),
+            focus: Pane::Calendar,
+            selected: 0,
+            running: true,
+            pending: String::new(),
+            prompt: None,
+            modal: None,
+            status: None,
+            watcher: None,
+        };
+    }
+
+    /// Watch the directories of the calendars and the configuration file for changes, which
+    /// `refresh` reloads. If that fails the UI works without reloading them and says so in the
+    /// status line.
+    pub fn watch(&mut self) {
+        let dirs: Vec<std::path::PathBuf> = self
+            .index
+            .calendars()
+            .iter()
+            .map(|c| c.dir.clone())
+            .collect();
+        match watch::Watcher::new(dirs, config::config_path()) {
+            Ok(w) => self.watcher = Some(w),
+            Err(e) => self.notify(format!("Not reloading changes: {}", e)),
+        }
+    }
+
+    /// Obtain the notice shown in the status line, if any.
+    pub fn status(&self) -> Option<&str> {
+        return self.status.as_ref().map(|(notice, _)| notice.as_str());
+    }
+
+    /// Obtain the position of the selected entry, if the agenda is focused and `date` has
+    /// entries.
+    pub fn selected_entry(&self) -> Option<usize> {
+        if self.focus != Pane::Agenda {
+            return None;
+        }
+        let count = self.index.records(&self.date).len();
+        if count == 0 {
+            return None;
+        }
+        return Some(self.selected.min(count - 1));
+    }
+
+    /// Apply the `input` of the terminal to the state, returning whether the UI has to be drawn
+    /// again.
+    ///
+    /// While a modal or the prompt is open, keys go to it, otherwise they are resolved to the
+    /// `KeyEvents` of the configured key sequences.
+    pub fn handle(&mut self, input: event::Input) -> bool {
+        return match input {
+            event::Input::NoInput => false,
+            event::Input::Resize => self.update(event::KeyEvents::Resize),
+            event::Input::Key(code) if self.modal.is_some() => self.edit_modal(code),
+            event::Input::Key(code) if self.prompt.is_some() => self.edit_prompt(code),
+            event::Input::Key(code) => {
+                let typed = self.pending.len();
+                let key_event = event::key_event(&self.conf, &mut self.pending, code);
+                // The pending keys are shown in the status line.
+                self.update(key_event) || self.pending.len() != typed
+            }
+        };
+    }
+
+    /// Apply `key_event` to the state, returning whether the UI has to be drawn again.
+    pub fn update(&mut self, key_event: event::KeyEvents) -> bool {
+        let mut new_date = self.date;
+        match key_event {
+            event::KeyEvents::NoEvent => return false,
+            event::KeyEvents::Quit => {
+                self.running = false;
+                return false;
+            }
+            event::KeyEvents::Next => new_date.increment(),
+            event::KeyEvents::Previous => new_date.decrement(),
+            event::KeyEvents::NextWeek => new_date.add_days(7),
+            event::KeyEvents::PreviousWeek => new_date.add_days(-7),
+            event::KeyEvents::NextMonth => new_date.add_months(1),
+            event::KeyEvents::PreviousMonth => new_date.add_months(-1),
+            event::KeyEvents::NextYear => new_date.add_months(12),
+            event::KeyEvents::PreviousYear => new_date.add_months(-12),
+            event::KeyEvents::Today => new_date = date::Date::default(),
+            event::KeyEvents::NextEntry | event::KeyEvents::PreviousEntry => {
+                let forward = key_event == event::KeyEvents::NextEntry;
+                match self.index.adjacent_date(&self.date, forward) {
+                    Some(d) => new_date = d,
+                    None => self.notify(format!(
+                        "No entry in the year {} {}",
+                        if forward { "after" } else { "before" },
+                        self.date
+                    )),
+                }
+            }
+            event::KeyEvents::FirstDay => new_date.day = 1,
+            event::KeyEvents::LastDay => new_date.day = new_date.max_day(),
+            event::KeyEvents::GoToDate => self.prompt = Some(String::new()),
+            event::KeyEvents::AddEntry => {
+                let shown = (0..self.index.calendars().len()).find(|i| self.index.is_shown(*i));
+                match shown {
+                    Some(i) => {
+                        let name = &self.index.calendars()[i].name;
+                        let event_form = form::EventForm::new(name);
+                        self.open_modal(form::Modal::Form(event_form));
+                    }
+                    None => self.notify("Show a calendar to add the event to".to_string()),
+                }
+            }
+            event::KeyEvents::EditEntry | event::KeyEvents::DeleteEntry => {
+                match self.selected_entry() {
+                    Some(i) => {
+                        let record = self.index.records(&self.date).swap_remove(i);
+                        self.open_modal(if key_event == event::KeyEvents::EditEntry {
+                            form::Modal::Form(form::EventForm::edit(&record))
+                        } else {
+                            form::Modal::ConfirmDelete(record)
+                        });
+                    }
+                    None => self.notify("Select an entry first, with Tab".to_string()),
+                }
+            }
+            event::KeyEvents::ToggleCalendar(position) => self.index.toggle(position),
+            event::KeyEvents::SwitchFocus => {
+                self.focus = match self.focus {
+                    Pane::Calendar => Pane::Agenda,
+                    Pane::Agenda => Pane::Calendar,
+                };
+            }
+            event::KeyEvents::SelectNext => {
+                if let Some(selected) = self.selected_entry() {
+                    // Stay on the last entry, `selected_entry` has checked there is one.
+                    let count = self.index.records(&self.date).len();
+                    self.selected = (selected + 1).min(count - 1);
+                }
+            }
+            event::KeyEvents::SelectPrevious => {
+                if let Some(selected) = self.selected_entry() {
+                    self.selected = selected.saturating_sub(1);
+                }
+            }
+            // The layout follows the size of the terminal when the UI is drawn again.
+            event::KeyEvents::Resize => {}
+        }
+        self.go_to(new_date);
+        return true;
+    }
+
+    /// Apply the key `code` to the open prompt, returning whether the UI has to be drawn again.
+    ///
+    /// Enter goes to the typed date, relative to the current day like the date expressions of the
+    /// commands, and escape closes the prompt without moving.
+    fn edit_prompt(&mut self, code: crossterm::event::KeyCode) -> bool {
+        let text = match self.prompt.as_mut() {
+            Some(text) => text,
+            None => return false,
+        };
+        match code {
+            crossterm::event::KeyCode::Char(c) => text.push(c),
+            crossterm::event::KeyCode::Backspace => {
+                text.pop();
+            }
+            crossterm::event::KeyCode::Esc => self.prompt = None,
+            crossterm::event::KeyCode::Enter => {
+                let expression = text.clone();
+                self.prompt = None;
+                match expression::parse_date(&expression, &date::Date::default()) {
+                    Ok(d) => self.go_to(d),
+                    Err(e) => self.notify(e),
+                }
+            }
+            _ => return false,
+        }
+        return true;
+    }
+
+    /// Show `modal`, clearing the status line so it shows the keys of the modal.
+    fn open_modal(&mut self, modal: form::Modal) {
+        self.status = None;
+        self.modal = Some(modal);
+    }
+
+    /// Apply the key `code` to the open modal, returning whether the UI has to be drawn again.
+    ///
+    /// A submitted form is saved, if that fails the form stays open with the error in the status
+    /// line. A deletion is confirmed with `y` and cancelled with `n` or escape.
+    fn edit_modal(&mut self, code: crossterm::event::KeyCode) -> bool {
+        match self.modal.take() {
+            Some(form::Modal::Form(mut event_form)) => match event_form.input(code) {
+                form::FormAction::Editing => self.modal = Some(form::Modal::Form(event_form)),
+                form::FormAction::Cancel => {}
+                form::FormAction::Submit => {
+                    if let Err(e) = self.save(&event_form) {
+                        self.notify(e);
+                        self.modal = Some(form::Modal::Form(event_form));
+                    }
+                }
+            },
+            Some(form::Modal::ConfirmDelete(record)) => match code {
+                crossterm::event::KeyCode::Char('y') => {
+                    let result = match self.calendar_position(&record.calendar) {
+                        Some(position) => modify::delete_entry(
+                            &self.index.calendars()[position],
+                            &self.date,
+                            &record.id,
+                        )
+                        .map(|notice| (position, notice))
+                        .map_err(|e| e.to_string()),
+                        None => Err(format!("Unknown calendar `{}`", record.calendar)),
+                    };
+                    self.apply_change(result);
+                }
+                crossterm::event::KeyCode::Char('n') | crossterm::event::KeyCode::Esc => {}
+                _ => self.modal = Some(form::Modal::ConfirmDelete(record)),
+            },
+            None => return false,
+        }
+        return true;
+    }
+
+    /// Store the event of `event_form` on `date`, adding it as `crust add` does or replacing the
+    /// edited entry. Returns an error message if the form is invalid or the event cannot be
+    /// stored.
+    fn save(&mut self, event_form: &form::EventForm) -> Result<(), String> {
+        let program_args = event_form.program_arguments(&self.date)?;
+        let position = match self.calendar_position(&event_form.calendar) {
+            Some(p) => p,
+            None => return Err(format!("Unknown calendar `{}`", event_form.calendar)),
+        };
+        let cal = &self.index.calendars()[position];
+        let result = match &event_form.editing {
+            Some(record) => modify::replace_entry(cal, &self.date, &record.id, &program_args),
+            None => parser::write_entry(cal.storage.as_ref(), &program_args)
+                .map(|_| format!("Added `{}` to {}.", program_args.entry, self.date)),
+        };
+        return match result {
+            Ok(notice) => {
+                self.apply_change(Ok((position, notice)));
+                Ok(())
+            }
+            Err(e) => Err(e.to_string()),
+        };
+    }
+
+    /// Read the calendar at the position of a successful change again and show its notice, or
+    /// show the error of a failed change.
+    fn apply_change(&mut self, result: Result<(usize, String), String>) {
+        match result {
+            Ok((position, notice)) => {
+                // The watcher notices the change as well, but finds nothing left to reload.
+                self.index.reload_calendar(position);
+                self.notify(notice);
+            }
+            Err(e) => self.notify(e),
+        }
+    }
+
+    /// Find the position of the calendar `name` in the index.
+    fn calendar_position(&self, name: &str) -> Option<usize> {
+        return self.index.calendars().iter().position(|c| c.name == name);
+    }
+
+    /// Show the agenda of `new_date`, reading the entries around it if needed.
+    fn go_to(&mut self, new_date: date::Date) {
+        if new_date != self.date {
+            self.date = new_date;
+            self.selected = 0;
+            self.index.ensure(&self.date);
+        }
+    }
+
+    /// Apply the changes made outside of the UI and expire the status notice, returning whether
+    /// the UI has to be drawn again.
+    ///
+    /// When an agenda file changes, only the calendar it belongs to is read again, and the number
+    /// of changed entries is shown in the status line. A changed configuration is applied
+    /// immediately, except for `data_dir`.
+    pub fn refresh(&mut self) -> bool {
+        let mut changed = false;
+        if let Some(changes) = self.watcher.as_ref().map(|w| w.changes()) {
+            let mut notices: Vec<String> = Vec::new();
+            if changes.config {
+                match config::Config::load() {
+                    Ok(c) => {
+                        self.conf = c;
+                        notices.push("Configuration reloaded".to_string());
+                    }
+                    Err(e) => notices.push(e.to_string()),
+                }
+            }
+            let reloaded: usize = changes
+                .dirs
+                .iter()
+                .map(|i| self.index.reload_calendar(*i))
+                .sum();
+            if reloaded > 0 {
+                notices.push(format!(
+                    "{} {} reloaded",
+                    reloaded,
+                    if reloaded == 1 { "event" } else { "events" }
+                ));
+            }
+            if !notices.is_empty() {
+                self.notify(notices.join(", "));
+                changed = true;
+            }
+        }
+        if self
+            .status
+            .as_ref()
+            .is_some_and(|(_, shown)| shown.elapsed() >= STATUS_DURATION)
+        {
+            self.status = None;
+            changed = true;
+        }
+        return changed;
+    }
+
+    /// Show `notice` in the status line.
+    fn notify(&mut self, notice: String) {
+        self.status = Some((notice, std::time::Instant::now()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::tests::agenda;
+    use crate::storage::storage::Storage;
+
+    #[test]
+    fn select_next_stays_on_the_last_entry() {
+        let today = date::Date {
+            day: 1,
+            month: 2,
+            year: 2026,
+        };
+        let store = storage::memory::MemoryStore::new();
+        store
+            .save_day(&today, &agenda(&["Holiday", "Birthday"], &[]))
+            .unwrap();
+        let calendars = vec![storage::calendar::Calendar {
+            name: storage::calendar::DEFAULT_CALENDAR.to_string(),
+            dir: std::path::PathBuf::new(),
+            storage: std::rc::Rc::new(store),
+        }];
+        let mut app = App::new(today, config::Config::default(), calendars);
+        app.focus = Pane::Agenda;
+        for _ in 0..3 {
+            app.update(event::KeyEvents::SelectNext);
+        }
+        assert_eq!(app.selected, 1);
+        app.update(event::KeyEvents::SelectPrevious);
+        assert_eq!(app.selected, 0);
+    }
+}
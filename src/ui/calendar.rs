@@ -5,7 +5,7 @@ use ratatui::prelude::*;
 // Local files
 use crate::configuration::config;
 use crate::date::date;
-use crate::file;
+use crate::ui::index;
 
 const DAYS_SHORT: &str = "| Mo | Tu | We | Th | Fr | Sa | Su |";
 const BOX: &str = "│ 󰹞  ";
@@ -15,21 +15,19 @@ const BOX_END: &str = "│ 󰹞  │";
 ///
 /// The month drawn is the month specified by the `date` parameter. Colors of the drawn calendar
 /// are determined by the `conf` argument. If a day in the shown month matches with an agenda entry
-/// in $HOME/.cache/crust/, special colors are used. Similarly, if a day matches with the
+/// in the `index`, or an occurrence of a recurring event, special colors are used. Similarly, if a day matches with the
 /// `given_day` argument, special colors are also used.
 pub fn render(
     date: &date::Date,
     given_date: &date::Date,
     conf: &config::Config,
+    index: &index::AgendaIndex,
 ) -> Vec<Line<'static>> {
     // Create empty vector of Lines.
     let mut calendar_text: Vec<Line> = Vec::new();
     // Add an empty line for spacing.
     calendar_text.push(Line::from(Span::raw("")));
 
-    // Get the agenda entries in $HOME/.cache/crust/
-    let entries: Vec<date::Date> = file::parser::get_agenda_entries();
-
     // Show the days
     calendar_text.push(Line::from(Span::styled(
         DAYS_SHORT,
@@ -49,6 +47,13 @@ pub fn render(
     let first_weekday_of_month = first_of_month.day_of_week();
     let days_in_month = first_of_month.max_day();
 
+    // Get the agenda entries of the shown month in the `index`, including recurring events.
+    let last_of_month: date::Date = date::Date {
+        day: days_in_month,
+        ..first_of_month
+    };
+    let entries: Vec<date::Date> = index.dates(&first_of_month, &last_of_month);
+
     let mut days_line: Vec<Span> = Vec::new();
     let mut weekday_counter = first_weekday_of_month;
 
@@ -72,30 +77,8 @@ pub fn render(
             ..first_of_month
         };
 
-        let style: Style = {
-            if current_date == *given_date {
-                Style::default()
-                    .fg(conf.calendar_day_selected)
-                    .bg(conf.calendar_day_selected_bg)
-                    .add_modifier(Modifier::BOLD | Modifier::ITALIC)
-            } else if entries.contains(&current_date) {
-                Style::default()
-                    .fg(conf.calendar_day_with_entry)
-                    .bg(conf.calendar_day_with_entry_bg)
-                    .add_modifier(Modifier::BOLD)
-            } else {
-                Style::default()
-                    .fg(conf.calendar_day)
-                    .bg(conf.calendar_day_bg)
-                    .add_modifier(Modifier::BOLD)
-            }
-        };
-
-        if day_counter < 10 {
-            days_line.push(Span::styled(format!("│ {}  ", day_counter), style));
-        } else {
-            days_line.push(Span::styled(format!("│ {} ", day_counter), style));
-        }
+        let style = day_style(&current_date, given_date, &entries, conf);
+        days_line.push(Span::styled(format!("│ {:<2} ", day_counter), style));
 
         weekday_counter += 1;
         if weekday_counter > 7 {
@@ -135,3 +118,70 @@ pub fn render(
 
     return calendar_text;
 }
+
+/// Draw the week of `date`, from Monday to Sunday, on a single line.
+///
+/// The days are colored as in `render`, with `date` as the given day. Days of the week in another
+/// month than `date` are shown as well.
+pub fn render_week(
+    date: &date::Date,
+    conf: &config::Config,
+    index: &index::AgendaIndex,
+) -> Vec<Line<'static>> {
+    let mut monday: date::Date = *date;
+    monday.add_days(1 - date.day_of_week());
+    let mut sunday: date::Date = monday;
+    sunday.add_days(6);
+    let entries: Vec<date::Date> = index.dates(&monday, &sunday);
+
+    let mut days_line: Vec<Span> = Vec::new();
+    let mut current_date = monday;
+    for _ in 0..7 {
+        let style = day_style(&current_date, date, &entries, conf);
+        days_line.push(Span::styled(format!("│ {:<2} ", current_date.day), style));
+        current_date.increment();
+    }
+    days_line.push(Span::styled(
+        "│",
+        Style::new()
+            .fg(conf.calendar_day)
+            .bg(conf.calendar_day_bg)
+            .add_modifier(Modifier::BOLD),
+    ));
+
+    return vec![
+        Line::from(Span::styled(
+            DAYS_SHORT,
+            Style::default()
+                .fg(conf.calendar_days_of_week)
+                .bg(conf.calendar_day_bg)
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+        )),
+        Line::from(days_line),
+    ];
+}
+
+/// Obtain the style of `current_date` in the calendar, which differs for the `given_date` and the
+/// dates with `entries`.
+fn day_style(
+    current_date: &date::Date,
+    given_date: &date::Date,
+    entries: &[date::Date],
+    conf: &config::Config,
+) -> Style {
+    if current_date == given_date {
+        return Style::default()
+            .fg(conf.calendar_day_selected)
+            .bg(conf.calendar_day_selected_bg)
+            .add_modifier(Modifier::BOLD | Modifier::ITALIC);
+    } else if entries.contains(current_date) {
+        return Style::default()
+            .fg(conf.calendar_day_with_entry)
+            .bg(conf.calendar_day_with_entry_bg)
+            .add_modifier(Modifier::BOLD);
+    }
+    return Style::default()
+        .fg(conf.calendar_day)
+        .bg(conf.calendar_day_bg)
+        .add_modifier(Modifier::BOLD);
+}
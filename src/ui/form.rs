@@ -0,0 +1,134 @@
+// External crates
+extern crate crossterm;
+
+// Local files
+use crate::args;
+use crate::date::{date, time};
+use crate::output::records;
+
+/// Labels of the fields of an `EventForm`, in order.
+pub const FIELDS: [&str; 3] = ["Text", "Start", "End"];
+
+/// Window shown on top of the UI that takes all key presses until it is closed.
+pub enum Modal {
+    /// Form to add or edit an event.
+    Form(EventForm),
+    /// Question whether the entry should be deleted, answered with `y` or `n`.
+    ConfirmDelete(records::Record),
+}
+
+/// Result of a key press in an `EventForm`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormAction {
+    /// The form is still being filled in.
+    Editing,
+    /// The form is submitted with enter.
+    Submit,
+    /// The form is closed with escape, without changes.
+    Cancel,
+}
+
+/// Form to add an event to, or edit an event of, the shown date.
+///
+/// The event is a full day event when the start time is left empty, and a timed event otherwise.
+/// Times are read as the `--start` and `--end` arguments of `crust add`.
+pub struct EventForm {
+    /// Entry that is edited, `None` when a new event is added.
+    pub editing: Option<records::Record>,
+    /// Name of the calendar the event is stored in.
+    pub calendar: String,
+    /// Values of the `FIELDS`.
+    pub values: [String; 3],
+    /// Position of the field that is typed in.
+    pub focused: usize,
+}
+
+impl EventForm {
+    /// Create an empty form to add an event to the calendar `calendar`.
+    pub fn new(calendar: &str) -> Self {
+        return EventForm {
+            editing: None,
+            calendar: calendar.to_string(),
+            values: [String::new(), String::new(), String::new()],
+            focused: 0,
+        };
+    }
+
+    /// Create a form filled in with the entry `record`, to edit it.
+    pub fn edit(record: &records::Record) -> Self {
+        return EventForm {
+            editing: Some(record.clone()),
+            calendar: record.calendar.clone(),
+            values: [
+                record.text.clone(),
+                record.start.clone().unwrap_or_default(),
+                record.end.clone().unwrap_or_default(),
+            ],
+            focused: 0,
+        };
+    }
+
+    /// Apply the key `code` to the form.
+    ///
+    /// Characters are typed in the focused field, tab and the arrows move between the fields.
+    pub fn input(&mut self, code: crossterm::event::KeyCode) -> FormAction {
+        match code {
+            crossterm::event::KeyCode::Char(c) => self.values[self.focused].push(c),
+            crossterm::event::KeyCode::Backspace => {
+                self.values[self.focused].pop();
+            }
+            crossterm::event::KeyCode::Tab | crossterm::event::KeyCode::Down => {
+                self.focused = (self.focused + 1) % FIELDS.len();
+            }
+            crossterm::event::KeyCode::BackTab | crossterm::event::KeyCode::Up => {
+                self.focused = (self.focused + FIELDS.len() - 1) % FIELDS.len();
+            }
+            crossterm::event::KeyCode::Enter => return FormAction::Submit,
+            crossterm::event::KeyCode::Esc => return FormAction::Cancel,
+            _ => {}
+        }
+        return FormAction::Editing;
+    }
+
+    /// Obtain the program arguments of `crust add` that add the event of the form on
+    /// `entry_date`.
+    ///
+    /// Returns an error message if the text is empty, a time cannot be read, or the event has an
+    /// end but no start or ends before it starts.
+    pub fn program_arguments(
+        &self,
+        entry_date: &date::Date,
+    ) -> Result<args::parser::ProgramArguments, String> {
+        let text = self.values[0].trim();
+        if text.is_empty() {
+            return Err("The event needs a text".to_string());
+        }
+        let parse = |value: &str| -> Result<Option<time::Time>, String> {
+            if value.trim().is_empty() {
+                return Ok(None);
+            }
+            return value.parse::<time::Time>().map(Some);
+        };
+        let start = parse(&self.values[1])?;
+        let end = parse(&self.values[2])?;
+        match (start, end) {
+            (None, Some(_)) => {
+                return Err("Full day events have no end, add a start time".to_string());
+            }
+            (Some(start), Some(end)) if end < start => {
+                return Err(format!("The end {} lies before the start {}", end, start));
+            }
+            _ => {}
+        }
+        let mut program_args = args::parser::ProgramArguments {
+            command: args::parser::Command::Add,
+            date: *entry_date,
+            start,
+            end,
+            entry: text.to_string(),
+            ..Default::default()
+        };
+        program_args.flags.full_day = start.is_none();
+        return Ok(program_args);
+    }
+}
@@ -0,0 +1,186 @@
+// External crates
+use std::collections::BTreeMap;
+
+// Local files
+use crate::date::date;
+use crate::output::records;
+use crate::storage;
+
+/// Months before the shown date that are kept in the index.
+const MONTHS_BEFORE: i32 = 12;
+/// Months after the shown date that are kept in the index. The UI looks for the next entry up to
+/// a year ahead, so this has to exceed twelve months.
+const MONTHS_AFTER: i32 = 24;
+/// Months after the shown date that the UI needs, the calendar shows three months and the agenda
+/// looks a year ahead.
+const MONTHS_NEEDED: i32 = 12;
+
+/// Agenda entries of the calendars around the shown date, kept in memory for the UI.
+///
+/// The entries are read once for a window of dates around the shown date, so drawing the UI does
+/// not read the storage. The window moves along when the shown date comes close to its edge.
+/// Queries outside the window find no entries. Hidden calendars stay in the index, so showing them
+/// again does not read the storage either.
+pub struct AgendaIndex {
+    calendars: Vec<storage::calendar::Calendar>,
+    shown: Vec<bool>,
+    from: date::Date,
+    to: date::Date,
+    /// Entries of every calendar by date, in the order of `calendars`.
+    days: Vec<BTreeMap<date::Date, Vec<records::Record>>>,
+}
+
+impl AgendaIndex {
+    /// Read the entries of the `calendars` around the date `around`.
+    pub fn new(calendars: Vec<storage::calendar::Calendar>, around: &date::Date) -> Self {
+        let (from, to) = window(around, MONTHS_BEFORE, MONTHS_AFTER);
+        let mut index = AgendaIndex {
+            shown: vec![true; calendars.len()],
+            calendars,
+            from,
+            to,
+            days: Vec::new(),
+        };
+        index.reload();
+        return index;
+    }
+
+    /// Obtain the calendars in the index, shown or not.
+    pub fn calendars(&self) -> &[storage::calendar::Calendar] {
+        return &self.calendars;
+    }
+
+    /// Check if the calendar at `index` is shown.
+    pub fn is_shown(&self, index: usize) -> bool {
+        return self.shown.get(index).copied().unwrap_or(false);
+    }
+
+    /// Show the calendar at `index` if it is hidden, hide it otherwise.
+    pub fn toggle(&mut self, index: usize) {
+        if let Some(shown) = self.shown.get_mut(index) {
+            *shown = !*shown;
+        }
+    }
+
+    /// Move the window of the index so it contains the dates the UI needs around `around`.
+    ///
+    /// The storage is only read when the needed dates lie outside the current window.
+    pub fn ensure(&mut self, around: &date::Date) {
+        let (needed_from, needed_to) = window(around, 0, MONTHS_NEEDED);
+        if needed_from >= self.from && needed_to <= self.to {
+            return;
+        }
+        (self.from, self.to) = window(around, MONTHS_BEFORE, MONTHS_AFTER);
+        self.reload();
+    }
+
+    /// Read the entries of every calendar in the window again.
+    pub fn reload(&mut self) {
+        self.days = (0..self.calendars.len())
+            .map(|index| self.load_calendar(index))
+            .collect();
+    }
+
+    /// Read the entries of the calendar at `index` again, returning the number of entries that
+    /// were added, changed or removed.
+    ///
+    /// An entry that changed counts once if it kept its id and twice otherwise, as a removed and
+    /// an added entry.
+    pub fn reload_calendar(&mut self, index: usize) -> usize {
+        if index >= self.calendars.len() {
+            return 0;
+        }
+        let days = self.load_calendar(index);
+        let old: Vec<&records::Record> = self.days[index].values().flatten().collect();
+        let new: Vec<&records::Record> = days.values().flatten().collect();
+        let added = new.iter().filter(|r| !old.contains(r)).count();
+        let removed = old
+            .iter()
+            .filter(|r| !new.iter().any(|n| n.id == r.id && n.date == r.date))
+            .count();
+        self.days[index] = days;
+        return added + removed;
+    }
+
+    /// Read the entries in the window of the calendar at `index`.
+    fn load_calendar(&self, index: usize) -> BTreeMap<date::Date, Vec<records::Record>> {
+        let mut days: BTreeMap<date::Date, Vec<records::Record>> = BTreeMap::new();
+        let calendar = std::slice::from_ref(&self.calendars[index]);
+        for record in records::collect_records(calendar, &self.from, &self.to).into_iter() {
+            if let Ok(entry_date) = record.parsed_date() {
+                days.entry(entry_date).or_default().push(record);
+            }
+        }
+        return days;
+    }
+
+    /// Obtain the entries of `date` in the shown calendars, in the order of
+    /// `records::collect_records`.
+    pub fn records(&self, date: &date::Date) -> Vec<records::Record> {
+        let mut day_records: Vec<records::Record> = Vec::new();
+        for (index, days) in self.days.iter().enumerate() {
+            if !self.is_shown(index) {
+                continue;
+            }
+            if let Some(calendar_records) = days.get(date) {
+                day_records.extend(calendar_records.iter().cloned());
+            }
+        }
+        day_records.sort_by(|a, b| records::listing_order(a).cmp(&records::listing_order(b)));
+        return day_records;
+    }
+
+    /// Find the closest date after `around`, or before it if `forward` is false, with an entry in
+    /// the shown calendars.
+    ///
+    /// Recurring events can repeat indefinitely, so only the year after or before `around` is
+    /// searched. The window is moved first if it does not contain that year.
+    pub fn adjacent_date(&mut self, around: &date::Date, forward: bool) -> Option<date::Date> {
+        let (mut from, mut to) = (*around, *around);
+        if forward {
+            from.increment();
+            to.add_months(12);
+        } else {
+            from.add_months(-12);
+            to.decrement();
+        }
+        if from < self.from || to > self.to {
+            (self.from, self.to) = window(around, MONTHS_BEFORE, MONTHS_AFTER);
+            self.reload();
+        }
+        let dates = self.dates(&from, &to);
+        return if forward {
+            dates.first().copied()
+        } else {
+            dates.last().copied()
+        };
+    }
+
+    /// Get a sorted vector of dates between `from` and `to`, both inclusive, that have an entry
+    /// in the shown calendars.
+    pub fn dates(&self, from: &date::Date, to: &date::Date) -> Vec<date::Date> {
+        let mut dates: Vec<date::Date> = Vec::new();
+        if from > to {
+            return dates;
+        }
+        for (index, days) in self.days.iter().enumerate() {
+            if self.is_shown(index) {
+                dates.extend(days.range(*from..=*to).map(|(d, _)| *d));
+            }
+        }
+        dates.sort();
+        dates.dedup();
+        return dates;
+    }
+}
+
+/// Obtain the range from the first of the month `months_before` months before `around` up to
+/// `months_after` months after `around`.
+fn window(around: &date::Date, months_before: i32, months_after: i32) -> (date::Date, date::Date) {
+    let mut from = *around;
+    from.add_months(-months_before);
+    from.day = 1;
+    let mut to = *around;
+    to.add_months(months_after);
+    return (from, to);
+}
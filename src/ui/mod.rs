@@ -1,4 +1,9 @@
 pub mod agenda;
+pub mod app;
 pub mod calendar;
+pub mod form;
+pub mod index;
+#[allow(clippy::module_inception)]
 pub mod ui;
+pub mod watch;
 pub mod window;
@@ -3,304 +3,485 @@ extern crate ratatui;
 use ratatui::{prelude::*, widgets::*};
 
 // Local files
-use super::{agenda, calendar};
-use crate::args;
+use super::{agenda, app, calendar, form, index};
 use crate::configuration::config;
 use crate::date::date;
-use crate::file;
 
-// TODO: Read up on lifetimes so we can use references to `ProgramArguments` and `Config` instead
-// of taking ownership and needing to copy it multiple times in the window.rs and main.rs files.
-// TODO: Add an error UI like `btop` when the UI is too small for the default and the restricted
-// UI.
 // TODO: Add yearly repeating events with special color and icon for holidays
 // TODO: Add synchronization with google calendar via their API.
 
-/// Create UI with `ProgramArguments` filled in as arguments.
-///
-/// Function returns a function on the `Frame` as required by `Ratatui`.
-pub fn ui_pre_args(
-    program_args: args::parser::ProgramArguments,
-    conf: config::Config,
-) -> Box<dyn Fn(&mut Frame)> {
-    Box::new(move |frame: &mut Frame| {
-        // Define outer layout
-        let layout = Layout::new(
-            Direction::Horizontal,
-            [Constraint::Percentage(40), Constraint::Percentage(60)],
-        )
-        .split(frame.size());
+/// Rows taken by a month of the calendar: the borders, an empty line, the days of the week and
+/// up to six weeks.
+const MONTH_ROWS: u16 = 10;
+/// Columns taken by a month of the calendar, or a week in the compact layout.
+const MONTH_COLUMNS: u16 = 38;
+/// Rows taken by a week in the compact layout: the borders, the days of the week and the days.
+const WEEK_ROWS: u16 = 4;
+/// Rows the agenda needs at least: the borders and a single entry.
+const AGENDA_ROWS: u16 = 3;
+/// Rows needed by the side-by-side layout, three months within the margins of the calendar.
+const SIDE_BY_SIDE_ROWS: u16 = 3 * MONTH_ROWS + 4;
+/// Rows needed by the vertical layout, a month above an agenda of a few entries.
+const VERTICAL_ROWS: u16 = MONTH_ROWS + 2 * AGENDA_ROWS;
+/// Rows needed by the compact layout, a week above the agenda.
+const COMPACT_ROWS: u16 = WEEK_ROWS + AGENDA_ROWS;
+/// Columns taken by a modal, if the terminal is wide enough.
+const MODAL_COLUMNS: u16 = 56;
 
-        // ========== Calendar UI ==========
-        // Calendar layout
-        let calendar_layout = Layout::new(
-            Direction::Vertical,
-            [
-                Constraint::Percentage(33),
-                Constraint::Percentage(33),
-                Constraint::Percentage(33),
-            ],
-        )
-        .margin(2)
-        .split(layout[0]);
+/// Layouts of the UI, chosen by the size of the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    /// Three months of calendar beside the agenda of the selected date and of the next date with
+    /// an entry.
+    SideBySide,
+    /// The month of the selected date above its agenda, for terminals narrower than
+    /// `layout_min_columns`.
+    Vertical,
+    /// The week of the selected date above its agenda, for terminals too short for a month.
+    Compact,
+    /// A notice with the size the terminal needs, for terminals too small for every layout.
+    TooSmall,
+}
 
-        // Define outer calendar block
-        let calendar_block = Block::new()
-            .title("   Calendar ")
-            .title_alignment(Alignment::Center)
-            .title_style(
-                Style::default()
-                    .fg(conf.calendar_title)
-                    .add_modifier(Modifier::BOLD | Modifier::ITALIC),
-            )
-            .border_type(BorderType::Rounded)
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(conf.calendar_box));
+/// Choose the layout that fits in `area`, preferring the layouts showing the most.
+pub fn view(area: Rect, conf: &config::Config) -> View {
+    if area.width < MONTH_COLUMNS || area.height < COMPACT_ROWS {
+        return View::TooSmall;
+    }
+    if area.height < VERTICAL_ROWS {
+        return View::Compact;
+    }
+    if area.width < conf.layout_min_columns || area.height < SIDE_BY_SIDE_ROWS {
+        return View::Vertical;
+    }
+    return View::SideBySide;
+}
 
-        // Get the next two months with respect to the Date passed in program_args, and the
-        // corresponding month strings for those months.
-        let next_month: date::Date = program_args.date.first_of_next_month();
-        let second_next_month: date::Date = next_month.first_of_next_month();
+/// Draw the UI described by `app` onto the `frame`, in the layout that fits the frame.
+///
+/// The layout is chosen on every draw, so it follows the size of the terminal. Drawing only reads
+/// the state, the agenda entries are read from the index of `app`, which contains the dates
+/// around the selected date. An open modal is drawn on top of the layout.
+pub fn render(frame: &mut Frame, app: &app::App) {
+    match view(frame.size(), &app.conf) {
+        View::SideBySide => render_side_by_side(frame, app),
+        View::Vertical => render_vertical(frame, app),
+        View::Compact => render_compact(frame, app),
+        View::TooSmall => return render_too_small(frame, &app.conf),
+    }
+    match &app.modal {
+        Some(form::Modal::Form(event_form)) => render_form(frame, app, event_form),
+        Some(form::Modal::ConfirmDelete(record)) => {
+            let question = format!(" Delete `{}` from {}?", record.text, app.date);
+            let lines = vec![
+                Line::from(""),
+                Line::from(Span::styled(
+                    question,
+                    Style::default().fg(app.conf.calendar_day),
+                )),
+            ];
+            render_modal(frame, app, " Delete event ", lines, "y: delete, n: keep");
+        }
+        None => {}
+    }
+}
 
-        let given_month_title = program_args.date.calendar_title();
-        let next_month_title = next_month.calendar_title();
-        let second_next_month_title = second_next_month.calendar_title();
+/// Draw the calendar of the selected month and the next two months beside the agenda of the
+/// selected date and of the next date with an entry.
+fn render_side_by_side(frame: &mut Frame, app: &app::App) {
+    let conf = &app.conf;
+    // Define outer layout
+    let layout = Layout::new(
+        Direction::Horizontal,
+        [
+            Constraint::Percentage(conf.layout_calendar_percentage),
+            Constraint::Percentage(100 - conf.layout_calendar_percentage),
+        ],
+    )
+    .split(frame.size());
 
-        // Define the three calendar sub-blocks with the given month and the next two months.
+    // ========== Calendar UI ==========
+    // Calendar layout
+    let calendar_layout = Layout::new(
+        Direction::Vertical,
+        [
+            Constraint::Percentage(33),
+            Constraint::Percentage(33),
+            Constraint::Percentage(33),
+        ],
+    )
+    .margin(2)
+    .split(layout[0]);
 
-        let given_month_block = Block::new()
-            .title(given_month_title)
-            .title_alignment(Alignment::Center)
-            .title_style(
-                Style::default()
-                    .fg(conf.calendar_month_title)
-                    .add_modifier(Modifier::BOLD),
-            )
-            .border_type(BorderType::Rounded)
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(conf.calendar_month_box));
+    // Define outer calendar block
+    let calendar_block = Block::new()
+        .title("   Calendar ")
+        .title_alignment(Alignment::Center)
+        .title_style(
+            Style::default()
+                .fg(conf.calendar_title)
+                .add_modifier(Modifier::BOLD | Modifier::ITALIC),
+        )
+        .border_type(border_type(app, app::Pane::Calendar))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(conf.calendar_box))
+        .title_bottom(status_line(app, conf));
+    frame.render_widget(calendar_block, layout[0]);
 
-        let next_month_block = Block::new()
-            .title(next_month_title)
-            .title_alignment(Alignment::Center)
-            .title_style(
-                Style::default()
-                    .fg(conf.calendar_month_title)
-                    .add_modifier(Modifier::BOLD),
-            )
-            .border_type(BorderType::Rounded)
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(conf.calendar_month_box));
+    // Show the selected month and the next two months.
+    let mut month: date::Date = app.date;
+    for area in calendar_layout.iter() {
+        let month_par = Paragraph::new(calendar::render(&month, &app.date, conf, &app.index))
+            .block(month_block(month.calendar_title(), conf))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true });
+        frame.render_widget(month_par, *area);
+        month = month.first_of_next_month();
+    }
 
-        let second_next_month_block = Block::new()
-            .title(second_next_month_title)
-            .title_alignment(Alignment::Center)
-            .title_style(
-                Style::default()
-                    .fg(conf.calendar_month_title)
-                    .add_modifier(Modifier::BOLD),
-            )
-            .border_type(BorderType::Rounded)
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(conf.calendar_month_box));
+    // ========== Agenda UI ==========
+    // Get the sorted entries from the given date up to a year later. Recurring events can
+    // repeat indefinitely, so we need to bound the range we look at.
+    let mut search_end: date::Date = app.date;
+    search_end.year += 1;
+    search_end.day = search_end.day.min(search_end.max_day());
+    let filtered_entries: Vec<date::Date> = app.index.dates(&app.date, &search_end);
+    // Extract the next non-empty agenda entry.
+    let next_nonempty: date::Date = {
+        if filtered_entries.len() >= 2 {
+            if filtered_entries[0] == app.date {
+                filtered_entries[1]
+            } else {
+                filtered_entries[0]
+            }
+        } else {
+            app.date
+        }
+    };
 
-        // Define text to be put into the calendar blocks.
-        let given_month_text = calendar::render(&program_args.date, &program_args.date, &conf);
-        let next_month_text = calendar::render(&next_month, &program_args.date, &conf);
-        let second_next_month_text =
-            calendar::render(&second_next_month, &program_args.date, &conf);
+    // Define the Agenda layout.
+    let agenda_layout = Layout::new(
+        Direction::Vertical,
+        [Constraint::Percentage(50), Constraint::Percentage(50)],
+    )
+    .margin(2)
+    .split(layout[1]);
 
-        // Define Paragraph objects onto the Blocks.
-        let given_month_par = Paragraph::new(given_month_text)
-            .block(given_month_block)
-            .alignment(Alignment::Center)
-            .wrap(Wrap { trim: true });
+    // Define agenda block.
+    let agenda_block = Block::new()
+        .title("   Agenda ")
+        .title_alignment(Alignment::Center)
+        .title_style(
+            Style::default()
+                .fg(conf.agenda_title)
+                .add_modifier(Modifier::BOLD | Modifier::ITALIC),
+        )
+        .border_type(border_type(app, app::Pane::Agenda))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(conf.agenda_box))
+        .title_bottom(agenda::legend(&app.index, conf).centered());
 
-        let next_month_par = Paragraph::new(next_month_text)
-            .block(next_month_block)
-            .alignment(Alignment::Center)
+    // Define `Paragraphs` for the agenda content of the two dates.
+    let given_agenda_par =
+        Paragraph::new(day_text(&app.index, &app.date, conf, app.selected_entry()))
+            .block(day_block(
+                app.date.agenda_title(),
+                conf,
+                BorderType::Rounded,
+            ))
+            .alignment(Alignment::Left)
             .wrap(Wrap { trim: true });
+    let next_agenda_par = Paragraph::new(day_text(&app.index, &next_nonempty, conf, None))
+        .block(day_block(
+            next_nonempty.agenda_title(),
+            conf,
+            BorderType::Rounded,
+        ))
+        .alignment(Alignment::Left)
+        .wrap(Wrap { trim: true });
 
-        let second_next_month_par = Paragraph::new(second_next_month_text)
-            .block(second_next_month_block)
-            .alignment(Alignment::Center)
-            .wrap(Wrap { trim: true });
+    // Render onto the frame.
+    frame.render_widget(agenda_block, layout[1]);
+    frame.render_widget(given_agenda_par, agenda_layout[0]);
+    frame.render_widget(next_agenda_par, agenda_layout[1]);
+}
 
-        // Render onto the frame.
-        frame.render_widget(calendar_block, layout[0]);
-        frame.render_widget(given_month_par, calendar_layout[0]);
-        frame.render_widget(next_month_par, calendar_layout[1]);
-        frame.render_widget(second_next_month_par, calendar_layout[2]);
+/// Draw the calendar of the selected month above the agenda of the selected date, for narrow
+/// terminals.
+fn render_vertical(frame: &mut Frame, app: &app::App) {
+    let conf = &app.conf;
+    // Define layout, the month takes a third of the height but never less than it needs.
+    let layout = Layout::new(
+        Direction::Vertical,
+        [Constraint::Min(MONTH_ROWS), Constraint::Percentage(66)],
+    )
+    .split(frame.size());
 
-        // ========== Agenda UI ==========
-        // Get the entries.
-        let entries: Vec<date::Date> = file::parser::get_agenda_entries();
-        // Filter and sort to dates equal or older than the given date.
-        let mut filtered_entries: Vec<date::Date> = entries
-            .into_iter()
-            .filter(|entry| *entry >= program_args.date)
-            .collect();
-        filtered_entries.sort();
-        // Extract the next non-empty agenda entry.
-        let next_nonempty: date::Date = {
-            if filtered_entries.len() >= 2 {
-                if filtered_entries[0] == program_args.date {
-                    filtered_entries[1].clone()
-                } else {
-                    filtered_entries[0].clone()
-                }
-            } else {
-                program_args.date
-            }
-        };
+    // ========== Calendar UI ==========
+    let cal_block = month_block(app.date.calendar_title(), conf)
+        .border_type(border_type(app, app::Pane::Calendar))
+        .title_bottom(status_line(app, conf));
+    let cal_par = Paragraph::new(calendar::render(&app.date, &app.date, conf, &app.index))
+        .block(cal_block)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+    frame.render_widget(cal_par, layout[0]);
 
-        // Define the Agenda layout.
-        let agenda_layout = Layout::new(
-            Direction::Vertical,
-            [Constraint::Percentage(50), Constraint::Percentage(50)],
-        )
-        .margin(2)
-        .split(layout[1]);
+    // ========== Agenda UI ==========
+    let agenda_block = day_block(
+        app.date.agenda_title(),
+        conf,
+        border_type(app, app::Pane::Agenda),
+    )
+    .title_bottom(agenda::legend(&app.index, conf).centered());
+    let agenda_par = Paragraph::new(day_text(&app.index, &app.date, conf, app.selected_entry()))
+        .block(agenda_block)
+        .alignment(Alignment::Left)
+        .wrap(Wrap { trim: true });
+    frame.render_widget(agenda_par, layout[1]);
+}
 
-        // Define agenda block.
-        let agenda_block = Block::new()
-            .title("   Agenda ")
-            .title_alignment(Alignment::Center)
-            .title_style(
-                Style::default()
-                    .fg(conf.agenda_title)
-                    .add_modifier(Modifier::BOLD | Modifier::ITALIC),
-            )
-            .border_type(BorderType::Rounded)
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(conf.agenda_box));
+/// Draw the week of the selected date above its agenda, for terminals too short for a month.
+fn render_compact(frame: &mut Frame, app: &app::App) {
+    let conf = &app.conf;
+    let layout = Layout::new(
+        Direction::Vertical,
+        [Constraint::Length(WEEK_ROWS), Constraint::Min(AGENDA_ROWS)],
+    )
+    .split(frame.size());
 
-        // Define agenda sub-blocks.
-        let given_agenda_title = program_args.date.agenda_title();
-        let given_agenda_block = Block::new()
-            .title(given_agenda_title)
-            .title_alignment(Alignment::Center)
-            .title_style(
-                Style::default()
-                    .fg(conf.agenda_entry_title)
-                    .add_modifier(Modifier::BOLD | Modifier::ITALIC),
-            )
-            .border_type(BorderType::Rounded)
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(conf.agenda_entry_box));
+    let week_block = month_block(app.date.calendar_title(), conf)
+        .border_type(border_type(app, app::Pane::Calendar))
+        .title_bottom(status_line(app, conf));
+    let week_par = Paragraph::new(calendar::render_week(&app.date, conf, &app.index))
+        .block(week_block)
+        .alignment(Alignment::Center);
+    frame.render_widget(week_par, layout[0]);
 
-        let next_agenda_title = next_nonempty.agenda_title();
-        let next_agenda_block = Block::new()
-            .title(next_agenda_title)
-            .title_alignment(Alignment::Center)
-            .title_style(
-                Style::default()
-                    .fg(conf.agenda_entry_title)
-                    .add_modifier(Modifier::BOLD | Modifier::ITALIC),
-            )
-            .border_type(BorderType::Rounded)
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(conf.agenda_entry_box));
+    let agenda_block = day_block(
+        app.date.agenda_title(),
+        conf,
+        border_type(app, app::Pane::Agenda),
+    )
+    .title_bottom(agenda::legend(&app.index, conf).centered());
+    let agenda_par = Paragraph::new(day_text(&app.index, &app.date, conf, app.selected_entry()))
+        .block(agenda_block)
+        .alignment(Alignment::Left)
+        .wrap(Wrap { trim: true });
+    frame.render_widget(agenda_par, layout[1]);
+}
 
-        // Obtain the agenda text from the two dates.
-        let given_agenda_text = match program_args.date.get_agenda() {
-            Some(given_agenda) => agenda::render(&given_agenda, &conf),
-            None => {
-                let agenda_text: Vec<Line> = vec![Line::from(Span::styled(
-                    "No entry for this date.",
-                    Style::default().fg(conf.agenda_entry_full_day_event),
-                ))];
-                agenda_text
-            }
-        };
-        let next_agenda_text = match next_nonempty.get_agenda() {
-            Some(next_agenda) => agenda::render(&next_agenda, &conf),
-            None => {
-                let agenda_text: Vec<Line> = vec![Line::from(Span::styled(
-                    "No entry for this date.",
-                    Style::default().fg(conf.agenda_entry_full_day_event),
-                ))];
-                agenda_text
-            }
-        };
+/// Draw the size of the terminal and the size needed by the smallest layout, like `btop` does.
+fn render_too_small(frame: &mut Frame, conf: &config::Config) {
+    let area = frame.size();
+    let current = Style::default()
+        .fg(conf.calendar_month_title)
+        .add_modifier(Modifier::BOLD);
+    let needed = Style::default()
+        .fg(conf.calendar_title)
+        .add_modifier(Modifier::BOLD);
+    let text = vec![
+        Line::from("Terminal size too small:"),
+        Line::from(vec![
+            Span::raw("Width = "),
+            Span::styled(area.width.to_string(), current),
+            Span::raw(" Height = "),
+            Span::styled(area.height.to_string(), current),
+        ]),
+        Line::from(""),
+        Line::from("Needed:"),
+        Line::from(vec![
+            Span::raw("Width = "),
+            Span::styled(MONTH_COLUMNS.to_string(), needed),
+            Span::raw(" Height = "),
+            Span::styled(COMPACT_ROWS.to_string(), needed),
+        ]),
+    ];
+    // Center the text vertically, as far as it fits.
+    let top = area.height.saturating_sub(text.len() as u16) / 2;
+    let text_area = Rect {
+        y: area.y + top,
+        height: area.height - top,
+        ..area
+    };
+    frame.render_widget(
+        Paragraph::new(text)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true }),
+        text_area,
+    );
+}
 
-        // Define `Paragraphs` for the agenda content.
-        let given_agenda_par = Paragraph::new(given_agenda_text)
-            .block(given_agenda_block)
-            .alignment(Alignment::Left)
-            .wrap(Wrap { trim: true });
-        let next_agenda_par = Paragraph::new(next_agenda_text)
-            .block(next_agenda_block)
-            .alignment(Alignment::Left)
-            .wrap(Wrap { trim: true });
+/// Obtain the border of `pane`, thick when it is focused.
+fn border_type(app: &app::App, pane: app::Pane) -> BorderType {
+    if app.focus == pane {
+        return BorderType::Thick;
+    }
+    return BorderType::Rounded;
+}
+
+/// Obtain the block around a month of the calendar with the given `title`.
+fn month_block(title: String, conf: &config::Config) -> Block<'static> {
+    return Block::new()
+        .title(title)
+        .title_alignment(Alignment::Center)
+        .title_style(
+            Style::default()
+                .fg(conf.calendar_month_title)
+                .add_modifier(Modifier::BOLD),
+        )
+        .border_type(BorderType::Rounded)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(conf.calendar_month_box));
+}
+
+/// Obtain the block around the agenda of a single date with the given `title` and `border`.
+fn day_block(title: String, conf: &config::Config, border: BorderType) -> Block<'static> {
+    return Block::new()
+        .title(title)
+        .title_alignment(Alignment::Center)
+        .title_style(
+            Style::default()
+                .fg(conf.agenda_entry_title)
+                .add_modifier(Modifier::BOLD | Modifier::ITALIC),
+        )
+        .border_type(border)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(conf.agenda_entry_box));
+}
 
-        // Render onto the frame.
-        frame.render_widget(agenda_block, layout[1]);
-        frame.render_widget(given_agenda_par, agenda_layout[0]);
-        frame.render_widget(next_agenda_par, agenda_layout[1]);
-    })
+/// Draw the `event_form` in a modal, with the focused field highlighted.
+fn render_form(frame: &mut Frame, app: &app::App, event_form: &form::EventForm) {
+    let conf = &app.conf;
+    let mut lines: Vec<Line> = Vec::new();
+    for (i, label) in form::FIELDS.iter().enumerate() {
+        let mut label_style = Style::default().fg(conf.agenda_title);
+        let mut value = event_form.values[i].clone();
+        if i == event_form.focused {
+            label_style = label_style.add_modifier(Modifier::BOLD | Modifier::REVERSED);
+            value.push('_');
+        }
+        lines.push(Line::from(vec![
+            Span::styled(format!(" {:<5} ", label), label_style),
+            Span::styled(
+                format!(" {}", value),
+                Style::default().fg(conf.calendar_day),
+            ),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        format!(
+            " {} in {}, without start for a full day",
+            app.date, event_form.calendar
+        ),
+        Style::default()
+            .fg(conf.calendar_title)
+            .add_modifier(Modifier::ITALIC),
+    )));
+    let title = match event_form.editing {
+        Some(_) => " Edit event ",
+        None => " New event ",
+    };
+    render_modal(
+        frame,
+        app,
+        title,
+        lines,
+        "Enter: save, Tab: next field, Esc: cancel",
+    );
 }
 
-/// Same as `ui_pre_args` but for a restricted, vertical half-screen, layout.
-pub fn ui_restricted_vertical_pre_args(
-    program_args: args::parser::ProgramArguments,
-    conf: config::Config,
-) -> Box<dyn Fn(&mut Frame)> {
-    Box::new(move |frame: &mut Frame| {
-        // Define layout
-        let layout = Layout::new(
-            Direction::Vertical,
-            [Constraint::Percentage(33), Constraint::Percentage(66)],
+/// Draw `lines` in a block titled `title` in the middle of the frame, clearing what is below it.
+///
+/// The bottom of the block shows the status notice of `app`, so errors of the modal are shown in
+/// it, and otherwise the `keys` that can be used.
+fn render_modal(frame: &mut Frame, app: &app::App, title: &str, lines: Vec<Line>, keys: &str) {
+    let conf = &app.conf;
+    let area = frame.size();
+    let width = MODAL_COLUMNS.min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let modal_area = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+    let bottom = match app.status() {
+        Some(_) => status_line(app, conf),
+        None => Line::from(Span::styled(
+            format!(" {} ", keys),
+            Style::default()
+                .fg(conf.calendar_title)
+                .add_modifier(Modifier::ITALIC),
+        ))
+        .centered(),
+    };
+    let block = Block::new()
+        .title(Line::from(title).centered())
+        .title_style(
+            Style::default()
+                .fg(conf.agenda_entry_title)
+                .add_modifier(Modifier::BOLD),
         )
-        .split(frame.size());
+        .title_bottom(bottom)
+        .borders(Borders::ALL)
+        .border_type(BorderType::Thick)
+        .border_style(Style::default().fg(conf.agenda_entry_box));
+    frame.render_widget(Clear, modal_area);
+    frame.render_widget(Paragraph::new(lines).block(block), modal_area);
+}
 
-        // ========== Calendar UI ==========
-        let cal_title = program_args.date.calendar_title();
-        let cal_block = Block::new()
-            .title(cal_title)
-            .title_alignment(Alignment::Center)
-            .title_style(
+/// Obtain the status line of the `app`, showing the go to date prompt while it is open, and
+/// otherwise the status notice or the keys typed of an incomplete key sequence.
+fn status_line(app: &app::App, conf: &config::Config) -> Line<'static> {
+    if let Some(text) = &app.prompt {
+        return Line::from(vec![
+            Span::styled(
+                " Go to: ",
                 Style::default()
-                    .fg(conf.calendar_month_title)
+                    .fg(conf.calendar_title)
                     .add_modifier(Modifier::BOLD),
-            )
-            .border_type(BorderType::Rounded)
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(conf.calendar_month_box));
-        let cal_text = calendar::render(&program_args.date, &program_args.date, &conf);
-        let cal_par = Paragraph::new(cal_text)
-            .block(cal_block)
-            .alignment(Alignment::Center)
-            .wrap(Wrap { trim: true });
-        frame.render_widget(cal_par, layout[0]);
+            ),
+            Span::styled(
+                format!("{}_ ", text),
+                Style::default().fg(conf.calendar_day),
+            ),
+        ])
+        .centered();
+    }
+    let notice = match app.status() {
+        Some(notice) => notice,
+        None if !app.pending.is_empty() => app.pending.as_str(),
+        None => return Line::from(""),
+    };
+    return Line::from(Span::styled(
+        format!(" {} ", notice),
+        Style::default()
+            .fg(conf.calendar_title)
+            .add_modifier(Modifier::ITALIC),
+    ))
+    .centered();
+}
 
-        // ========== Agenda UI ==========
-        let agenda_title = program_args.date.agenda_title();
-        let agenda_block = Block::new()
-            .title(agenda_title)
-            .title_alignment(Alignment::Center)
-            .title_style(
-                Style::default()
-                    .fg(conf.agenda_entry_title)
-                    .add_modifier(Modifier::BOLD | Modifier::ITALIC),
-            )
-            .border_type(BorderType::Rounded)
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(conf.agenda_entry_box));
-        let agenda_text = match program_args.date.get_agenda() {
-            Some(ag) => agenda::render(&ag, &conf),
-            None => {
-                let txt: Vec<Line> = vec![Line::from(Span::styled(
-                    "No entry for this date.",
-                    Style::default().fg(conf.agenda_entry_full_day_event),
-                ))];
-                txt
-            }
-        };
-        let agenda_par = Paragraph::new(agenda_text)
-            .block(agenda_block)
-            .alignment(Alignment::Left)
-            .wrap(Wrap { trim: true });
-        frame.render_widget(agenda_par, layout[1]);
-    })
+/// Obtain the text of the agenda entries of `date` in the `index`, with the entry at position
+/// `selected` highlighted.
+fn day_text(
+    index: &index::AgendaIndex,
+    date: &date::Date,
+    conf: &config::Config,
+    selected: Option<usize>,
+) -> Vec<Line<'static>> {
+    let day_records = index.records(date);
+    if day_records.is_empty() {
+        return vec![Line::from(Span::styled(
+            "No entry for this date.",
+            Style::default().fg(conf.agenda_entry_full_day_event),
+        ))];
+    }
+    let mut lines = agenda::render(&day_records, conf);
+    if let Some(line) = selected.and_then(|s| lines.get_mut(s)) {
+        *line = line.clone().patch_style(Modifier::REVERSED);
+    }
+    return lines;
 }
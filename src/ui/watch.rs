@@ -0,0 +1,129 @@
+// External crates
+extern crate notify;
+
+use notify::Watcher as _;
+
+/// Files that changed since the last call of `Watcher::changes`.
+#[derive(Debug, Default)]
+pub struct Changes {
+    /// Indices of the watched directories in which a file changed.
+    pub dirs: Vec<usize>,
+    /// The configuration file changed.
+    pub config: bool,
+}
+
+/// Watch the directories of the calendars and the configuration file for changes.
+///
+/// Changes are collected in the background by the watcher of the platform, e.g. inotify on
+/// Linux, and read without blocking by `changes`. Hidden files, such as the lock file and the
+/// temporary files written before they replace an agenda file, are ignored.
+pub struct Watcher {
+    _watcher: notify::RecommendedWatcher,
+    receiver: std::sync::mpsc::Receiver<notify::Result<notify::Event>>,
+    dirs: Vec<std::path::PathBuf>,
+    config: Option<std::path::PathBuf>,
+}
+
+impl Watcher {
+    /// Start watching the files in `dirs`, not their subdirectories, and the configuration file
+    /// `config`.
+    ///
+    /// The directory of the configuration file is watched, so the file is also noticed when it
+    /// is created or replaced. If that directory does not exist, the configuration is not watched.
+    pub fn new(
+        dirs: Vec<std::path::PathBuf>,
+        config: Option<std::path::PathBuf>,
+    ) -> Result<Self, String> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut watcher = match notify::recommended_watcher(sender) {
+            Ok(w) => w,
+            Err(e) => {
+                return Err(format!("Error starting the file watcher: {}", e));
+            }
+        };
+        for dir in dirs.iter() {
+            if let Err(e) = watcher.watch(dir, notify::RecursiveMode::NonRecursive) {
+                return Err(format!("Error watching {}: {}", dir.display(), e));
+            }
+        }
+        if let Some(config_dir) = config.as_ref().and_then(|c| c.parent()) {
+            if config_dir.is_dir() {
+                if let Err(e) = watcher.watch(config_dir, notify::RecursiveMode::NonRecursive) {
+                    return Err(format!("Error watching {}: {}", config_dir.display(), e));
+                }
+            }
+        }
+        return Ok(Watcher {
+            _watcher: watcher,
+            receiver,
+            dirs,
+            config,
+        });
+    }
+
+    /// Collect the changes reported since the last call, without blocking.
+    pub fn changes(&self) -> Changes {
+        let mut changes = Changes::default();
+        while let Ok(result) = self.receiver.try_recv() {
+            let event = match result {
+                Ok(e) => e,
+                Err(_) => continue,
+            };
+            // Reading a file is not a change.
+            if matches!(event.kind, notify::EventKind::Access(_)) {
+                continue;
+            }
+            for path in event.paths.iter() {
+                let hidden = path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+                if hidden {
+                    continue;
+                }
+                if self.config.as_deref() == Some(path.as_path()) {
+                    changes.config = true;
+                    continue;
+                }
+                if let Some(index) = changed_dir(&self.dirs, path) {
+                    if !changes.dirs.contains(&index) {
+                        changes.dirs.push(index);
+                    }
+                }
+            }
+        }
+        return changes;
+    }
+}
+
+/// Obtain the index of the directory in `dirs` that contains the changed file `path`.
+///
+/// Calendars are subdirectories of the data directory, which is watched as well. Changes to a
+/// watched directory itself are not changes to the files of the directory containing it, so
+/// they give `None`.
+fn changed_dir(dirs: &[std::path::PathBuf], path: &std::path::Path) -> Option<usize> {
+    if dirs.iter().any(|dir| dir.as_path() == path) {
+        return None;
+    }
+    return dirs
+        .iter()
+        .position(|dir| Some(dir.as_path()) == path.parent());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calendar_directories_are_not_changes_of_the_data_directory() {
+        let data = std::path::PathBuf::from("/data/crust");
+        let work = data.join("work");
+        let dirs = vec![data.clone(), work.clone()];
+        assert_eq!(changed_dir(&dirs, &data.join("01-02-2026.toml")), Some(0));
+        assert_eq!(changed_dir(&dirs, &work.join("01-02-2026.toml")), Some(1));
+        assert_eq!(changed_dir(&dirs, &work), None);
+        assert_eq!(
+            changed_dir(&dirs, std::path::Path::new("/tmp/x.toml")),
+            None
+        );
+    }
+}
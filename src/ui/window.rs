@@ -1,57 +1,52 @@
 // External crates
 extern crate crossterm;
 extern crate ratatui;
-extern crate termsize;
-// TODO: Find a better way to determine if the terminal is large enough for the default layout.
-// currently it is hard coded if the columns is less than 80, the restricted layout is used.
 
 // Local files
-use super::ui;
-use crate::args;
+use super::{app, ui};
 use crate::configuration::config;
+use crate::date::date;
+use crate::error::error;
 use crate::key::event;
+use crate::storage;
 
 /// Create alternate window for UI.
 ///
 /// Enter an alternate window on top of the current terminal session using `crossterm` and
-/// draw a UI in it using `Ratatui`. The possible key events are listed in the `KeyEvents` enum.
-pub fn create_window(program_args: args::parser::ProgramArguments, conf: config::Config) {
-    let prog_args_copy = program_args.clone();
-    let conf_copy = conf.clone();
-
-    let mut user_interface = {
-        let mut nosize = false;
-        let size: termsize::Size = match termsize::get() {
-            Some(s) => s,
-            None => {
-                nosize = true;
-                termsize::Size { rows: 0, cols: 0 }
-            }
-        };
-
-        if size.cols >= 80 || nosize {
-            ui::ui_pre_args(program_args, conf)
-        } else {
-            ui::ui_restricted_vertical_pre_args(program_args, conf)
-        }
-    };
+/// draw a UI in it using `Ratatui`, showing the agenda entries in the `calendars` around `date`.
+/// The possible key events are listed in the `KeyEvents` enum.
+///
+/// Every iteration of the loop applies the key pressed and the changes to the files to the
+/// `App` state, and draws the UI from that state if it changed. The directories of the calendars
+/// and the configuration file are watched while the UI is open, see `App::refresh`.
+///
+/// Returns an error if the terminal cannot be switched to or from the alternate window.
+pub fn create_window(
+    date: date::Date,
+    conf: config::Config,
+    calendars: Vec<storage::calendar::Calendar>,
+) -> Result<(), error::Error> {
+    let mut app = app::App::new(date, conf, calendars);
+    app.watch();
 
     // Enable raw mode, this disables typical user input like typing.
-    let _ = match crossterm::terminal::enable_raw_mode() {
-        Ok(v) => v,
+    match crossterm::terminal::enable_raw_mode() {
+        Ok(_) => {}
         Err(e) => {
-            eprintln!("Error enabling raw mode: {e}");
-            return;
+            return Err(error::Error::Terminal(format!(
+                "Error enabling raw mode: {e}"
+            )));
         }
     };
 
     // Switch to alternate screen on top of the terminal.
-    let _ = match crossterm::execute!(std::io::stdout(), crossterm::terminal::EnterAlternateScreen)
-    {
-        Ok(v) => v,
+    match crossterm::execute!(std::io::stdout(), crossterm::terminal::EnterAlternateScreen) {
+        Ok(_) => {}
         Err(e) => {
-            eprintln!("Error changing to alternate screen: {e}");
-            return;
+            let _ = crossterm::terminal::disable_raw_mode();
+            return Err(error::Error::Terminal(format!(
+                "Error changing to alternate screen: {e}"
+            )));
         }
     };
 
@@ -60,93 +55,58 @@ pub fn create_window(program_args: args::parser::ProgramArguments, conf: config:
         match ratatui::Terminal::new(ratatui::backend::CrosstermBackend::new(std::io::stdout())) {
             Ok(term) => term,
             Err(e) => {
-                eprintln!("Error creating Ratatui terminal: {e}");
-                return;
+                leave_window();
+                return Err(error::Error::Terminal(format!(
+                    "Error creating Ratatui terminal: {e}"
+                )));
             }
         };
 
-    // Enter UI drawing loop
-    let mut run = true;
-    let mut day_shift_counter = 0;
-    while run {
-        let _ = terminal.draw(&user_interface);
-        let key_event: event::KeyEvents = event::get_key_event(&conf_copy);
-        match key_event {
-            event::KeyEvents::Quit => {
-                run = false;
-            }
-            event::KeyEvents::Next => {
-                // Update date shift.
-                day_shift_counter += 1;
-                // Copy the program arguments and the configuration.
-                let mut prog_args_ui = prog_args_copy.clone();
-                let conf_ui = conf_copy.clone();
-                // Update the `date` stored in the program arguments.
-                prog_args_ui.date.add_days(day_shift_counter);
-                // Get the current terminal size
-                let mut nosize = false;
-                let size: termsize::Size = match termsize::get() {
-                    Some(s) => s,
-                    None => {
-                        nosize = true;
-                        termsize::Size { rows: 0, cols: 0 }
-                    }
-                };
-                // Update the UI.
-                user_interface = {
-                    if size.cols >= 80 || nosize {
-                        ui::ui_pre_args(prog_args_ui, conf_ui)
-                    } else {
-                        ui::ui_restricted_vertical_pre_args(prog_args_ui, conf_ui)
-                    }
-                };
-            }
-            event::KeyEvents::Previous => {
-                // Update date shift.
-                day_shift_counter -= 1;
-                // Copy the program arguments and configuration.
-                let mut prog_args_ui = prog_args_copy.clone();
-                let conf_ui = conf_copy.clone();
-                // Update the `date` stored in the program arguments.
-                prog_args_ui.date.add_days(day_shift_counter);
-                // Get the current terminal size
-                let mut nosize = false;
-                let size: termsize::Size = match termsize::get() {
-                    Some(s) => s,
-                    None => {
-                        nosize = true;
-                        termsize::Size { rows: 0, cols: 0 }
-                    }
-                };
-                // Update the UI.
-                user_interface = {
-                    if size.cols >= 80 || nosize {
-                        ui::ui_pre_args(prog_args_ui, conf_ui)
-                    } else {
-                        ui::ui_restricted_vertical_pre_args(prog_args_ui, conf_ui)
-                    }
-                };
-            }
-            _ => {}
+    // Return to the original terminal before the message of a panic is printed, otherwise the
+    // terminal is left in raw mode on the alternate screen. The default hook is restored when the
+    // UI is closed.
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        leave_window();
+        default_hook(info);
+    }));
+
+    // Enter UI drawing loop, the UI is only drawn again after something changed.
+    let mut redraw = true;
+    while app.running {
+        if redraw {
+            let _ = terminal.draw(|frame| ui::render(frame, &app));
         }
+        redraw = app.handle(event::read_input());
+        redraw |= app.refresh();
     }
+    let _ = std::panic::take_hook();
 
     // Disable raw mode so we return to normal terminal function.
-    let _ = match crossterm::terminal::disable_raw_mode() {
-        Ok(v) => v,
+    match crossterm::terminal::disable_raw_mode() {
+        Ok(_) => {}
         Err(e) => {
-            eprintln!("Error disabling raw mode: {e}");
-            return;
+            return Err(error::Error::Terminal(format!(
+                "Error disabling raw mode: {e}"
+            )));
         }
     };
 
     // Leave the alternate screen and return to original terminal.
-    let _ = match crossterm::execute!(std::io::stdout(), crossterm::terminal::LeaveAlternateScreen)
-    {
-        Ok(v) => v,
+    match crossterm::execute!(std::io::stdout(), crossterm::terminal::LeaveAlternateScreen) {
+        Ok(_) => {}
         Err(e) => {
-            eprintln!("Error leaving alternate screen: {e}");
-            return;
+            return Err(error::Error::Terminal(format!(
+                "Error leaving alternate screen: {e}"
+            )));
         }
     };
+    return Ok(());
+}
+
+/// Return to the original terminal after an error, ignoring further errors so the first one is
+/// reported.
+fn leave_window() {
+    let _ = crossterm::terminal::disable_raw_mode();
+    let _ = crossterm::execute!(std::io::stdout(), crossterm::terminal::LeaveAlternateScreen);
 }